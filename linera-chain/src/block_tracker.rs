@@ -114,9 +114,15 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
     {
         let chain_execution_context = self.chain_execution_context(&transaction);
         let mut txn_tracker = self.new_transaction_tracker()?;
+        self.resource_controller_mut().start_transaction();
 
         match transaction {
             Transaction::ReceiveMessages(incoming_bundle) => {
+                self.resource_controller_mut()
+                    .with_state(&mut chain.system)
+                    .await?
+                    .track_transaction_base()
+                    .with_execution_context(chain_execution_context)?;
                 self.resource_controller_mut()
                     .track_block_size_of(&incoming_bundle)
                     .with_execution_context(chain_execution_context)?;
@@ -133,6 +139,11 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
                 }
             }
             Transaction::ExecuteOperation(operation) => {
+                self.resource_controller_mut()
+                    .with_state(&mut chain.system)
+                    .await?
+                    .track_transaction_base()
+                    .with_execution_context(chain_execution_context)?;
                 self.resource_controller_mut()
                     .with_state(&mut chain.system)
                     .await?
@@ -305,7 +316,7 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
 
         for message_out in &txn_outcome.outgoing_messages {
             resource_controller
-                .track_message(&message_out.message)
+                .track_message(self.chain_id, message_out)
                 .with_execution_context(context)?;
         }
 
@@ -319,10 +330,15 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
             .with_execution_context(context)?;
 
         // Account for blobs published by this transaction directly.
+        let mut blob_deposits = Vec::new();
         for blob in &txn_outcome.blobs {
             resource_controller
                 .track_blob_published(blob)
                 .with_execution_context(context)?;
+            let deposit = resource_controller
+                .track_blob_deposit(blob)
+                .with_execution_context(context)?;
+            blob_deposits.push((blob.id(), deposit));
         }
 
         // Account for blobs published indirectly but referenced by the transaction.
@@ -331,6 +347,10 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
                 resource_controller
                     .track_blob_published(blob)
                     .with_execution_context(context)?;
+                let deposit = resource_controller
+                    .track_blob_deposit(blob)
+                    .with_execution_context(context)?;
+                blob_deposits.push((*blob_id, deposit));
             } else {
                 return Err(ChainError::InternalError(format!(
                     "Missing published blob {blob_id}"
@@ -338,6 +358,15 @@ impl<'resources, 'blobs> BlockExecutionTracker<'resources, 'blobs> {
             }
         }
 
+        // Record the deposits in the chain state, so that they can be released later.
+        for (blob_id, deposit) in blob_deposits {
+            if deposit > Amount::ZERO {
+                view.record_blob_deposit(blob_id, deposit)
+                    .await
+                    .with_execution_context(context)?;
+            }
+        }
+
         self.resource_controller
             .track_block_size_of(&(&txn_outcome.operation_result))
             .with_execution_context(context)?;
@@ -727,6 +727,7 @@ where
         confirmed_log: &LogView<C, CryptoHash>,
         previous_message_blocks_view: &MapView<C, ChainId, BlockHeight>,
         block: &ProposedBlock,
+        num_operations: u32,
         local_time: Timestamp,
         round: Option<u32>,
         published_blobs: &[Blob],
@@ -744,11 +745,14 @@ where
             .policy()
             .clone();
 
-        let mut resource_controller = ResourceController::new(
-            Arc::new(policy),
-            ResourceTracker::default(),
-            block.authenticated_signer,
-        );
+        // The warmup window spans the chain's first operations, not those of each block.
+        // These are counted by the chain tip, so no separate counter is kept.
+        let tracker = ResourceTracker {
+            warmup_used: num_operations.min(policy.warmup_operations),
+            ..ResourceTracker::default()
+        };
+        let mut resource_controller =
+            ResourceController::new(Arc::new(policy), tracker, block.authenticated_signer);
 
         for blob in published_blobs {
             let blob_id = blob.id();
@@ -778,6 +782,11 @@ where
                 .await?;
         }
 
+        block_execution_tracker
+            .resource_controller_mut()
+            .finalize()
+            .with_execution_context(ChainExecutionContext::Block)?;
+
         let recipients = block_execution_tracker.recipients();
         let mut previous_message_blocks = BTreeMap::new();
         for recipient in recipients {
@@ -868,6 +877,7 @@ where
             &self.confirmed_log,
             &self.previous_message_blocks,
             block,
+            self.tip_state.get().num_operations,
             local_time,
             round,
             published_blobs,
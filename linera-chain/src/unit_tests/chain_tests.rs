@@ -239,6 +239,123 @@ async fn test_block_size_limit() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_warmup_operations_span_blocks() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let config = env.make_open_chain_config();
+    let chain_desc = env.make_child_chain_description_with_config(3, config);
+    let chain_id = chain_desc.id();
+    let owner = chain_desc
+        .config()
+        .ownership
+        .all_owners()
+        .next()
+        .copied()
+        .unwrap();
+
+    let mut chain = ChainStateView::new(chain_id).await;
+    let policy = ResourceControlPolicy {
+        operation: Amount::ONE,
+        warmup_operations: 1,
+        ..ResourceControlPolicy::default()
+    };
+    chain
+        .context()
+        .extra()
+        .add_blobs([committee_blob(policy)])
+        .await?;
+    chain
+        .context()
+        .extra()
+        .add_blobs(env.description_blobs())
+        .await?;
+    chain.ensure_is_active(time).await?;
+
+    let block = make_first_block(chain_id)
+        .with_authenticated_signer(Some(owner))
+        .with_operation(SystemOperation::Transfer {
+            owner: AccountOwner::CHAIN,
+            recipient: Recipient::chain(env.admin_id()),
+            amount: Amount::ONE,
+        });
+
+    // The first operation of the chain is free...
+    chain.execute_block(&block, time, None, &[], None).await?;
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(9)
+    );
+
+    // ...but the warmup window does not start over in the next block, once the chain tip
+    // counts the first block's operation.
+    chain.tip_state.get_mut().num_operations = 1;
+    chain.execute_block(&block, time, None, &[], None).await?;
+    assert_eq!(
+        *chain.execution_state.system.balance.get(),
+        Amount::from_tokens(7)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blob_deposits_are_recorded() -> anyhow::Result<()> {
+    let mut env = TestEnvironment::new();
+    let time = Timestamp::from(0);
+    let config = env.make_open_chain_config();
+    let chain_desc = env.make_child_chain_description_with_config(3, config);
+    let chain_id = chain_desc.id();
+    let owner = chain_desc
+        .config()
+        .ownership
+        .all_owners()
+        .next()
+        .copied()
+        .unwrap();
+
+    let mut chain = ChainStateView::new(chain_id).await;
+    let policy = ResourceControlPolicy {
+        blob_byte_deposit: Amount::from_attos(2),
+        ..ResourceControlPolicy::default()
+    };
+    chain
+        .context()
+        .extra()
+        .add_blobs([committee_blob(policy)])
+        .await?;
+    chain
+        .context()
+        .extra()
+        .add_blobs(env.description_blobs())
+        .await?;
+    chain.ensure_is_active(time).await?;
+
+    let blob = Blob::new_data(b"hello".to_vec());
+    let block = make_first_block(chain_id)
+        .with_authenticated_signer(Some(owner))
+        .with_operation(SystemOperation::PublishDataBlob {
+            blob_hash: blob.id().hash,
+        });
+    chain
+        .execute_block(&block, time, None, &[blob.clone()], None)
+        .await?;
+
+    // The deposit is kept in the chain state, to be released when the blob is removed.
+    let system = &mut chain.execution_state.system;
+    assert_eq!(
+        system.blob_deposits.get(&blob.id()).await?,
+        Some(Amount::from_attos(10))
+    );
+    assert_eq!(
+        system.take_blob_deposit(&blob.id()).await?,
+        Some(Amount::from_attos(10))
+    );
+    assert_eq!(system.take_blob_deposit(&blob.id()).await?, None);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_application_permissions() -> anyhow::Result<()> {
     let mut env = TestEnvironment::new();
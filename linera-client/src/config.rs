@@ -15,7 +15,7 @@ use linera_base::{
 };
 use linera_execution::{
     committee::{Committee, ValidatorState},
-    ResourceControlPolicy,
+    PolicyError, ResourceControlPolicy,
 };
 use linera_persistent as persistent;
 use linera_rpc::config::{
@@ -36,6 +36,8 @@ pub enum Error {
     StorageIsAlreadyInitialized(Box<NetworkDescription>),
     #[error("no admin chain configured")]
     NoAdminChain,
+    #[error("invalid resource control policy: {0}")]
+    InvalidPolicy(#[from] PolicyError),
 }
 
 use crate::util;
@@ -173,6 +175,7 @@ impl GenesisConfig {
         {
             return Err(Error::StorageIsAlreadyInitialized(Box::new(description)));
         }
+        self.committee.policy().validate()?;
         let network_description = self.network_description();
         storage
             .write_blob(&self.committee_blob())
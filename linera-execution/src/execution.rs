@@ -221,11 +221,14 @@ where
             .with_state_and_grant(&mut self.system, cloned_grant.as_mut())
             .await?
             .balance()?;
-        let controller = ResourceController::new(
-            resource_controller.policy().clone(),
-            resource_controller.tracker,
-            initial_balance,
-        );
+        // What the action spends counts toward the fee cap when its balance is merged
+        // below, so the runtime only gets what is left of the cap.
+        let fee_cap = resource_controller.tracker.fee_cap;
+        let capped_fees = resource_controller.tracker.capped_fees;
+        let mut tracker = resource_controller.tracker.clone();
+        tracker.fee_cap = fee_cap.map(|cap| cap.saturating_sub(capped_fees));
+        tracker.capped_fees = Amount::ZERO;
+        let controller = resource_controller.with_tracker_and_account(tracker, initial_balance);
         let (execution_state_sender, mut execution_state_receiver) =
             futures::channel::mpsc::unbounded();
         let (code, description) = self.load_contract(application_id, txn_tracker).await?;
@@ -254,16 +257,22 @@ where
             self.handle_request(request, resource_controller).await?;
         }
 
-        let (result, controller, txn_tracker_moved) = contract_runtime_task.join().await?;
+        let (result, mut controller, txn_tracker_moved) = contract_runtime_task.join().await?;
+        // Charge the bytes read that were deferred, while this action's payer is known.
+        controller.flush_pending_charges()?;
 
         *txn_tracker = txn_tracker_moved;
         txn_tracker.add_operation_result(result);
 
+        // Copy the tracker first, so that it records the amount transferred by the merge.
+        let final_balance = controller.balance()?;
+        resource_controller.tracker = controller.tracker;
+        resource_controller.tracker.fee_cap = fee_cap;
+        resource_controller.tracker.capped_fees = capped_fees;
         resource_controller
             .with_state_and_grant(&mut self.system, grant)
             .await?
-            .merge_balance(initial_balance, controller.balance()?)?;
-        resource_controller.tracker = controller.tracker;
+            .merge_balance(initial_balance, final_balance)?;
 
         Ok(())
     }
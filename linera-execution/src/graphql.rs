@@ -15,11 +15,15 @@ use linera_views::{context::Context, map_view::MapView};
 use crate::{
     committee::{Committee, ValidatorState},
     system::{Recipient, UserData},
-    ExecutionStateView, SystemExecutionStateView,
+    ExecutionStateView, ResourceKind, SystemExecutionStateView,
 };
 
 doc_scalar!(Recipient, "The recipient of a transfer");
 doc_scalar!(UserData, "Optional user message attached to a transfer");
+doc_scalar!(
+    ResourceKind,
+    "A kind of resource tracked by a resource controller"
+);
 
 #[async_graphql::Object(cache_control(no_cache))]
 impl Committee {
@@ -68,8 +68,17 @@ pub use crate::{
     committee::Committee,
     execution::{ExecutionStateView, ServiceRuntimeEndpoint},
     execution_state_actor::ExecutionRequest,
-    policy::ResourceControlPolicy,
-    resources::{BalanceHolder, ResourceController, ResourceTracker},
+    policy::{
+        BlockCostPlan, OracleTimeoutPolicy, PolicyError, PolicyFieldChange, ResourceControlPolicy,
+        StorageRefundTarget, TransactionHints,
+    },
+    resources::{
+        message_size, operation_size, BalanceHolder, BlockComponent, Charge, ChargeKind,
+        ChargeTimes, ControllerSnapshot, CustomResource, CustomResourceId, FuelReservation,
+        LogRecord, RefundTarget, RemainingFuel, ResourceController, ResourceEvent, ResourceKind,
+        ResourceTracker, SpendingHeadroom, TransactionReceipt, Utilization, WriteEstimate,
+        MAX_ORACLE_LATENCY_SAMPLES,
+    },
     runtime::{
         ContractSyncRuntimeHandle, ServiceRuntimeRequest, ServiceSyncRuntime,
         ServiceSyncRuntimeHandle,
@@ -232,10 +241,24 @@ pub enum ExecutionError {
     ExcessiveRead,
     #[error("Excessive number of bytes written to storage")]
     ExcessiveWrite,
+    #[error("Excessive number of bytes deserialized")]
+    ExcessiveDeserialization,
+    #[error("Excessive number of blob bytes read")]
+    ExcessiveBlobBytesRead,
+    #[error("Excessive number of blob bytes published")]
+    ExcessiveBlobBytesPublished,
+    #[error("Excessive number of bytes read from the runtime")]
+    ExcessiveRuntimeReads,
+    #[error("Excessive number of bytes in the bodies of HTTP requests")]
+    ExcessiveHttpRequestBytes,
+    #[error("Too many charges were rejected for a lack of funds")]
+    TooManyFailedChargeAttempts,
     #[error("Block execution required too much fuel for VM {0}")]
     MaximumFuelExceeded(VmRuntime),
     #[error("Services running as oracles in block took longer than allowed")]
     MaximumServiceOracleExecutionTimeExceeded,
+    #[error("Block execution did not finish before its deadline")]
+    BlockExecutionTimedOut,
     #[error("Service running as an oracle produced a response that's too large")]
     ServiceOracleResponseTooLarge,
     #[error("Serialized size of the block exceeds limit")]
@@ -270,6 +293,8 @@ pub enum ExecutionError {
     StreamNameTooLong,
     #[error("Blob exceeds size limit")]
     BlobTooLarge,
+    #[error("Too many published blobs: the maximum is {0}")]
+    TooManyPublishedBlobs(u64),
     #[error("Bytecode exceeds size limit")]
     BytecodeTooLarge,
     #[error("Attempt to perform an HTTP request to an unauthorized host: {0:?}")]
@@ -309,6 +334,31 @@ pub enum ExecutionError {
     },
     #[error("Required execution fees exceeded the total funding available. Fees {fees}, available balance: {balance}")]
     FeesExceedFunding { fees: Amount, balance: Amount },
+    #[error(
+        "Insufficient funds to pay for {fuel} units of fuel: fees {needed}, available balance: \
+        {balance}"
+    )]
+    InsufficientFundsForFuel {
+        fuel: u64,
+        needed: Amount,
+        balance: Amount,
+    },
+    #[error("Fees of {needed} for this operation exceed its fee cap of {cap}")]
+    FeeCapExceeded { cap: Amount, needed: Amount },
+    #[error("Refunds exceed the fees charged")]
+    RefundExceedsCharges,
+    #[error("Credits of {credited} exceed the fees charged of {debited}")]
+    NegativeNetFees { debited: Amount, credited: Amount },
+    #[error("At least one funding source is required")]
+    NoFundingSources,
+    #[error("Maximum depth of nested application calls exceeded")]
+    CallDepthExceeded,
+    #[error("Invalid compact encoding of resource usage")]
+    InvalidCompactResourceTracker,
+    #[error("Unknown custom resource {0:?}")]
+    UnknownCustomResource(CustomResourceId),
+    #[error("Maximum usage of custom resource {0:?} exceeded")]
+    MaximumCustomResourceExceeded(String),
     #[error("Claim must have positive amount")]
     IncorrectClaimAmount,
     #[error("Claim must be authenticated by the right signer")]
@@ -319,6 +369,8 @@ pub enum ExecutionError {
     InvalidCommitteeEpoch { expected: Epoch, provided: Epoch },
     #[error("Failed to remove committee")]
     InvalidCommitteeRemoval,
+    #[error("Failed to create new committee: {0}")]
+    InvalidCommitteePolicy(#[from] PolicyError),
     #[error("Amount overflow")]
     AmountOverflow,
     #[error("Amount underflow")]
@@ -842,6 +894,75 @@ pub enum Operation {
 
 impl BcsHashable<'_> for Operation {}
 
+/// The type of an [`Operation`], without its arguments.
+#[derive(
+    Clone, Copy, Debug, Display, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum OperationDiscriminant {
+    /// A user operation, of any application.
+    User,
+    /// [`SystemOperation::Transfer`].
+    Transfer,
+    /// [`SystemOperation::Claim`].
+    Claim,
+    /// [`SystemOperation::OpenChain`].
+    OpenChain,
+    /// [`SystemOperation::CloseChain`].
+    CloseChain,
+    /// [`SystemOperation::ChangeOwnership`].
+    ChangeOwnership,
+    /// [`SystemOperation::ChangeApplicationPermissions`].
+    ChangeApplicationPermissions,
+    /// [`SystemOperation::PublishModule`].
+    PublishModule,
+    /// [`SystemOperation::PublishDataBlob`].
+    PublishDataBlob,
+    /// [`SystemOperation::ReadBlob`].
+    ReadBlob,
+    /// [`SystemOperation::CreateApplication`].
+    CreateApplication,
+    /// [`SystemOperation::Admin`].
+    Admin,
+    /// [`SystemOperation::ProcessNewEpoch`].
+    ProcessNewEpoch,
+    /// [`SystemOperation::ProcessRemovedEpoch`].
+    ProcessRemovedEpoch,
+    /// [`SystemOperation::UpdateStreams`].
+    UpdateStreams,
+}
+
+impl FromStr for OperationDiscriminant {
+    type Err = InvalidOperationDiscriminant;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "User" => Ok(OperationDiscriminant::User),
+            "Transfer" => Ok(OperationDiscriminant::Transfer),
+            "Claim" => Ok(OperationDiscriminant::Claim),
+            "OpenChain" => Ok(OperationDiscriminant::OpenChain),
+            "CloseChain" => Ok(OperationDiscriminant::CloseChain),
+            "ChangeOwnership" => Ok(OperationDiscriminant::ChangeOwnership),
+            "ChangeApplicationPermissions" => {
+                Ok(OperationDiscriminant::ChangeApplicationPermissions)
+            }
+            "PublishModule" => Ok(OperationDiscriminant::PublishModule),
+            "PublishDataBlob" => Ok(OperationDiscriminant::PublishDataBlob),
+            "ReadBlob" => Ok(OperationDiscriminant::ReadBlob),
+            "CreateApplication" => Ok(OperationDiscriminant::CreateApplication),
+            "Admin" => Ok(OperationDiscriminant::Admin),
+            "ProcessNewEpoch" => Ok(OperationDiscriminant::ProcessNewEpoch),
+            "ProcessRemovedEpoch" => Ok(OperationDiscriminant::ProcessRemovedEpoch),
+            "UpdateStreams" => Ok(OperationDiscriminant::UpdateStreams),
+            unknown => Err(InvalidOperationDiscriminant(unknown.to_owned())),
+        }
+    }
+}
+
+/// Attempts to parse an invalid [`OperationDiscriminant`] from a string.
+#[derive(Clone, Debug, Error)]
+#[error("{0:?} is not a valid operation type")]
+pub struct InvalidOperationDiscriminant(String);
+
 /// A message to be sent and possibly executed in the receiver's block.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum Message {
@@ -1154,6 +1275,32 @@ impl Operation {
         Operation::System(Box::new(operation))
     }
 
+    /// Returns the type of this operation, which is stable across its arguments.
+    pub fn discriminant(&self) -> OperationDiscriminant {
+        let operation = match self {
+            Operation::User { .. } => return OperationDiscriminant::User,
+            Operation::System(operation) => operation,
+        };
+        match **operation {
+            SystemOperation::Transfer { .. } => OperationDiscriminant::Transfer,
+            SystemOperation::Claim { .. } => OperationDiscriminant::Claim,
+            SystemOperation::OpenChain(_) => OperationDiscriminant::OpenChain,
+            SystemOperation::CloseChain => OperationDiscriminant::CloseChain,
+            SystemOperation::ChangeOwnership { .. } => OperationDiscriminant::ChangeOwnership,
+            SystemOperation::ChangeApplicationPermissions(_) => {
+                OperationDiscriminant::ChangeApplicationPermissions
+            }
+            SystemOperation::PublishModule { .. } => OperationDiscriminant::PublishModule,
+            SystemOperation::PublishDataBlob { .. } => OperationDiscriminant::PublishDataBlob,
+            SystemOperation::ReadBlob { .. } => OperationDiscriminant::ReadBlob,
+            SystemOperation::CreateApplication { .. } => OperationDiscriminant::CreateApplication,
+            SystemOperation::Admin(_) => OperationDiscriminant::Admin,
+            SystemOperation::ProcessNewEpoch(_) => OperationDiscriminant::ProcessNewEpoch,
+            SystemOperation::ProcessRemovedEpoch(_) => OperationDiscriminant::ProcessRemovedEpoch,
+            SystemOperation::UpdateStreams(_) => OperationDiscriminant::UpdateStreams,
+        }
+    }
+
     /// Creates a new user application operation following the `application_id`'s [`Abi`].
     #[cfg(with_testing)]
     pub fn user<A: Abi>(
@@ -9,18 +9,27 @@
 //! It also sets overarching limits such as the maximum fuel allowed per block,
 //! the maximum block size, and limits on concurrent operations.
 
-use std::{collections::BTreeSet, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    time::Duration,
+};
 
-use async_graphql::InputObject;
+use async_graphql::{scalar, InputObject};
 use linera_base::{
-    data_types::{Amount, ArithmeticError, BlobContent, CompressedBytecode, Resources},
+    data_types::{Amount, ArithmeticError, Blob, BlobContent, CompressedBytecode, Resources},
     ensure,
-    identifiers::BlobType,
+    identifiers::{AccountOwner, BlobType},
     vm::VmRuntime,
 };
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{resources::fuel_kind, ExecutionError, OperationDiscriminant, ResourceKind};
 
-use crate::ExecutionError;
+#[cfg(test)]
+#[path = "unit_tests/policy_tests.rs"]
+mod tests;
 
 /// A collection of prices and limits associated with block execution.
 #[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize, InputObject)]
@@ -37,31 +46,79 @@ pub struct ResourceControlPolicy {
     pub byte_runtime: Amount,
     /// The price of reading a byte.
     pub byte_read: Amount,
+    /// The number of bytes read from storage that are charged together. Smaller reads are
+    /// buffered, and the remainder is charged at the end of each operation or message. Zero
+    /// charges every read immediately.
+    pub read_charge_granularity: u64,
     /// The price of writing a byte
     pub byte_written: Amount,
+    /// The price of deserializing a byte into typed values, in addition to reading it.
+    pub byte_deserialized: Amount,
     /// The base price to read a blob.
     pub blob_read: Amount,
+    /// The fixed price charged for each blob read, e.g. for its lookup and verification, in
+    /// addition to the base price of the read.
+    pub blob_read_fixed: Amount,
     /// The base price to publish a blob.
     pub blob_published: Amount,
+    /// The fixed price charged for each blob published, regardless of its type and size.
+    pub blob_published_fixed: Amount,
     /// The price to read a blob, per byte.
     pub blob_byte_read: Amount,
     /// The price to publish a blob, per byte.
     pub blob_byte_published: Amount,
+    /// The refundable deposit locked for each byte of a published blob, released when the blob
+    /// is removed.
+    pub blob_byte_deposit: Amount,
+    /// The multiplier applied to the price of publishing a bytecode blob.
+    pub blob_bytecode_multiplier: u64,
+    /// The multiplier applied to the price of publishing an application or chain description
+    /// blob.
+    pub blob_description_multiplier: u64,
     /// The price of increasing storage by a byte.
     // TODO(#1536): This is not fully supported.
     pub byte_stored: Amount,
+    /// The rent for keeping one byte stored for one second.
+    pub storage_rent: Amount,
+    /// Where the refunds for freed storage go.
+    pub storage_refund_target: StorageRefundTarget,
     /// The base price of adding an operation to a block.
     pub operation: Amount,
+    /// Prices that replace the base price of an operation for some types of operations.
+    pub operation_price_overrides: BTreeMap<OperationDiscriminant, Amount>,
+    /// The flat fee charged once per transaction, regardless of the resources it uses.
+    pub base_transaction_fee: Amount,
+    /// If not zero, every fee is rounded up to a multiple of this amount, to avoid dust.
+    pub fee_increment: Amount,
     /// The additional price for each byte in the argument of a user operation.
     pub operation_byte: Amount,
+    /// Whether the bytes of an operation are counted from its whole BCS serialization, instead
+    /// of only the argument of user operations.
+    pub price_operations_by_serialized_size: bool,
+    /// The minimum total fee for an operation, including the price of its bytes.
+    pub minimum_operation_fee: Amount,
+    /// The number of operations on a new chain that are not charged any fees.
+    pub warmup_operations: u32,
     /// The base price of sending a message from a block.
     pub message: Amount,
     /// The additional price for each byte in the argument of a user message.
     pub message_byte: Amount,
+    /// The additional price for each chain an outgoing message travels to. Messages sent to
+    /// the sending chain itself make no hops.
+    pub message_hop: Amount,
+    /// The denomination of message grants passed to `ResourceController::track_grant` and
+    /// `ResourceController::track_grant_for`, if it is not the native token.
+    pub grant_denomination: Option<String>,
+    /// The value in native tokens of one token of the grant denomination.
+    pub grant_exchange_rate: Amount,
     /// The price per query to a service as an oracle.
     pub service_as_oracle_query: Amount,
     /// The price for a performing an HTTP request.
     pub http_request: Amount,
+    /// The additional price for each byte in the body of an HTTP response.
+    pub http_response_byte: Amount,
+    /// The price per byte in the body of an HTTP request.
+    pub http_request_byte: Amount,
 
     // TODO(#1538): Cap the number of transactions per block and the total size of their
     // arguments.
@@ -71,6 +128,8 @@ pub struct ResourceControlPolicy {
     pub maximum_evm_fuel_per_block: u64,
     /// The maximum time in milliseconds that a block can spend executing services as oracles.
     pub maximum_service_oracle_execution_ms: u64,
+    /// What to do when services executed as oracles exceed their execution time limits.
+    pub oracle_timeout_policy: OracleTimeoutPolicy,
     /// The maximum size of a block. This includes the block proposal itself as well as
     /// the execution outcome.
     pub maximum_block_size: u64,
@@ -86,14 +145,137 @@ pub struct ResourceControlPolicy {
     pub maximum_bytes_read_per_block: u64,
     /// The maximum data to write per block
     pub maximum_bytes_written_per_block: u64,
+    /// The maximum data to deserialize per block
+    pub maximum_bytes_deserialized_per_block: u64,
+    /// The maximum blob data to read per block
+    pub maximum_blob_bytes_read_per_block: u64,
+    /// The maximum blob data to publish per block
+    pub maximum_blob_bytes_published_per_block: u64,
+    /// The maximum data to read from the runtime per block
+    pub maximum_bytes_runtime_per_block: u64,
     /// The maximum size in bytes of an oracle response.
     pub maximum_oracle_response_bytes: u64,
     /// The maximum size in bytes of a received HTTP response.
     pub maximum_http_response_bytes: u64,
+    /// The maximum number of bytes in the bodies of HTTP requests per block.
+    pub maximum_http_request_bytes_per_block: u64,
+    /// The maximum number of charges per block that may be rejected for a lack of funds in
+    /// best-effort modes, before execution is aborted.
+    pub maximum_failed_charge_attempts: u64,
+    /// The maximum depth of nested application calls.
+    pub maximum_call_depth: u64,
     /// The maximum amount of time allowed to wait for an HTTP response.
     pub http_request_timeout_ms: u64,
     /// The list of hosts that contracts and services can send HTTP requests to.
     pub http_request_allow_list: BTreeSet<String>,
+    /// The owners whose transactions are not charged any fees. Their resource usage is still
+    /// tracked and limited.
+    pub fee_exempt_owners: BTreeSet<AccountOwner>,
+    /// The resources whose usage is still tracked and limited, but not charged, regardless
+    /// of their prices.
+    pub uncharged_resources: BTreeSet<ResourceKind>,
+}
+
+/// What to do when services executed as oracles exceed their execution time limits.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OracleTimeoutPolicy {
+    /// Fail with an error.
+    #[default]
+    Abort,
+    /// Only record the time up to the limit, and continue with the result of the oracle.
+    Truncate,
+}
+
+scalar!(OracleTimeoutPolicy);
+
+/// Where the refunds for freed storage go.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageRefundTarget {
+    /// Credit the account of the owner, if there is one, and otherwise the chain.
+    #[default]
+    OwnerAccount,
+    /// Credit the chain account, or the grant if one is being used.
+    ChainAccount,
+    /// Do not credit anyone: the refund is burned.
+    Burn,
+}
+
+scalar!(StorageRefundTarget);
+
+/// The counts and sizes of the contents of a prospective block, used to estimate its cost
+/// with [`ResourceControlPolicy::project_block_cost`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BlockCostPlan {
+    /// The number of operations.
+    pub operations: u32,
+    /// The total size of the arguments of user operations.
+    pub operation_bytes: u64,
+    /// The number of outgoing messages.
+    pub messages: u32,
+    /// The total size of the arguments of outgoing user messages.
+    pub message_bytes: u64,
+    /// The number of blobs read.
+    pub blobs_read: u32,
+    /// The total size of the blobs read.
+    pub blob_bytes_read: u64,
+    /// The number of blobs published.
+    pub blobs_published: u32,
+    /// The total size of the blobs published.
+    pub blob_bytes_published: u64,
+    /// The estimated Wasm fuel.
+    pub wasm_fuel: u64,
+    /// The estimated EVM fuel.
+    pub evm_fuel: u64,
+}
+
+/// The sizes declared by a transaction before its execution, checked against the limits of
+/// a policy with [`ResourceControlPolicy::validate_transaction_hints`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionHints {
+    /// The serialized size of the transaction.
+    pub size: u64,
+    /// The total size of the blobs read.
+    pub blob_bytes_read: u64,
+    /// The size of each blob published.
+    pub published_blob_sizes: Vec<u64>,
+}
+
+/// A difference between two policies, found by [`ResourceControlPolicy::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyFieldChange {
+    /// A per-unit price changed.
+    Price {
+        field: &'static str,
+        old: Amount,
+        new: Amount,
+    },
+    /// A numeric limit changed.
+    Limit {
+        field: &'static str,
+        old: u64,
+        new: u64,
+    },
+    /// Another setting changed, e.g. a multiplier or the exempt owners. The values are
+    /// formatted for display.
+    Setting {
+        field: &'static str,
+        old: String,
+        new: String,
+    },
+}
+
+/// An inconsistency found by [`ResourceControlPolicy::validate`].
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("The limit {0} must not be zero")]
+    ZeroLimit(&'static str),
+    #[error("The price {price} times the limit {limit} overflows")]
+    PriceOverflow {
+        price: &'static str,
+        limit: &'static str,
+    },
+    #[error("The price {0} times the largest number of items a block can count overflows")]
+    CountPriceOverflow(&'static str),
 }
 
 impl fmt::Display for ResourceControlPolicy {
@@ -105,21 +287,42 @@ impl fmt::Display for ResourceControlPolicy {
             write_operation,
             byte_runtime,
             byte_read,
+            read_charge_granularity,
             byte_written,
+            byte_deserialized,
             blob_read,
+            blob_read_fixed,
             blob_published,
+            blob_published_fixed,
             blob_byte_read,
             blob_byte_published,
+            blob_byte_deposit,
+            blob_bytecode_multiplier,
+            blob_description_multiplier,
             byte_stored,
+            storage_rent,
+            storage_refund_target,
             operation,
+            operation_price_overrides,
+            base_transaction_fee,
+            fee_increment,
             operation_byte,
+            price_operations_by_serialized_size,
+            minimum_operation_fee,
+            warmup_operations,
             message,
             message_byte,
+            message_hop,
+            grant_denomination,
+            grant_exchange_rate,
             service_as_oracle_query,
             http_request,
+            http_response_byte,
+            http_request_byte,
             maximum_wasm_fuel_per_block,
             maximum_evm_fuel_per_block,
             maximum_service_oracle_execution_ms,
+            oracle_timeout_policy,
             maximum_block_size,
             maximum_blob_size,
             maximum_published_blobs,
@@ -127,11 +330,21 @@ impl fmt::Display for ResourceControlPolicy {
             maximum_block_proposal_size,
             maximum_bytes_read_per_block,
             maximum_bytes_written_per_block,
+            maximum_bytes_deserialized_per_block,
+            maximum_blob_bytes_read_per_block,
+            maximum_blob_bytes_published_per_block,
+            maximum_bytes_runtime_per_block,
             maximum_oracle_response_bytes,
             maximum_http_response_bytes,
+            maximum_http_request_bytes_per_block,
+            maximum_failed_charge_attempts,
+            maximum_call_depth,
             http_request_allow_list,
+            fee_exempt_owners,
+            uncharged_resources,
             http_request_timeout_ms,
         } = self;
+        let grant_denomination = grant_denomination.as_deref().unwrap_or("native token");
         write!(
             f,
             "Resource control policy:\n\
@@ -141,22 +354,43 @@ impl fmt::Display for ResourceControlPolicy {
             {write_operation:.2} cost per write operation\n\
             {byte_runtime:.2} cost per runtime byte read operation\n\
             {byte_read:.2} cost per byte read\n\
+            {read_charge_granularity} bytes read charged together\n\
             {byte_written:.2} cost per byte written\n\
+            {byte_deserialized:.2} cost per byte deserialized\n\
             {blob_read:.2} base cost per read blob\n\
+            {blob_read_fixed:.2} fixed cost per read blob\n\
             {blob_published:.2} base cost per published blob\n\
+            {blob_published_fixed:.2} fixed cost per published blob\n\
             {blob_byte_read:.2} cost of reading blobs, per byte\n\
             {blob_byte_published:.2} cost of publishing blobs, per byte\n\
+            {blob_byte_deposit:.2} deposit per byte of a published blob\n\
+            {blob_bytecode_multiplier} multiplier of the cost of publishing bytecode blobs\n\
+            {blob_description_multiplier} multiplier of the cost of publishing description blobs\n\
             {byte_stored:.2} cost per byte stored\n\
+            {storage_rent:.2} rent per byte stored per second\n\
+            {storage_refund_target:?} recipient of the refunds for freed storage\n\
             {operation:.2} per operation\n\
+            {operation_price_overrides:?} base price overrides per type of operation\n\
+            {base_transaction_fee:.2} base fee per transaction\n\
+            {fee_increment:.2} increment that fees are rounded up to\n\
             {operation_byte:.2} per byte in the argument of an operation\n\
+            {price_operations_by_serialized_size} operations priced by serialized size\n\
+            {minimum_operation_fee:.2} minimum fee per operation\n\
+            {warmup_operations} fee-free operations on a new chain\n\
             {service_as_oracle_query:.2} per query to a service as an oracle\n\
             {message:.2} per outgoing messages\n\
             {message_byte:.2} per byte in the argument of an outgoing messages\n\
+            {message_hop:.2} cost per hop of an outgoing message\n\
+            {grant_denomination} denomination of message grants\n\
+            {grant_exchange_rate:.2} native tokens per token of the grant denomination\n\
             {http_request:.2} per HTTP request performed\n\
+            {http_response_byte:.2} per byte in the body of an HTTP response\n\
+            {http_request_byte:.2} per byte in the body of an HTTP request\n\
             {maximum_wasm_fuel_per_block} maximum Wasm fuel per block\n\
             {maximum_evm_fuel_per_block} maximum EVM fuel per block\n\
             {maximum_service_oracle_execution_ms} ms maximum service-as-oracle execution time per \
                 block\n\
+            {oracle_timeout_policy:?} behavior when an oracle execution time limit is exceeded\n\
             {maximum_block_size} maximum size of a block\n\
             {maximum_blob_size} maximum size of a data blob, bytecode or other binary blob\n\
             {maximum_published_blobs} maximum number of blobs published per block\n\
@@ -164,10 +398,21 @@ impl fmt::Display for ResourceControlPolicy {
             {maximum_block_proposal_size} maximum size of a block proposal\n\
             {maximum_bytes_read_per_block} maximum number of bytes read per block\n\
             {maximum_bytes_written_per_block} maximum number of bytes written per block\n\
+            {maximum_bytes_deserialized_per_block} maximum number of bytes deserialized per block\n\
+            {maximum_blob_bytes_read_per_block} maximum number of blob bytes read per block\n\
+            {maximum_blob_bytes_published_per_block} maximum number of blob bytes published per block\n\
+            {maximum_bytes_runtime_per_block} maximum number of bytes read from the runtime per block\n\
             {maximum_oracle_response_bytes} maximum number of bytes of an oracle response\n\
             {maximum_http_response_bytes} maximum number of bytes of an HTTP response\n\
+            {maximum_http_request_bytes_per_block} maximum number of bytes in the bodies of HTTP \
+                requests per block\n\
+            {maximum_failed_charge_attempts} maximum number of charges rejected for a lack of \
+                funds per block\n\
+            {maximum_call_depth} maximum depth of nested application calls\n\
             {http_request_timeout_ms} ms timeout for HTTP requests\n\
-            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n",
+            HTTP hosts allowed for contracts and services: {http_request_allow_list:#?}\n\
+            Owners exempt from fees: {fee_exempt_owners:#?}\n\
+            Resources that are not charged: {uncharged_resources:#?}\n",
         )?;
         Ok(())
     }
@@ -184,28 +429,49 @@ impl ResourceControlPolicy {
     ///
     /// This can be used in tests or benchmarks.
     pub fn no_fees() -> Self {
-        Self {
+        let policy = Self {
             wasm_fuel_unit: Amount::ZERO,
             evm_fuel_unit: Amount::ZERO,
             read_operation: Amount::ZERO,
             write_operation: Amount::ZERO,
             byte_runtime: Amount::ZERO,
             byte_read: Amount::ZERO,
+            read_charge_granularity: 0,
             byte_written: Amount::ZERO,
+            byte_deserialized: Amount::ZERO,
             blob_read: Amount::ZERO,
+            blob_read_fixed: Amount::ZERO,
             blob_published: Amount::ZERO,
+            blob_published_fixed: Amount::ZERO,
             blob_byte_read: Amount::ZERO,
             blob_byte_published: Amount::ZERO,
+            blob_byte_deposit: Amount::ZERO,
+            blob_bytecode_multiplier: 1,
+            blob_description_multiplier: 1,
             byte_stored: Amount::ZERO,
+            storage_rent: Amount::ZERO,
+            storage_refund_target: StorageRefundTarget::OwnerAccount,
             operation: Amount::ZERO,
+            operation_price_overrides: BTreeMap::new(),
+            base_transaction_fee: Amount::ZERO,
+            fee_increment: Amount::ZERO,
             operation_byte: Amount::ZERO,
+            price_operations_by_serialized_size: false,
+            minimum_operation_fee: Amount::ZERO,
+            warmup_operations: 0,
             message: Amount::ZERO,
             message_byte: Amount::ZERO,
+            message_hop: Amount::ZERO,
+            grant_denomination: None,
+            grant_exchange_rate: Amount::ONE,
             service_as_oracle_query: Amount::ZERO,
             http_request: Amount::ZERO,
+            http_response_byte: Amount::ZERO,
+            http_request_byte: Amount::ZERO,
             maximum_wasm_fuel_per_block: u64::MAX,
             maximum_evm_fuel_per_block: u64::MAX,
             maximum_service_oracle_execution_ms: u64::MAX,
+            oracle_timeout_policy: OracleTimeoutPolicy::Abort,
             maximum_block_size: u64::MAX,
             maximum_blob_size: u64::MAX,
             maximum_published_blobs: u64::MAX,
@@ -213,11 +479,22 @@ impl ResourceControlPolicy {
             maximum_block_proposal_size: u64::MAX,
             maximum_bytes_read_per_block: u64::MAX,
             maximum_bytes_written_per_block: u64::MAX,
+            maximum_bytes_deserialized_per_block: u64::MAX,
+            maximum_blob_bytes_read_per_block: u64::MAX,
+            maximum_blob_bytes_published_per_block: u64::MAX,
+            maximum_bytes_runtime_per_block: u64::MAX,
             maximum_oracle_response_bytes: u64::MAX,
             maximum_http_response_bytes: u64::MAX,
+            maximum_http_request_bytes_per_block: u64::MAX,
+            maximum_failed_charge_attempts: u64::MAX,
+            maximum_call_depth: u64::MAX,
             http_request_timeout_ms: u64::MAX,
             http_request_allow_list: BTreeSet::new(),
-        }
+            fee_exempt_owners: BTreeSet::new(),
+            uncharged_resources: BTreeSet::new(),
+        };
+        debug_assert_eq!(policy.validate(), Ok(()));
+        policy
     }
 
     /// The maximum fuel per block according to the `VmRuntime`.
@@ -248,6 +525,7 @@ impl ResourceControlPolicy {
             evm_fuel_unit: Amount::from_nanos(1),
             byte_read: Amount::from_attos(100),
             byte_written: Amount::from_attos(1_000),
+            byte_deserialized: Amount::from_attos(100),
             blob_read: Amount::from_nanos(1),
             blob_published: Amount::from_nanos(10),
             blob_byte_read: Amount::from_attos(100),
@@ -263,28 +541,49 @@ impl ResourceControlPolicy {
 
     /// Creates a policy that matches the Testnet.
     pub fn testnet() -> Self {
-        Self {
+        let policy = Self {
             wasm_fuel_unit: Amount::from_nanos(10),
             evm_fuel_unit: Amount::from_nanos(10),
             byte_runtime: Amount::from_nanos(1),
             byte_read: Amount::from_nanos(10),
+            read_charge_granularity: 0,
             byte_written: Amount::from_nanos(100),
+            byte_deserialized: Amount::from_nanos(10),
             blob_read: Amount::from_nanos(100),
+            blob_read_fixed: Amount::ZERO,
             blob_published: Amount::from_nanos(1000),
+            blob_published_fixed: Amount::ZERO,
             blob_byte_read: Amount::from_nanos(10),
             blob_byte_published: Amount::from_nanos(100),
+            blob_byte_deposit: Amount::ZERO,
+            blob_bytecode_multiplier: 1,
+            blob_description_multiplier: 1,
             read_operation: Amount::from_micros(10),
             write_operation: Amount::from_micros(20),
             byte_stored: Amount::from_nanos(10),
+            storage_rent: Amount::ZERO,
+            storage_refund_target: StorageRefundTarget::OwnerAccount,
             message_byte: Amount::from_nanos(100),
+            message_hop: Amount::ZERO,
+            grant_denomination: None,
+            grant_exchange_rate: Amount::ONE,
             operation_byte: Amount::from_nanos(10),
+            price_operations_by_serialized_size: false,
+            minimum_operation_fee: Amount::ZERO,
+            warmup_operations: 0,
             operation: Amount::from_micros(10),
+            operation_price_overrides: BTreeMap::new(),
+            base_transaction_fee: Amount::ZERO,
+            fee_increment: Amount::ZERO,
             message: Amount::from_micros(10),
             service_as_oracle_query: Amount::from_millis(10),
             http_request: Amount::from_micros(50),
+            http_response_byte: Amount::ZERO,
+            http_request_byte: Amount::ZERO,
             maximum_wasm_fuel_per_block: 100_000_000,
             maximum_evm_fuel_per_block: 100_000_000,
             maximum_service_oracle_execution_ms: 10_000,
+            oracle_timeout_policy: OracleTimeoutPolicy::Abort,
             maximum_block_size: 1_000_000,
             maximum_blob_size: 1_000_000,
             maximum_published_blobs: 10,
@@ -292,11 +591,330 @@ impl ResourceControlPolicy {
             maximum_block_proposal_size: 13_000_000,
             maximum_bytes_read_per_block: 100_000_000,
             maximum_bytes_written_per_block: 10_000_000,
+            maximum_bytes_deserialized_per_block: 100_000_000,
+            maximum_blob_bytes_read_per_block: 100_000_000,
+            maximum_blob_bytes_published_per_block: 10_000_000,
+            maximum_bytes_runtime_per_block: 10_000_000,
             maximum_oracle_response_bytes: 10_000,
             maximum_http_response_bytes: 10_000,
+            maximum_http_request_bytes_per_block: 10_000_000,
+            maximum_failed_charge_attempts: 1_000,
+            maximum_call_depth: 64,
             http_request_timeout_ms: 20_000,
             http_request_allow_list: BTreeSet::new(),
+            fee_exempt_owners: BTreeSet::new(),
+            uncharged_resources: BTreeSet::new(),
+        };
+        debug_assert_eq!(policy.validate(), Ok(()));
+        policy
+    }
+
+    /// Returns a copy of this policy with all prices nudged up or down, depending on how far
+    /// the utilization of the last block was above or below the target, as with
+    /// EIP-1559 base fees. The relative change is capped at `max_change_bps` basis points.
+    /// The policy is returned unchanged if the utilization is not a finite non-negative
+    /// number or the target is not a finite positive number.
+    pub fn adjust_base_fee(&self, utilization: f64, target: f64, max_change_bps: u32) -> Self {
+        if !utilization.is_finite() || utilization < 0.0 || !target.is_finite() || target <= 0.0 {
+            return self.clone();
         }
+        let max_change = f64::from(max_change_bps);
+        let change_bps = (((utilization - target) / target) * 10_000.0)
+            .round()
+            .clamp(-max_change, max_change);
+        // The multiplier is expressed in basis points, and cannot go below zero.
+        let multiplier = (10_000.0 + change_bps).max(0.0) as u128;
+        let adjust = |price: Amount| {
+            Amount::from_attos(u128::from(price).saturating_mul(multiplier) / 10_000)
+        };
+        Self {
+            wasm_fuel_unit: adjust(self.wasm_fuel_unit),
+            evm_fuel_unit: adjust(self.evm_fuel_unit),
+            read_operation: adjust(self.read_operation),
+            write_operation: adjust(self.write_operation),
+            byte_runtime: adjust(self.byte_runtime),
+            byte_read: adjust(self.byte_read),
+            byte_written: adjust(self.byte_written),
+            blob_read: adjust(self.blob_read),
+            blob_published: adjust(self.blob_published),
+            blob_byte_read: adjust(self.blob_byte_read),
+            blob_byte_published: adjust(self.blob_byte_published),
+            byte_stored: adjust(self.byte_stored),
+            operation: adjust(self.operation),
+            operation_byte: adjust(self.operation_byte),
+            message: adjust(self.message),
+            message_byte: adjust(self.message_byte),
+            service_as_oracle_query: adjust(self.service_as_oracle_query),
+            http_request: adjust(self.http_request),
+            minimum_operation_fee: adjust(self.minimum_operation_fee),
+            http_response_byte: adjust(self.http_response_byte),
+            blob_read_fixed: adjust(self.blob_read_fixed),
+            blob_published_fixed: adjust(self.blob_published_fixed),
+            message_hop: adjust(self.message_hop),
+            http_request_byte: adjust(self.http_request_byte),
+            base_transaction_fee: adjust(self.base_transaction_fee),
+            byte_deserialized: adjust(self.byte_deserialized),
+            storage_rent: adjust(self.storage_rent),
+            blob_byte_deposit: adjust(self.blob_byte_deposit),
+            operation_price_overrides: self
+                .operation_price_overrides
+                .iter()
+                .map(|(discriminant, price)| (*discriminant, adjust(*price)))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns every per-unit price of the policy, keyed by the name of its field.
+    pub fn price_schedule(&self) -> BTreeMap<&'static str, Amount> {
+        BTreeMap::from([
+            ("wasm_fuel_unit", self.wasm_fuel_unit),
+            ("evm_fuel_unit", self.evm_fuel_unit),
+            ("read_operation", self.read_operation),
+            ("write_operation", self.write_operation),
+            ("byte_runtime", self.byte_runtime),
+            ("byte_read", self.byte_read),
+            ("byte_written", self.byte_written),
+            ("blob_read", self.blob_read),
+            ("blob_published", self.blob_published),
+            ("blob_byte_read", self.blob_byte_read),
+            ("blob_byte_published", self.blob_byte_published),
+            ("byte_stored", self.byte_stored),
+            ("operation", self.operation),
+            ("operation_byte", self.operation_byte),
+            ("message", self.message),
+            ("message_byte", self.message_byte),
+            ("service_as_oracle_query", self.service_as_oracle_query),
+            ("http_request", self.http_request),
+            ("minimum_operation_fee", self.minimum_operation_fee),
+            ("http_response_byte", self.http_response_byte),
+            ("blob_read_fixed", self.blob_read_fixed),
+            ("blob_published_fixed", self.blob_published_fixed),
+            ("message_hop", self.message_hop),
+            ("blob_byte_deposit", self.blob_byte_deposit),
+            ("http_request_byte", self.http_request_byte),
+            ("base_transaction_fee", self.base_transaction_fee),
+            ("byte_deserialized", self.byte_deserialized),
+            ("storage_rent", self.storage_rent),
+        ])
+    }
+
+    /// Returns every numeric limit of the policy, keyed by the name of its field.
+    pub fn limits(&self) -> BTreeMap<&'static str, u64> {
+        BTreeMap::from([
+            (
+                "maximum_wasm_fuel_per_block",
+                self.maximum_wasm_fuel_per_block,
+            ),
+            (
+                "maximum_evm_fuel_per_block",
+                self.maximum_evm_fuel_per_block,
+            ),
+            (
+                "maximum_service_oracle_execution_ms",
+                self.maximum_service_oracle_execution_ms,
+            ),
+            ("maximum_block_size", self.maximum_block_size),
+            ("maximum_bytecode_size", self.maximum_bytecode_size),
+            ("maximum_blob_size", self.maximum_blob_size),
+            ("maximum_published_blobs", self.maximum_published_blobs),
+            (
+                "maximum_block_proposal_size",
+                self.maximum_block_proposal_size,
+            ),
+            (
+                "maximum_bytes_read_per_block",
+                self.maximum_bytes_read_per_block,
+            ),
+            (
+                "maximum_bytes_written_per_block",
+                self.maximum_bytes_written_per_block,
+            ),
+            (
+                "maximum_blob_bytes_read_per_block",
+                self.maximum_blob_bytes_read_per_block,
+            ),
+            (
+                "maximum_blob_bytes_published_per_block",
+                self.maximum_blob_bytes_published_per_block,
+            ),
+            (
+                "maximum_oracle_response_bytes",
+                self.maximum_oracle_response_bytes,
+            ),
+            (
+                "maximum_http_response_bytes",
+                self.maximum_http_response_bytes,
+            ),
+            ("http_request_timeout_ms", self.http_request_timeout_ms),
+            (
+                "maximum_bytes_runtime_per_block",
+                self.maximum_bytes_runtime_per_block,
+            ),
+            (
+                "maximum_http_request_bytes_per_block",
+                self.maximum_http_request_bytes_per_block,
+            ),
+            (
+                "maximum_failed_charge_attempts",
+                self.maximum_failed_charge_attempts,
+            ),
+            (
+                "maximum_bytes_deserialized_per_block",
+                self.maximum_bytes_deserialized_per_block,
+            ),
+            ("maximum_call_depth", self.maximum_call_depth),
+        ])
+    }
+
+    /// Returns the settings of the policy that are neither prices nor limits, e.g. the
+    /// multipliers or the exempt owners, keyed by the name of their field and formatted for
+    /// display.
+    pub fn other_settings(&self) -> BTreeMap<&'static str, String> {
+        // Every field is listed, so that a new one cannot be forgotten here.
+        let Self {
+            blob_bytecode_multiplier,
+            blob_description_multiplier,
+            fee_exempt_owners,
+            fee_increment,
+            grant_denomination,
+            grant_exchange_rate,
+            http_request_allow_list,
+            operation_price_overrides,
+            oracle_timeout_policy,
+            price_operations_by_serialized_size,
+            read_charge_granularity,
+            storage_refund_target,
+            uncharged_resources,
+            warmup_operations,
+            // The prices of `price_schedule` and the limits of `limits`.
+            wasm_fuel_unit: _,
+            evm_fuel_unit: _,
+            read_operation: _,
+            write_operation: _,
+            byte_runtime: _,
+            byte_read: _,
+            byte_written: _,
+            blob_read: _,
+            blob_published: _,
+            blob_byte_read: _,
+            blob_byte_published: _,
+            byte_stored: _,
+            operation: _,
+            operation_byte: _,
+            message: _,
+            message_byte: _,
+            service_as_oracle_query: _,
+            http_request: _,
+            minimum_operation_fee: _,
+            http_response_byte: _,
+            blob_read_fixed: _,
+            blob_published_fixed: _,
+            message_hop: _,
+            blob_byte_deposit: _,
+            http_request_byte: _,
+            base_transaction_fee: _,
+            byte_deserialized: _,
+            storage_rent: _,
+            maximum_wasm_fuel_per_block: _,
+            maximum_evm_fuel_per_block: _,
+            maximum_service_oracle_execution_ms: _,
+            maximum_block_size: _,
+            maximum_bytecode_size: _,
+            maximum_blob_size: _,
+            maximum_published_blobs: _,
+            maximum_block_proposal_size: _,
+            maximum_bytes_read_per_block: _,
+            maximum_bytes_written_per_block: _,
+            maximum_blob_bytes_read_per_block: _,
+            maximum_blob_bytes_published_per_block: _,
+            maximum_oracle_response_bytes: _,
+            maximum_http_response_bytes: _,
+            http_request_timeout_ms: _,
+            maximum_bytes_runtime_per_block: _,
+            maximum_http_request_bytes_per_block: _,
+            maximum_failed_charge_attempts: _,
+            maximum_bytes_deserialized_per_block: _,
+            maximum_call_depth: _,
+        } = self;
+        BTreeMap::from([
+            (
+                "blob_bytecode_multiplier",
+                blob_bytecode_multiplier.to_string(),
+            ),
+            (
+                "blob_description_multiplier",
+                blob_description_multiplier.to_string(),
+            ),
+            ("fee_exempt_owners", format!("{fee_exempt_owners:?}")),
+            ("fee_increment", fee_increment.to_string()),
+            ("grant_denomination", format!("{grant_denomination:?}")),
+            ("grant_exchange_rate", grant_exchange_rate.to_string()),
+            (
+                "http_request_allow_list",
+                format!("{http_request_allow_list:?}"),
+            ),
+            (
+                "operation_price_overrides",
+                format!("{operation_price_overrides:?}"),
+            ),
+            (
+                "oracle_timeout_policy",
+                format!("{oracle_timeout_policy:?}"),
+            ),
+            (
+                "price_operations_by_serialized_size",
+                price_operations_by_serialized_size.to_string(),
+            ),
+            (
+                "read_charge_granularity",
+                read_charge_granularity.to_string(),
+            ),
+            (
+                "storage_refund_target",
+                format!("{storage_refund_target:?}"),
+            ),
+            ("uncharged_resources", format!("{uncharged_resources:?}")),
+            ("warmup_operations", warmup_operations.to_string()),
+        ])
+    }
+
+    /// Returns the fields of the policy that differ in `other`: the prices of
+    /// [`Self::price_schedule`], then the limits of [`Self::limits`], then the
+    /// [`Self::other_settings`], each ordered by field name.
+    pub fn diff(&self, other: &Self) -> Vec<PolicyFieldChange> {
+        let new_prices = other.price_schedule();
+        let new_limits = other.limits();
+        let mut new_settings = other.other_settings();
+        let price_changes = self
+            .price_schedule()
+            .into_iter()
+            .filter(|(field, old)| new_prices[field] != *old)
+            .map(|(field, old)| PolicyFieldChange::Price {
+                field,
+                old,
+                new: new_prices[field],
+            });
+        let limit_changes = self
+            .limits()
+            .into_iter()
+            .filter(|(field, old)| new_limits[field] != *old)
+            .map(|(field, old)| PolicyFieldChange::Limit {
+                field,
+                old,
+                new: new_limits[field],
+            });
+        let setting_changes = self
+            .other_settings()
+            .into_iter()
+            .filter_map(|(field, old)| {
+                let new = new_settings.remove(field)?;
+                (new != old).then_some(PolicyFieldChange::Setting { field, old, new })
+            });
+        price_changes
+            .chain(limit_changes)
+            .chain(setting_changes)
+            .collect()
     }
 
     pub fn total_price(&self, resources: &Resources) -> Result<Amount, ArithmeticError> {
@@ -309,19 +927,28 @@ impl ResourceControlPolicy {
         amount.try_add_assign(self.bytes_read_price(resources.bytes_to_read as u64)?)?;
         amount.try_add_assign(self.bytes_written_price(resources.bytes_to_write as u64)?)?;
         amount.try_add_assign(
-            self.blob_byte_read
-                .try_mul(resources.blob_bytes_to_read as u128)?
-                .try_add(self.blob_read.try_mul(resources.blobs_to_read as u128)?)?,
+            self.price_of(
+                ResourceKind::BlobBytesRead,
+                u64::from(resources.blob_bytes_to_read),
+            )?
+            .try_add(Self::price_for(
+                self.blob_read,
+                u64::from(resources.blobs_to_read),
+            )?)?,
         )?;
         amount.try_add_assign(
-            self.blob_byte_published
-                .try_mul(resources.blob_bytes_to_publish as u128)?
-                .try_add(
-                    self.blob_published
-                        .try_mul(resources.blobs_to_publish as u128)?,
-                )?,
+            self.price_of(
+                ResourceKind::BlobBytesPublished,
+                u64::from(resources.blob_bytes_to_publish),
+            )?
+            .try_add(Self::price_for(
+                self.blob_published,
+                u64::from(resources.blobs_to_publish),
+            )?)?,
+        )?;
+        amount.try_add_assign(
+            self.price_of(ResourceKind::Messages, u64::from(resources.messages))?,
         )?;
-        amount.try_add_assign(self.message.try_mul(resources.messages as u128)?)?;
         amount.try_add_assign(self.message_bytes_price(resources.message_size as u64)?)?;
         amount.try_add_assign(self.bytes_stored_price(resources.storage_size_delta as u64)?)?;
         amount.try_add_assign(
@@ -331,50 +958,408 @@ impl ResourceControlPolicy {
         Ok(amount)
     }
 
+    /// Checks that the policy is usable: the limits without which no block could execute
+    /// must not be zero, and the price of each resource up to its per-block limit must not
+    /// overflow. Resources without a limit of their own are counted with `u32` counters,
+    /// e.g. operations and messages, so their prices must not overflow for `u32::MAX` items.
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        for (limit, value) in [
+            (
+                "maximum_wasm_fuel_per_block",
+                self.maximum_wasm_fuel_per_block,
+            ),
+            (
+                "maximum_evm_fuel_per_block",
+                self.maximum_evm_fuel_per_block,
+            ),
+            ("maximum_block_size", self.maximum_block_size),
+            (
+                "maximum_block_proposal_size",
+                self.maximum_block_proposal_size,
+            ),
+            (
+                "maximum_bytes_read_per_block",
+                self.maximum_bytes_read_per_block,
+            ),
+            (
+                "maximum_bytes_written_per_block",
+                self.maximum_bytes_written_per_block,
+            ),
+            (
+                "maximum_bytes_deserialized_per_block",
+                self.maximum_bytes_deserialized_per_block,
+            ),
+        ] {
+            ensure!(value != 0, PolicyError::ZeroLimit(limit));
+        }
+        for (price, unit_price, limit, maximum) in [
+            (
+                "wasm_fuel_unit",
+                self.wasm_fuel_unit,
+                "maximum_wasm_fuel_per_block",
+                self.maximum_wasm_fuel_per_block,
+            ),
+            (
+                "evm_fuel_unit",
+                self.evm_fuel_unit,
+                "maximum_evm_fuel_per_block",
+                self.maximum_evm_fuel_per_block,
+            ),
+            (
+                "byte_runtime",
+                self.byte_runtime,
+                "maximum_bytes_runtime_per_block",
+                self.maximum_bytes_runtime_per_block,
+            ),
+            (
+                "byte_read",
+                self.byte_read,
+                "maximum_bytes_read_per_block",
+                self.maximum_bytes_read_per_block,
+            ),
+            (
+                "byte_written",
+                self.byte_written,
+                "maximum_bytes_written_per_block",
+                self.maximum_bytes_written_per_block,
+            ),
+            (
+                "byte_deserialized",
+                self.byte_deserialized,
+                "maximum_bytes_deserialized_per_block",
+                self.maximum_bytes_deserialized_per_block,
+            ),
+            (
+                "blob_published",
+                self.blob_published,
+                "maximum_published_blobs",
+                self.maximum_published_blobs,
+            ),
+            (
+                "blob_byte_read",
+                self.blob_byte_read,
+                "maximum_blob_bytes_read_per_block",
+                self.maximum_blob_bytes_read_per_block,
+            ),
+            (
+                "blob_byte_published",
+                self.blob_byte_published,
+                "maximum_blob_bytes_published_per_block",
+                self.maximum_blob_bytes_published_per_block,
+            ),
+            (
+                "http_request_byte",
+                self.http_request_byte,
+                "maximum_http_request_bytes_per_block",
+                self.maximum_http_request_bytes_per_block,
+            ),
+            (
+                "http_response_byte",
+                self.http_response_byte,
+                "maximum_http_response_bytes",
+                self.maximum_http_response_bytes,
+            ),
+            (
+                "blob_published_fixed",
+                self.blob_published_fixed,
+                "maximum_published_blobs",
+                self.maximum_published_blobs,
+            ),
+            (
+                "blob_byte_deposit",
+                self.blob_byte_deposit,
+                "maximum_blob_bytes_published_per_block",
+                self.maximum_blob_bytes_published_per_block,
+            ),
+            (
+                "operation_byte",
+                self.operation_byte,
+                "maximum_block_size",
+                self.maximum_block_size,
+            ),
+            (
+                "message_byte",
+                self.message_byte,
+                "maximum_block_size",
+                self.maximum_block_size,
+            ),
+        ] {
+            ensure!(
+                Self::price_for(unit_price, maximum).is_ok(),
+                PolicyError::PriceOverflow { price, limit }
+            );
+        }
+        let overrides = self
+            .operation_price_overrides
+            .values()
+            .map(|unit_price| ("operation_price_overrides", *unit_price));
+        for (price, unit_price) in [
+            ("read_operation", self.read_operation),
+            ("write_operation", self.write_operation),
+            ("byte_stored", self.byte_stored),
+            ("blob_read", self.blob_read),
+            ("blob_read_fixed", self.blob_read_fixed),
+            ("operation", self.operation),
+            ("minimum_operation_fee", self.minimum_operation_fee),
+            ("base_transaction_fee", self.base_transaction_fee),
+            ("message", self.message),
+            ("message_hop", self.message_hop),
+            ("service_as_oracle_query", self.service_as_oracle_query),
+            ("http_request", self.http_request),
+        ]
+        .into_iter()
+        .chain(overrides)
+        {
+            ensure!(
+                Self::price_for(unit_price, u64::from(u32::MAX)).is_ok(),
+                PolicyError::CountPriceOverflow(price)
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the declared sizes of a transaction against the per-block limits of the policy,
+    /// without executing it. Returns every limit that is exceeded, not only the first one.
+    pub fn validate_transaction_hints(
+        &self,
+        hints: &TransactionHints,
+    ) -> Result<(), Vec<ExecutionError>> {
+        let mut errors = Vec::new();
+        if hints.size > self.maximum_block_size {
+            errors.push(ExecutionError::BlockTooLarge);
+        }
+        if hints.blob_bytes_read > self.maximum_blob_bytes_read_per_block {
+            errors.push(ExecutionError::ExcessiveBlobBytesRead);
+        }
+        let sizes = &hints.published_blob_sizes;
+        if sizes.len() as u64 > self.maximum_published_blobs {
+            errors.push(ExecutionError::TooManyPublishedBlobs(
+                self.maximum_published_blobs,
+            ));
+        }
+        if sizes.iter().any(|size| *size > self.maximum_blob_size) {
+            errors.push(ExecutionError::BlobTooLarge);
+        }
+        let total_size = sizes
+            .iter()
+            .fold(0u64, |total, size| total.saturating_add(*size));
+        if total_size > self.maximum_blob_bytes_published_per_block {
+            errors.push(ExecutionError::ExcessiveBlobBytesPublished);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the total fees of a block with the given contents, without executing it.
+    pub fn project_block_cost(&self, plan: &BlockCostPlan) -> Result<Amount, ArithmeticError> {
+        let mut amount = Amount::ZERO;
+        amount
+            .try_add_assign(self.price_of(ResourceKind::Operations, u64::from(plan.operations))?)?;
+        amount.try_add_assign(self.operation_bytes_price(plan.operation_bytes)?)?;
+        amount.try_add_assign(self.price_of(ResourceKind::Messages, u64::from(plan.messages))?)?;
+        amount.try_add_assign(self.message_bytes_price(plan.message_bytes)?)?;
+        amount.try_add_assign(
+            self.price_of(ResourceKind::BlobBytesRead, plan.blob_bytes_read)?
+                .try_add(Self::price_for(self.blob_read, u64::from(plan.blobs_read))?)?,
+        )?;
+        amount.try_add_assign(Self::price_for(
+            self.blob_read_fixed,
+            u64::from(plan.blobs_read),
+        )?)?;
+        amount.try_add_assign(Self::price_for(
+            self.blob_published_fixed,
+            u64::from(plan.blobs_published),
+        )?)?;
+        amount.try_add_assign(
+            self.price_of(ResourceKind::BlobBytesPublished, plan.blob_bytes_published)?
+                .try_add(Self::price_for(
+                    self.blob_published,
+                    u64::from(plan.blobs_published),
+                )?)?,
+        )?;
+        amount.try_add_assign(self.fuel_price(plan.wasm_fuel, VmRuntime::Wasm)?)?;
+        amount.try_add_assign(self.fuel_price(plan.evm_fuel, VmRuntime::Evm)?)?;
+        Ok(amount)
+    }
+
+    /// Returns the unit price of a resource, or `None` if it is not chargeable, e.g. because
+    /// it is one of the [`Self::uncharged_resources`].
+    pub fn unit_price(&self, kind: ResourceKind) -> Option<Amount> {
+        let price = match kind {
+            ResourceKind::WasmFuel => self.wasm_fuel_unit,
+            ResourceKind::EvmFuel => self.evm_fuel_unit,
+            ResourceKind::ReadOperations => self.read_operation,
+            ResourceKind::WriteOperations => self.write_operation,
+            ResourceKind::BytesRead => self.byte_read,
+            ResourceKind::BytesWritten => self.byte_written,
+            ResourceKind::DeserializedBytes => self.byte_deserialized,
+            ResourceKind::BlobBytesRead => self.blob_byte_read,
+            ResourceKind::BlobBytesPublished => self.blob_byte_published,
+            ResourceKind::Operations => self.operation,
+            ResourceKind::OperationBytes => self.operation_byte,
+            ResourceKind::Messages => self.message,
+            ResourceKind::MessageBytes => self.message_byte,
+            ResourceKind::MessageHops => self.message_hop,
+            ResourceKind::HttpRequests => self.http_request,
+            ResourceKind::HttpRequestBytes => self.http_request_byte,
+            ResourceKind::HttpResponseBytes => self.http_response_byte,
+            ResourceKind::RuntimeBytes => self.byte_runtime,
+            ResourceKind::OracleQueries => self.service_as_oracle_query,
+            // Custom resources have their own unit prices.
+            ResourceKind::OracleTime | ResourceKind::BlockSize | ResourceKind::Custom => {
+                return None
+            }
+        };
+        (!self.uncharged_resources.contains(&kind)).then_some(price)
+    }
+
+    /// Returns the price of `count` units of the given resource, which is zero if it is not
+    /// chargeable.
+    pub(crate) fn price_of(
+        &self,
+        kind: ResourceKind,
+        count: u64,
+    ) -> Result<Amount, ArithmeticError> {
+        match self.unit_price(kind) {
+            Some(unit_price) => Self::price_for(unit_price, count),
+            None => Ok(Amount::ZERO),
+        }
+    }
+
+    /// Returns the price of `count` units at the given unit price.
+    ///
+    /// Every per-unit price is computed with this function, so that all categories round
+    /// the same way: a partially used unit is charged as a whole unit. Callers measuring a
+    /// resource in coarser units than they track must round the count up.
+    pub(crate) fn price_for(unit_price: Amount, count: u64) -> Result<Amount, ArithmeticError> {
+        unit_price.try_mul(u128::from(count))
+    }
+
+    /// Returns the base price of an operation of the given type: its override if there is
+    /// one, and the price of [`Self::operation`] otherwise.
+    pub fn operation_price(&self, discriminant: OperationDiscriminant) -> Amount {
+        self.operation_price_overrides
+            .get(&discriminant)
+            .copied()
+            .unwrap_or(self.operation)
+    }
+
     pub(crate) fn operation_bytes_price(&self, size: u64) -> Result<Amount, ArithmeticError> {
-        self.operation_byte.try_mul(size as u128)
+        self.price_of(ResourceKind::OperationBytes, size)
     }
 
     pub(crate) fn message_bytes_price(&self, size: u64) -> Result<Amount, ArithmeticError> {
-        self.message_byte.try_mul(size as u128)
+        self.price_of(ResourceKind::MessageBytes, size)
     }
 
     pub(crate) fn read_operations_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
-        self.read_operation.try_mul(count as u128)
+        self.price_of(ResourceKind::ReadOperations, u64::from(count))
     }
 
     pub(crate) fn write_operations_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
-        self.write_operation.try_mul(count as u128)
+        self.price_of(ResourceKind::WriteOperations, u64::from(count))
     }
 
     pub(crate) fn bytes_runtime_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
-        self.byte_runtime.try_mul(count as u128)
+        self.price_of(ResourceKind::RuntimeBytes, u64::from(count))
     }
 
     pub(crate) fn bytes_read_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
-        self.byte_read.try_mul(count as u128)
+        self.price_of(ResourceKind::BytesRead, count)
     }
 
     pub(crate) fn bytes_written_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
-        self.byte_written.try_mul(count as u128)
+        self.price_of(ResourceKind::BytesWritten, count)
+    }
+
+    pub(crate) fn deserialization_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
+        self.price_of(ResourceKind::DeserializedBytes, count)
     }
 
     pub(crate) fn blob_read_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
-        self.blob_byte_read
-            .try_mul(count as u128)?
-            .try_add(self.blob_read)
+        self.price_of(ResourceKind::BlobBytesRead, count)?
+            .try_add(Self::price_for(self.blob_read, 1)?)
     }
 
     pub(crate) fn blob_published_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
-        self.blob_byte_published
-            .try_mul(count as u128)?
-            .try_add(self.blob_published)
+        self.price_of(ResourceKind::BlobBytesPublished, count)?
+            .try_add(Self::price_for(self.blob_published, 1)?)
+    }
+
+    /// Returns the deposit locked when publishing a blob of the given size.
+    pub(crate) fn blob_deposit_price(&self, size: u64) -> Result<Amount, ArithmeticError> {
+        Self::price_for(self.blob_byte_deposit, size)
+    }
+
+    /// Returns the multiplier applied to the price of publishing a blob of the given type.
+    pub(crate) fn blob_type_multiplier(&self, blob_type: BlobType) -> u64 {
+        match blob_type {
+            BlobType::Data | BlobType::Committee => 1,
+            BlobType::ContractBytecode | BlobType::ServiceBytecode | BlobType::EvmBytecode => {
+                self.blob_bytecode_multiplier
+            }
+            BlobType::ApplicationDescription | BlobType::ChainDescription => {
+                self.blob_description_multiplier
+            }
+        }
+    }
+
+    /// Returns the price of publishing a blob of the given type and size. Committee blobs
+    /// are free.
+    pub(crate) fn blob_published_price_for(
+        &self,
+        blob_type: BlobType,
+        size: u64,
+    ) -> Result<Amount, ArithmeticError> {
+        if blob_type == BlobType::Committee {
+            return Ok(Amount::ZERO);
+        }
+        self.blob_published_price(size)?
+            .try_mul(u128::from(self.blob_type_multiplier(blob_type)))
+    }
+
+    /// Returns the price of reading the given blob, including the fixed price per blob read.
+    /// Committee blobs are free.
+    pub fn blob_read_cost(&self, blob: &Blob) -> Result<Amount, ArithmeticError> {
+        if blob.is_committee_blob() {
+            return Ok(Amount::ZERO);
+        }
+        let size = blob.content().bytes().len() as u64;
+        self.blob_read_price(size)?
+            .try_add(Self::price_for(self.blob_read_fixed, 1)?)
+    }
+
+    /// Returns `fees` rounded up to the nearest multiple of [`Self::fee_increment`], or
+    /// unchanged if the increment is zero.
+    pub fn round_up_fees(&self, fees: Amount) -> Result<Amount, ArithmeticError> {
+        let increment = u128::from(self.fee_increment);
+        if increment == 0 {
+            return Ok(fees);
+        }
+        match u128::from(fees) % increment {
+            0 => Ok(fees),
+            remainder => fees.try_add(Amount::from_attos(increment - remainder)),
+        }
     }
 
     // TODO(#1536): This is not fully implemented.
     #[allow(dead_code)]
     pub(crate) fn bytes_stored_price(&self, count: u64) -> Result<Amount, ArithmeticError> {
-        self.byte_stored.try_mul(count as u128)
+        Self::price_for(self.byte_stored, count)
+    }
+
+    /// Returns the rent for keeping `bytes` stored during `elapsed`. A partial second is
+    /// charged as a whole second, and no rent is due if no time elapsed.
+    pub fn storage_rent_price(
+        &self,
+        bytes: u64,
+        elapsed: Duration,
+    ) -> Result<Amount, ArithmeticError> {
+        let seconds = elapsed.as_secs() + u64::from(elapsed.subsec_nanos() > 0);
+        Self::price_for(self.storage_rent, bytes)?.try_mul(u128::from(seconds))
     }
 
     /// Returns how much it would cost to perform `count` queries to services running as oracles.
@@ -382,18 +1367,44 @@ impl ResourceControlPolicy {
         &self,
         count: u32,
     ) -> Result<Amount, ArithmeticError> {
-        self.service_as_oracle_query.try_mul(count as u128)
+        self.price_of(ResourceKind::OracleQueries, u64::from(count))
     }
 
-    pub(crate) fn http_requests_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
-        self.http_request.try_mul(count as u128)
+    /// Returns the cost of an HTTP request whose response has a body of `response_bytes`
+    /// bytes.
+    pub fn estimate_http_cost(&self, response_bytes: u64) -> Result<Amount, ArithmeticError> {
+        self.price_of(ResourceKind::HttpRequests, 1)?
+            .try_add(self.price_of(ResourceKind::HttpResponseBytes, response_bytes)?)
     }
 
-    fn fuel_unit_price(&self, vm_runtime: VmRuntime) -> Amount {
-        match vm_runtime {
-            VmRuntime::Wasm => self.wasm_fuel_unit,
-            VmRuntime::Evm => self.evm_fuel_unit,
+    /// Converts a grant in the grant denomination into native tokens, rounding up.
+    pub(crate) fn grant_in_native_tokens(&self, grant: Amount) -> Result<Amount, ArithmeticError> {
+        if self.grant_denomination.is_none() {
+            return Ok(grant);
         }
+        // Split the rate and the grant into whole tokens and attos, so that no intermediate
+        // product is scaled by `Amount::ONE` twice.
+        let one = u128::from(Amount::ONE);
+        let (grant, rate) = (u128::from(grant), u128::from(self.grant_exchange_rate));
+        let (whole_rate, atto_rate) = (rate / one, rate % one);
+        let (whole_grant, atto_grant) = (grant / one, grant % one);
+        let fractional = (atto_grant * atto_rate).div_ceil(one);
+        let attos = grant
+            .checked_mul(whole_rate)
+            .and_then(|attos| attos.checked_add(whole_grant.checked_mul(atto_rate)?))
+            .and_then(|attos| attos.checked_add(fractional))
+            .ok_or(ArithmeticError::Overflow)?;
+        Ok(Amount::from_attos(attos))
+    }
+
+    pub(crate) fn http_requests_price(&self, count: u32) -> Result<Amount, ArithmeticError> {
+        self.price_of(ResourceKind::HttpRequests, u64::from(count))
+    }
+
+    /// Returns the price of one unit of fuel for the given VM, or zero if fuel is not
+    /// chargeable.
+    pub fn fuel_unit_price(&self, vm_runtime: VmRuntime) -> Amount {
+        self.unit_price(fuel_kind(vm_runtime)).unwrap_or_default()
     }
 
     pub(crate) fn fuel_price(
@@ -401,12 +1412,16 @@ impl ResourceControlPolicy {
         fuel: u64,
         vm_runtime: VmRuntime,
     ) -> Result<Amount, ArithmeticError> {
-        self.fuel_unit_price(vm_runtime).try_mul(u128::from(fuel))
+        self.price_of(fuel_kind(vm_runtime), fuel)
     }
 
-    /// Returns how much fuel can be paid with the given balance.
-    pub(crate) fn remaining_fuel(&self, balance: Amount, vm_runtime: VmRuntime) -> u64 {
+    /// Returns how much fuel of the given VM can be paid with the given balance, regardless
+    /// of the per-block limits. This is `u64::MAX` if fuel is free or not chargeable.
+    pub fn affordable_fuel(&self, balance: Amount, vm_runtime: VmRuntime) -> u64 {
         let fuel_unit = self.fuel_unit_price(vm_runtime);
+        if fuel_unit == Amount::ZERO {
+            return u64::MAX;
+        }
         u64::try_from(balance.saturating_div(fuel_unit)).unwrap_or(u64::MAX)
     }
 
@@ -3,20 +3,33 @@
 
 //! This module tracks the resources used during the execution of a transaction.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io, mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use custom_debug_derive::Debug;
 use linera_base::{
-    data_types::{Amount, ArithmeticError, Blob},
+    data_types::{Amount, ArithmeticError, Blob, Timestamp},
     ensure,
-    identifiers::AccountOwner,
+    identifiers::{AccountOwner, ApplicationId, BlobId, ChainId},
     ownership::ChainOwnership,
     vm::VmRuntime,
 };
-use linera_views::{context::Context, ViewError};
-use serde::Serialize;
+use linera_views::{batch::Batch, context::Context, ViewError};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ExecutionError, ExecutionRuntimeContext, Message, Operation, OperationDiscriminant,
+    OracleTimeoutPolicy, OutgoingMessage, ResourceControlPolicy, StorageRefundTarget,
+    SystemExecutionStateView,
+};
 
-use crate::{ExecutionError, Message, Operation, ResourceControlPolicy, SystemExecutionStateView};
+#[cfg(test)]
+#[path = "unit_tests/resources_tests.rs"]
+mod tests;
 
 #[derive(Clone, Debug, Default)]
 pub struct ResourceController<Account = Amount, Tracker = ResourceTracker> {
@@ -26,6 +39,42 @@ pub struct ResourceController<Account = Amount, Tracker = ResourceTracker> {
     pub tracker: Tracker,
     /// The account paying for the resource usage.
     pub account: Account,
+    /// Whether the account is exempt from fees by the policy. Resources are still tracked and
+    /// limited, but nothing is debited.
+    fee_exempt: bool,
+    /// The settings that are passed on to the controllers derived from this one.
+    settings: ControllerSettings,
+}
+
+/// The settings of a [`ResourceController`] that do not depend on its account. They are
+/// passed on to the controllers derived from it, e.g. by
+/// [`ResourceController::with_state`], unlike the usage recorded in the tracker.
+#[derive(Clone, Debug, Default)]
+struct ControllerSettings {
+    /// The clock used to record when the first and last charges happen, if any.
+    #[debug(skip)]
+    clock: Option<Arc<dyn Fn() -> Timestamp + Send + Sync>>,
+    /// The time by which the execution of the block must be finished, if any.
+    deadline: Option<Instant>,
+    /// The clock used to check the deadline, instead of [`Instant::now`].
+    monotonic_clock: Option<fn() -> Instant>,
+    /// Whether the per-block limits of the policy are ignored. Resources are still counted
+    /// and charged. This is used to measure the usage of past blocks under a different
+    /// policy.
+    analytics_mode: bool,
+    /// Whether tracking any resource fails right away when the account is empty, so that
+    /// nothing is recorded for a transaction that cannot pay.
+    strict_empty_account: bool,
+    /// The custom resources registered so far, indexed by [`CustomResourceId`].
+    custom_resources: Arc<Vec<CustomResource>>,
+}
+
+impl ControllerSettings {
+    /// Returns `error` if a limit is exceeded, unless limits are ignored in analytics mode.
+    fn check_limit(&self, within_limit: bool, error: ExecutionError) -> Result<(), ExecutionError> {
+        ensure!(within_limit || self.analytics_mode, error);
+        Ok(())
+    }
 }
 
 impl<Account, Tracker> ResourceController<Account, Tracker> {
@@ -35,9 +84,62 @@ impl<Account, Tracker> ResourceController<Account, Tracker> {
             policy,
             tracker,
             account,
+            fee_exempt: false,
+            settings: ControllerSettings::default(),
         }
     }
 
+    /// Returns a controller with the same policy and settings as this one, e.g. its modes
+    /// and clocks, but with the given tracker and account. The account is not exempt from
+    /// fees.
+    pub fn with_tracker_and_account<A, T>(
+        &self,
+        tracker: T,
+        account: A,
+    ) -> ResourceController<A, T> {
+        ResourceController {
+            policy: self.policy.clone(),
+            tracker,
+            account,
+            fee_exempt: false,
+            settings: self.settings.clone(),
+        }
+    }
+
+    /// Sets the clock used to record the times of the first and last charges in the
+    /// tracker. Without a clock, these times are not recorded.
+    pub fn with_clock(mut self, clock: impl Fn() -> Timestamp + Send + Sync + 'static) -> Self {
+        self.settings.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Sets the time by which the execution of the block must be finished, as checked by
+    /// [`Self::check_deadline`].
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.settings.deadline = Some(deadline);
+        self
+    }
+
+    /// Sets the clock used to check the deadline, e.g. for tests.
+    pub fn with_monotonic_clock(mut self, clock: fn() -> Instant) -> Self {
+        self.settings.monotonic_clock = Some(clock);
+        self
+    }
+
+    /// Fails with [`ExecutionError::BlockExecutionTimedOut`] if the deadline has passed.
+    /// This is meant to be called between operations.
+    pub fn check_deadline(&self) -> Result<(), ExecutionError> {
+        let Some(deadline) = self.settings.deadline else {
+            return Ok(());
+        };
+        let now = self
+            .settings
+            .monotonic_clock
+            .map_or_else(Instant::now, |clock| clock());
+        ensure!(now < deadline, ExecutionError::BlockExecutionTimedOut);
+        Ok(())
+    }
+
     /// Returns a reference to the policy.
     pub fn policy(&self) -> &Arc<ResourceControlPolicy> {
         &self.policy
@@ -47,8 +149,53 @@ impl<Account, Tracker> ResourceController<Account, Tracker> {
     pub fn tracker(&self) -> &Tracker {
         &self.tracker
     }
+
+    /// Returns whether the per-block limits of the policy are ignored, as set up by
+    /// [`Self::analytics_mode`].
+    pub fn is_analytics_mode(&self) -> bool {
+        self.settings.analytics_mode
+    }
+
+    /// Enables or disables strict mode, where tracking any resource fails with
+    /// [`ExecutionError::FeesExceedFunding`] as soon as the account is empty.
+    pub fn set_strict_empty_account(&mut self, strict: bool) {
+        self.settings.strict_empty_account = strict;
+    }
+}
+
+impl<Account> ResourceController<Account, ResourceTracker> {
+    /// Creates a new resource controller that counts and charges resources without enforcing
+    /// the limits of the policy, e.g. to replay past blocks under a different policy.
+    pub fn analytics_mode(policy: Arc<ResourceControlPolicy>, account: Account) -> Self {
+        let mut controller = Self::new(policy, ResourceTracker::default(), account);
+        controller.settings.analytics_mode = true;
+        controller
+    }
+}
+
+impl<Tracker> ResourceController<Amount, Tracker>
+where
+    Tracker: AsRef<ResourceTracker>,
+{
+    /// Returns a new controller with the same policy and balance but with all the counters
+    /// reset, e.g. for speculative execution. Unlike `clone`, the usage so far is not copied.
+    /// The settings of the controller, such as the analytics mode, are kept.
+    pub fn fork(&self) -> ResourceController<Amount, ResourceTracker> {
+        let tracker = ResourceTracker {
+            event_log: self.tracker.as_ref().event_log.as_ref().map(|_| Vec::new()),
+            ..ResourceTracker::default()
+        };
+        ResourceController {
+            fee_exempt: self.fee_exempt,
+            ..self.with_tracker_and_account(tracker, self.account)
+        }
+    }
 }
 
+/// The maximum number of oracle execution times kept by a [`ResourceTracker`] for latency
+/// analysis.
+pub const MAX_ORACLE_LATENCY_SAMPLES: usize = 1_000;
+
 /// The runtime size of an `Amount`.
 pub const RUNTIME_AMOUNT_SIZE: u32 = 16;
 
@@ -73,31 +220,31 @@ pub const RUNTIME_OWNER_WEIGHT_SIZE: u32 = 8;
 /// TODO(#4164): Implement a procedure for computing naive sizes.
 pub const RUNTIME_CONSTANT_CHAIN_OWNERSHIP_SIZE: u32 = 4 + 4 * 8;
 
-#[cfg(test)]
-mod tests {
-    use std::mem::size_of;
-
-    use linera_base::{
-        data_types::{Amount, BlockHeight, Timestamp},
-        identifiers::{ApplicationId, ChainId},
-    };
-
-    use crate::resources::{
-        RUNTIME_AMOUNT_SIZE, RUNTIME_APPLICATION_ID_SIZE, RUNTIME_BLOCK_HEIGHT_SIZE,
-        RUNTIME_CHAIN_ID_SIZE, RUNTIME_OWNER_WEIGHT_SIZE, RUNTIME_TIMESTAMP_SIZE,
-    };
-
-    #[test]
-    fn test_size_of_runtime_operations() {
-        assert_eq!(RUNTIME_AMOUNT_SIZE as usize, size_of::<Amount>());
-        assert_eq!(
-            RUNTIME_APPLICATION_ID_SIZE as usize,
-            size_of::<ApplicationId>()
-        );
-        assert_eq!(RUNTIME_BLOCK_HEIGHT_SIZE as usize, size_of::<BlockHeight>());
-        assert_eq!(RUNTIME_CHAIN_ID_SIZE as usize, size_of::<ChainId>());
-        assert_eq!(RUNTIME_TIMESTAMP_SIZE as usize, size_of::<Timestamp>());
-        assert_eq!(RUNTIME_OWNER_WEIGHT_SIZE as usize, size_of::<u64>());
+/// Returns the runtime size of `n` items of the given size.
+fn runtime_size_of_many(n: usize, item_size: u32) -> Result<u32, ArithmeticError> {
+    u32::try_from(n)
+        .ok()
+        .and_then(|n| n.checked_mul(item_size))
+        .ok_or(ArithmeticError::Overflow)
+}
+
+/// Returns the number of bytes of an operation that are charged as operation bytes, e.g. to
+/// preview fees: the length of the argument of a user operation, and zero for system
+/// operations. Policies that price operations by serialized size charge the whole
+/// serialized operation instead.
+pub fn operation_size(operation: &Operation) -> usize {
+    match operation {
+        Operation::System(_) => 0,
+        Operation::User { bytes, .. } => bytes.len(),
+    }
+}
+
+/// Returns the number of bytes of a message that are charged as message bytes, e.g. to
+/// preview fees: the length of the content of a user message, and zero for system messages.
+pub fn message_size(message: &Message) -> usize {
+    match message {
+        Message::System(_) => 0,
+        Message::User { bytes, .. } => bytes.len(),
     }
 }
 
@@ -106,10 +253,13 @@ mod tests {
 /// a specific execution flow. This could be the execution of a block,
 /// the processing of a single message, or a specific phase within these
 /// broader operations.
-#[derive(Copy, Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ResourceTracker {
     /// The total size of the block so far.
     pub block_size: u64,
+    /// Whether the block exceeded its maximum size with the grace allowed by
+    /// [`ResourceController::track_block_size_with_grace`].
+    pub block_size_grace_used: bool,
     /// The EVM fuel used so far.
     pub evm_fuel: u64,
     /// The Wasm fuel used so far.
@@ -122,8 +272,17 @@ pub struct ResourceTracker {
     pub bytes_runtime: u32,
     /// The number of bytes read.
     pub bytes_read: u64,
+    /// The number of bytes read that have not been charged yet, because they are below the
+    /// policy's `read_charge_granularity`.
+    pub pending_bytes_read: u64,
     /// The number of bytes written.
     pub bytes_written: u64,
+    /// The writes of the batch tracked by [`ResourceController::estimate_writes`] that are
+    /// not settled yet.
+    #[debug(skip_if = Option::is_none)]
+    pub write_estimate: Option<WriteEstimate>,
+    /// The number of bytes deserialized into typed values.
+    pub deserialized_bytes: u64,
     /// The number of blobs read.
     pub blobs_read: u32,
     /// The number of blobs published.
@@ -132,33 +291,1076 @@ pub struct ResourceTracker {
     pub blob_bytes_read: u64,
     /// The number of blob bytes published.
     pub blob_bytes_published: u64,
+    /// The number of committee blobs published. These are exempt from fees.
+    pub committee_blobs_published: u32,
+    /// The number of committee blob bytes published.
+    pub committee_blob_bytes_published: u64,
     /// The change in the number of bytes being stored by user applications.
     pub bytes_stored: i32,
+    /// The total rent charged for keeping bytes stored over time.
+    pub rent_charged: Amount,
+    /// The fees charged for newly stored bytes, net of refunds. Refunds for freed bytes
+    /// never exceed this amount.
+    pub bytes_stored_charged: Amount,
     /// The number of operations executed.
     pub operations: u32,
+    /// The number of operations that were not charged because of the policy's
+    /// `warmup_operations`, including those of earlier blocks on the same chain.
+    pub warmup_used: u32,
+    /// The number of operations executed, for each type of operation.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub operations_by_type: BTreeMap<OperationDiscriminant, u32>,
+    /// The applications that executed user operations or consumed fuel.
+    #[debug(skip_if = BTreeSet::is_empty)]
+    pub applications_executed: BTreeSet<ApplicationId>,
     /// The total size of the arguments of user operations.
     pub operation_bytes: u64,
+    /// The fees charged on top of the computed fees of operations, to reach the policy's
+    /// `minimum_operation_fee`.
+    pub minimum_fee_surcharges: Amount,
     /// The number of outgoing messages created (system and user).
     pub messages: u32,
     /// The total size of the arguments of outgoing user messages.
     pub message_bytes: u64,
+    /// The total number of chains that outgoing messages travel to.
+    pub outgoing_message_hops: u64,
     /// The number of HTTP requests performed.
     pub http_requests: u32,
+    /// The total size of the bodies of HTTP requests.
+    pub http_request_bytes: u64,
+    /// The total size of the bodies of HTTP responses.
+    pub http_response_bytes: u64,
     /// The number of calls to services as oracles.
     pub service_oracle_queries: u32,
     /// The time spent executing services as oracles.
     pub service_oracle_execution: Duration,
-    /// The amount allocated to message grants.
+    /// The time spent executing each service queried as an oracle, in order, up to
+    /// [`MAX_ORACLE_LATENCY_SAMPLES`] queries.
+    #[debug(skip_if = Vec::is_empty)]
+    pub oracle_latencies: Vec<Duration>,
+    /// Whether the time spent executing services as oracles was truncated at a limit,
+    /// according to [`OracleTimeoutPolicy::Truncate`].
+    pub oracle_time_truncated: bool,
+    /// The amount allocated to message grants, net of refunds: refunds of unused grants,
+    /// e.g. by [`ResourceController::refund_grant_for`], are subtracted from it.
     pub grants: Amount,
+    /// The grants allocated to each outgoing message, by message index, net of refunds.
+    #[debug(skip_if = Vec::is_empty)]
+    pub grants_by_message: Vec<(usize, Amount)>,
+    /// The labeled one-off fees charged so far, in order.
+    #[debug(skip_if = Vec::is_empty)]
+    pub custom_charges: Vec<(&'static str, Amount)>,
+    /// The maximum fees that may be charged for the current operation, if any.
+    #[debug(skip_if = Option::is_none)]
+    pub fee_cap: Option<Amount>,
+    /// The net amount taken from the account since the fee cap was set: the fees charged
+    /// and the balances merged out, minus the refunds and the balances merged in.
+    pub capped_fees: Amount,
+    /// The total amount debited from the account so far. Unlike the totals per kind of
+    /// resource and the event log, this includes the rounding of each charge up to the
+    /// policy's `fee_increment`.
+    pub debited: Amount,
+    /// The total amount credited back to the account so far, e.g. refunds.
+    pub credited: Amount,
+    /// The total amount burned instead of being credited to any account.
+    pub burned: Amount,
+    /// The total amount added to fees by rounding them up to the policy's `fee_increment`.
+    pub rounding_residue: Amount,
+    /// The deposits locked for the blobs published here. They are recorded in the chain
+    /// state, in `SystemExecutionStateView::blob_deposits`.
+    pub blob_deposits_held: Amount,
+    /// The deposits released for blobs published earlier, e.g. because they were removed.
+    pub blob_deposits_released: Amount,
+    /// The total amount added to the account by [`ResourceController::merge_balance`].
+    pub merged_balance_credits: Amount,
+    /// The total amount removed from the account by [`ResourceController::merge_balance`].
+    /// Together with `merged_balance_credits`, this is the net amount transferred by merges.
+    pub merged_balance_debits: Amount,
+    /// If set, the credits may not exceed the debits plus this initial grant amount.
+    #[debug(skip_if = Option::is_none)]
+    pub refund_allowance: Option<Amount>,
+    /// The largest single charge so far for each kind of resource.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub max_single_charge: BTreeMap<ResourceKind, Amount>,
+    /// The current depth of nested application calls.
+    pub call_depth: u32,
+    /// The deepest nesting of application calls reached so far.
+    pub max_call_depth_reached: u32,
+    /// The total fees charged so far for each kind of resource, net of refunds. These are
+    /// the fees before rounding: the rounding is only recorded in `rounding_residue`.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub charges_by_kind: BTreeMap<ResourceKind, Amount>,
+    /// The part of the block size tracked with
+    /// [`ResourceController::track_block_size_labeled`], for each component of the block.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub block_size_by_component: BTreeMap<BlockComponent, u64>,
+    /// The compressed size of the parts of the block tracked with
+    /// [`ResourceController::track_block_size_compressed`]. This is what counts towards the
+    /// block size.
+    pub compressed_block_size: u64,
+    /// The uncompressed size of the parts of the block tracked with
+    /// [`ResourceController::track_block_size_compressed`].
+    pub uncompressed_block_size: u64,
+    /// The fees paid by each owner account, when charging [`Sources`] that include owner
+    /// accounts.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub paid_by_owner: BTreeMap<AccountOwner, Amount>,
+    /// The number of units used of each custom resource.
+    #[debug(skip_if = BTreeMap::is_empty)]
+    pub custom_resource_counts: BTreeMap<CustomResourceId, u64>,
+    /// When the first and last charges happened, if the controller has a clock. These are
+    /// ignored when comparing trackers.
+    #[debug(skip_if = ChargeTimes::is_empty)]
+    pub charge_times: ChargeTimes,
+    /// Whether the flat base fee of the current transaction was charged by
+    /// [`ResourceController::track_transaction_base`]. This is reset by
+    /// [`ResourceController::start_transaction`].
+    pub base_fee_charged: bool,
+    /// The total of the flat base fees charged for all transactions so far.
+    pub base_fees: Amount,
+    /// The number of charges rejected for a lack of funds in best-effort modes, e.g. by
+    /// [`ResourceController::track_fuel_best_effort`].
+    pub failed_charge_attempts: u32,
+    /// Every charge made so far, in order, if enabled with
+    /// [`ResourceController::with_event_log`]. Controllers obtained with
+    /// [`ResourceController::with_state`] share it, since they share the tracker.
+    #[debug(skip_if = Option::is_none)]
+    pub event_log: Option<Vec<ResourceEvent>>,
 }
 
 impl ResourceTracker {
+    /// Encodes the resource usage compactly, e.g. for block headers: counters are encoded
+    /// as LEB128 varints, since most of them are small, and the oracle execution time and
+    /// grants with a fixed width. The fees and the other bookkeeping of the controller are
+    /// not included.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for counter in [
+            self.block_size,
+            self.evm_fuel,
+            self.wasm_fuel,
+            self.read_operations.into(),
+            self.write_operations.into(),
+            self.bytes_runtime.into(),
+            self.bytes_read,
+            self.bytes_written,
+            self.blobs_read.into(),
+            self.blobs_published.into(),
+            self.blob_bytes_read,
+            self.blob_bytes_published,
+            // Zigzag encoding, so that small negative changes are small too.
+            ((self.bytes_stored << 1) ^ (self.bytes_stored >> 31)) as u32 as u64,
+            self.operations.into(),
+            self.operation_bytes,
+            self.messages.into(),
+            self.message_bytes,
+            self.outgoing_message_hops,
+            self.http_requests.into(),
+            self.http_request_bytes,
+            self.http_response_bytes,
+            self.service_oracle_queries.into(),
+        ] {
+            write_varint(&mut bytes, counter);
+        }
+        bytes.extend(self.service_oracle_execution.as_secs().to_le_bytes());
+        bytes.extend(self.service_oracle_execution.subsec_nanos().to_le_bytes());
+        bytes.extend(u128::from(self.grants).to_le_bytes());
+        bytes
+    }
+
+    /// Decodes resource usage encoded with [`Self::to_compact_bytes`]. The fields that are
+    /// not part of the encoding have their default values.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self, ExecutionError> {
+        let mut reader = CompactReader(bytes);
+        let tracker = ResourceTracker {
+            block_size: reader.varint()?,
+            evm_fuel: reader.varint()?,
+            wasm_fuel: reader.varint()?,
+            read_operations: reader.small_varint()?,
+            write_operations: reader.small_varint()?,
+            bytes_runtime: reader.small_varint()?,
+            bytes_read: reader.varint()?,
+            bytes_written: reader.varint()?,
+            blobs_read: reader.small_varint()?,
+            blobs_published: reader.small_varint()?,
+            blob_bytes_read: reader.varint()?,
+            blob_bytes_published: reader.varint()?,
+            bytes_stored: {
+                let zigzag = reader.small_varint()?;
+                (zigzag >> 1) as i32 ^ -((zigzag & 1) as i32)
+            },
+            operations: reader.small_varint()?,
+            operation_bytes: reader.varint()?,
+            messages: reader.small_varint()?,
+            message_bytes: reader.varint()?,
+            outgoing_message_hops: reader.varint()?,
+            http_requests: reader.small_varint()?,
+            http_request_bytes: reader.varint()?,
+            http_response_bytes: reader.varint()?,
+            service_oracle_queries: reader.small_varint()?,
+            service_oracle_execution: {
+                let secs = u64::from_le_bytes(reader.fixed()?);
+                let nanos = u32::from_le_bytes(reader.fixed()?);
+                ensure!(
+                    nanos < 1_000_000_000,
+                    ExecutionError::InvalidCompactResourceTracker
+                );
+                Duration::new(secs, nanos)
+            },
+            grants: Amount::from_attos(u128::from_le_bytes(reader.fixed()?)),
+            ..ResourceTracker::default()
+        };
+        ensure!(
+            reader.0.is_empty(),
+            ExecutionError::InvalidCompactResourceTracker
+        );
+        Ok(tracker)
+    }
+
+    /// Returns the oracle execution time at the given percentile, between `0.0` and `1.0`,
+    /// using the nearest-rank method, or `None` if no oracle was queried.
+    pub fn oracle_latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.oracle_latencies.is_empty() || !(0.0..=1.0).contains(&percentile) {
+            return None;
+        }
+        let mut latencies = self.oracle_latencies.clone();
+        latencies.sort_unstable();
+        let rank = (percentile * latencies.len() as f64).ceil() as usize;
+        Some(latencies[rank.saturating_sub(1)])
+    }
+
+    /// Adds `fees` to the total charged for the given kind of resource.
+    fn record_charge(&mut self, kind: ResourceKind, fees: Amount) {
+        let total = self.charges_by_kind.entry(kind).or_default();
+        *total = total.saturating_add(fees);
+    }
+
+    /// Adds the fees paid by each owner account to [`Self::paid_by_owner`].
+    fn record_payments(&mut self, payments: Vec<(AccountOwner, Amount)>) {
+        for (owner, paid) in payments {
+            let total = self.paid_by_owner.entry(owner).or_default();
+            *total = total.saturating_add(paid);
+        }
+    }
+
+    /// Subtracts a refund from the total charged for the given kind of resource.
+    fn record_refund(&mut self, kind: ResourceKind, refund: Amount) {
+        if let Some(total) = self.charges_by_kind.get_mut(&kind) {
+            *total = total.saturating_sub(refund);
+        }
+    }
+
+    /// Returns the fees counted toward the fee cap after `fees` more are taken from the
+    /// account, or [`ExecutionError::FeeCapExceeded`] if that exceeds the cap.
+    fn capped_fees_after(&self, fees: Amount) -> Result<Amount, ExecutionError> {
+        let Some(cap) = self.fee_cap else {
+            return Ok(self.capped_fees);
+        };
+        let needed = self.capped_fees.try_add(fees)?;
+        ensure!(
+            needed <= cap,
+            ExecutionError::FeeCapExceeded { cap, needed }
+        );
+        Ok(needed)
+    }
+
+    /// Stops counting `amount` returned to the account, e.g. a refund, toward the fee cap.
+    fn uncap_fees(&mut self, amount: Amount) {
+        if self.fee_cap.is_some() {
+            self.capped_fees = self.capped_fees.saturating_sub(amount);
+        }
+    }
+
     fn fuel(&self, vm_runtime: VmRuntime) -> u64 {
         match vm_runtime {
             VmRuntime::Wasm => self.wasm_fuel,
             VmRuntime::Evm => self.evm_fuel,
         }
     }
+
+    /// Returns the total price of the fuel used by all VMs according to the given policy, so
+    /// that computation in different VMs can be compared. Saturates at `Amount::MAX`.
+    pub fn normalized_fuel(&self, policy: &ResourceControlPolicy) -> Amount {
+        [VmRuntime::Wasm, VmRuntime::Evm]
+            .into_iter()
+            .map(|vm_runtime| {
+                policy
+                    .fuel_price(self.fuel(vm_runtime), vm_runtime)
+                    .unwrap_or(Amount::MAX)
+            })
+            .fold(Amount::ZERO, Amount::saturating_add)
+    }
+
+    /// Returns the total number of bytes read and written, including blobs. Saturates at
+    /// `u64::MAX`.
+    pub fn total_io_bytes(&self) -> u64 {
+        self.bytes_read
+            .saturating_add(self.bytes_written)
+            .saturating_add(self.blob_bytes_read)
+            .saturating_add(self.blob_bytes_published)
+    }
+
+    /// Returns the fraction of the maximum block size of the given policy used so far.
+    pub fn block_size_fraction(&self, policy: &ResourceControlPolicy) -> f64 {
+        fraction(self.block_size as f64, policy.maximum_block_size as f64)
+    }
+
+    /// Returns the fraction of each budget of the given policy consumed so far.
+    pub fn utilization(&self, policy: &ResourceControlPolicy) -> Utilization {
+        let oracle_limit = Duration::from_millis(policy.maximum_service_oracle_execution_ms);
+        Utilization {
+            wasm_fuel: fraction(
+                self.wasm_fuel as f64,
+                policy.maximum_wasm_fuel_per_block as f64,
+            ),
+            evm_fuel: fraction(
+                self.evm_fuel as f64,
+                policy.maximum_evm_fuel_per_block as f64,
+            ),
+            bytes_read: fraction(
+                self.bytes_read as f64,
+                policy.maximum_bytes_read_per_block as f64,
+            ),
+            bytes_written: fraction(
+                self.bytes_written as f64,
+                policy.maximum_bytes_written_per_block as f64,
+            ),
+            block_size: self.block_size_fraction(policy),
+            service_oracle_execution: fraction(
+                self.service_oracle_execution.as_secs_f64(),
+                oracle_limit.as_secs_f64(),
+            ),
+        }
+    }
+
+    /// Returns the base prices of the operations under the given policy. Operations counted
+    /// in [`Self::operations_by_type`] use the price overrides of the policy, if any.
+    fn operation_base_fees(&self, policy: &ResourceControlPolicy) -> Amount {
+        if policy.unit_price(ResourceKind::Operations).is_none() {
+            return Amount::ZERO;
+        }
+        let mut fees = Amount::ZERO;
+        let mut default_priced = self.operations;
+        for (discriminant, count) in &self.operations_by_type {
+            if let Some(price) = policy.operation_price_overrides.get(discriminant) {
+                let overridden = ResourceControlPolicy::price_for(*price, (*count).into());
+                fees = fees.saturating_add(overridden.unwrap_or(Amount::MAX));
+                default_priced = default_priced.saturating_sub(*count);
+            }
+        }
+        let default = ResourceControlPolicy::price_for(policy.operation, default_priced.into());
+        fees.saturating_add(default.unwrap_or(Amount::MAX))
+    }
+
+    /// Returns the fees for the resources used so far, by category, according to the given
+    /// policy. Blob publications are priced at the base rate, regardless of their type.
+    /// Each amount saturates at `Amount::MAX`.
+    fn cost_breakdown(&self, policy: &ResourceControlPolicy) -> Vec<(&'static str, Amount)> {
+        let price = |unit_price: Amount, count: u64| {
+            ResourceControlPolicy::price_for(unit_price, count).unwrap_or(Amount::MAX)
+        };
+        let price_of =
+            |kind: ResourceKind, count: u64| policy.price_of(kind, count).unwrap_or(Amount::MAX);
+        let sum = |amounts: &[Amount]| {
+            amounts
+                .iter()
+                .copied()
+                .fold(Amount::ZERO, Amount::saturating_add)
+        };
+        vec![
+            ("base_transaction_fee", self.base_fees),
+            ("fuel", self.normalized_fuel(policy)),
+            (
+                "storage",
+                sum(&[
+                    price_of(ResourceKind::ReadOperations, self.read_operations.into()),
+                    price_of(ResourceKind::WriteOperations, self.write_operations.into()),
+                    price_of(ResourceKind::BytesRead, self.bytes_read.into()),
+                    price_of(ResourceKind::BytesWritten, self.bytes_written.into()),
+                    self.rent_charged,
+                    self.bytes_stored_charged,
+                ]),
+            ),
+            (
+                "runtime",
+                price_of(ResourceKind::RuntimeBytes, self.bytes_runtime.into()),
+            ),
+            (
+                "deserialization",
+                price_of(ResourceKind::DeserializedBytes, self.deserialized_bytes),
+            ),
+            (
+                "blobs",
+                sum(&[
+                    price(policy.blob_read, self.blobs_read.into()),
+                    price(policy.blob_read_fixed, self.blobs_read.into()),
+                    price_of(ResourceKind::BlobBytesRead, self.blob_bytes_read.into()),
+                    price(policy.blob_published, self.blobs_published.into()),
+                    price(policy.blob_published_fixed, self.blobs_published.into()),
+                    price_of(
+                        ResourceKind::BlobBytesPublished,
+                        self.blob_bytes_published.into(),
+                    ),
+                ]),
+            ),
+            (
+                "operations",
+                sum(&[
+                    self.operation_base_fees(policy),
+                    price_of(ResourceKind::OperationBytes, self.operation_bytes.into()),
+                    self.minimum_fee_surcharges,
+                ]),
+            ),
+            (
+                "messages",
+                sum(&[
+                    price_of(ResourceKind::Messages, self.messages.into()),
+                    price_of(ResourceKind::MessageBytes, self.message_bytes.into()),
+                    price_of(ResourceKind::MessageHops, self.outgoing_message_hops),
+                ]),
+            ),
+            (
+                "http_requests",
+                sum(&[
+                    price_of(ResourceKind::HttpRequests, self.http_requests.into()),
+                    price_of(ResourceKind::HttpRequestBytes, self.http_request_bytes),
+                    price_of(
+                        ResourceKind::HttpResponseBytes,
+                        self.http_response_bytes.into(),
+                    ),
+                ]),
+            ),
+            (
+                "service_oracle_queries",
+                price_of(
+                    ResourceKind::OracleQueries,
+                    self.service_oracle_queries.into(),
+                ),
+            ),
+            ("rounding", self.rounding_residue),
+            (
+                "custom",
+                self.custom_charges
+                    .iter()
+                    .map(|(_, amount)| *amount)
+                    .fold(Amount::ZERO, Amount::saturating_add),
+            ),
+        ]
+    }
+
+    /// Returns the counters of this tracker as a flat record, e.g. to log the resources used
+    /// by each transaction for offline analysis.
+    pub fn to_log_record(&self) -> LogRecord {
+        LogRecord {
+            block_size: self.block_size,
+            wasm_fuel: self.wasm_fuel,
+            evm_fuel: self.evm_fuel,
+            read_operations: self.read_operations,
+            write_operations: self.write_operations,
+            bytes_runtime: self.bytes_runtime,
+            bytes_read: self.bytes_read,
+            bytes_written: self.bytes_written,
+            deserialized_bytes: self.deserialized_bytes,
+            blobs_read: self.blobs_read,
+            blobs_published: self.blobs_published,
+            blob_bytes_read: self.blob_bytes_read,
+            blob_bytes_published: self.blob_bytes_published,
+            bytes_stored: self.bytes_stored,
+            operations: self.operations,
+            operation_bytes: self.operation_bytes,
+            messages: self.messages,
+            message_bytes: self.message_bytes,
+            outgoing_message_hops: self.outgoing_message_hops,
+            http_requests: self.http_requests,
+            http_request_bytes: self.http_request_bytes,
+            http_response_bytes: self.http_response_bytes,
+            service_oracle_queries: self.service_oracle_queries,
+            service_oracle_execution_ms: u64::try_from(self.service_oracle_execution.as_millis())
+                .unwrap_or(u64::MAX),
+            grants: self.grants,
+            debited: self.debited,
+            credited: self.credited,
+        }
+    }
+
+    /// Returns a JSON document with the counters of this tracker, the fees they amount to
+    /// under the given policy, and the fraction of each budget they use. Durations are in
+    /// milliseconds and amounts are strings.
+    pub fn to_report_json(&self, policy: &ResourceControlPolicy) -> serde_json::Value {
+        fn millis(duration: Duration) -> u64 {
+            u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+        }
+
+        let costs = self.cost_breakdown(policy);
+        let total = costs
+            .iter()
+            .map(|(_, amount)| *amount)
+            .fold(Amount::ZERO, Amount::saturating_add);
+        let mut cost_map = costs
+            .into_iter()
+            .map(|(category, amount)| (category.to_string(), amount.to_string().into()))
+            .collect::<serde_json::Map<_, _>>();
+        cost_map.insert("total".to_string(), total.to_string().into());
+        let utilization = self.utilization(policy);
+
+        serde_json::json!({
+            "counters": {
+                "block_size": self.block_size,
+                "wasm_fuel": self.wasm_fuel,
+                "evm_fuel": self.evm_fuel,
+                "read_operations": self.read_operations,
+                "write_operations": self.write_operations,
+                "bytes_runtime": self.bytes_runtime,
+                "bytes_read": self.bytes_read,
+                "bytes_written": self.bytes_written,
+                "deserialized_bytes": self.deserialized_bytes,
+                "blobs_read": self.blobs_read,
+                "blobs_published": self.blobs_published,
+                "blob_bytes_read": self.blob_bytes_read,
+                "blob_bytes_published": self.blob_bytes_published,
+                "bytes_stored": self.bytes_stored,
+                "operations": self.operations,
+                "operation_bytes": self.operation_bytes,
+                "messages": self.messages,
+                "message_bytes": self.message_bytes,
+                "outgoing_message_hops": self.outgoing_message_hops,
+                "http_requests": self.http_requests,
+                "http_response_bytes": self.http_response_bytes,
+                "service_oracle_queries": self.service_oracle_queries,
+                "service_oracle_execution_ms": millis(self.service_oracle_execution),
+                "grants": self.grants.to_string(),
+            },
+            "costs": cost_map,
+            "utilization": {
+                "wasm_fuel": utilization.wasm_fuel,
+                "evm_fuel": utilization.evm_fuel,
+                "bytes_read": utilization.bytes_read,
+                "bytes_written": utilization.bytes_written,
+                "block_size": utilization.block_size,
+                "service_oracle_execution": utilization.service_oracle_execution,
+            },
+        })
+    }
+}
+
+/// A kind of resource tracked by a [`ResourceController`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ResourceKind {
+    /// Wasm fuel.
+    WasmFuel,
+    /// EVM fuel.
+    EvmFuel,
+    /// Storage read operations.
+    ReadOperations,
+    /// Storage write operations.
+    WriteOperations,
+    /// Bytes read from storage.
+    BytesRead,
+    /// Bytes written to storage.
+    BytesWritten,
+    /// Bytes of blobs read.
+    BlobBytesRead,
+    /// Bytes of blobs published.
+    BlobBytesPublished,
+    /// Operations in a block.
+    Operations,
+    /// Bytes in the arguments of user operations.
+    OperationBytes,
+    /// Outgoing messages.
+    Messages,
+    /// Bytes in the arguments of outgoing user messages.
+    MessageBytes,
+    /// Chains that outgoing messages travel to.
+    MessageHops,
+    /// HTTP requests.
+    HttpRequests,
+    /// Bytes in the bodies of HTTP requests.
+    HttpRequestBytes,
+    /// Bytes in the bodies of HTTP responses.
+    HttpResponseBytes,
+    /// Bytes read from the runtime.
+    RuntimeBytes,
+    /// Queries to services as oracles.
+    OracleQueries,
+    /// Time spent executing services as oracles.
+    OracleTime,
+    /// The size of a block.
+    BlockSize,
+    /// Bytes deserialized into typed values.
+    DeserializedBytes,
+    /// Units of the custom resources registered with
+    /// [`ResourceController::register_custom_resource`].
+    Custom,
+}
+
+impl ResourceKind {
+    /// All kinds of resources, in order.
+    pub const ALL: &'static [ResourceKind] = &[
+        ResourceKind::WasmFuel,
+        ResourceKind::EvmFuel,
+        ResourceKind::ReadOperations,
+        ResourceKind::WriteOperations,
+        ResourceKind::BytesRead,
+        ResourceKind::BytesWritten,
+        ResourceKind::BlobBytesRead,
+        ResourceKind::BlobBytesPublished,
+        ResourceKind::Operations,
+        ResourceKind::OperationBytes,
+        ResourceKind::Messages,
+        ResourceKind::MessageBytes,
+        ResourceKind::MessageHops,
+        ResourceKind::HttpRequests,
+        ResourceKind::HttpRequestBytes,
+        ResourceKind::HttpResponseBytes,
+        ResourceKind::RuntimeBytes,
+        ResourceKind::OracleQueries,
+        ResourceKind::OracleTime,
+        ResourceKind::BlockSize,
+        ResourceKind::DeserializedBytes,
+        ResourceKind::Custom,
+    ];
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ResourceKind::WasmFuel => "wasm_fuel",
+            ResourceKind::EvmFuel => "evm_fuel",
+            ResourceKind::ReadOperations => "read_operations",
+            ResourceKind::WriteOperations => "write_operations",
+            ResourceKind::BytesRead => "bytes_read",
+            ResourceKind::BytesWritten => "bytes_written",
+            ResourceKind::BlobBytesRead => "blob_bytes_read",
+            ResourceKind::BlobBytesPublished => "blob_bytes_published",
+            ResourceKind::Operations => "operations",
+            ResourceKind::OperationBytes => "operation_bytes",
+            ResourceKind::Messages => "messages",
+            ResourceKind::MessageBytes => "message_bytes",
+            ResourceKind::MessageHops => "message_hops",
+            ResourceKind::HttpRequests => "http_requests",
+            ResourceKind::HttpRequestBytes => "http_request_bytes",
+            ResourceKind::HttpResponseBytes => "http_response_bytes",
+            ResourceKind::RuntimeBytes => "runtime_bytes",
+            ResourceKind::OracleQueries => "oracle_queries",
+            ResourceKind::OracleTime => "oracle_time",
+            ResourceKind::BlockSize => "block_size",
+            ResourceKind::DeserializedBytes => "deserialized_bytes",
+            ResourceKind::Custom => "custom",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Returns the kind of resource for the fuel of the given VM.
+pub(crate) fn fuel_kind(vm_runtime: VmRuntime) -> ResourceKind {
+    match vm_runtime {
+        VmRuntime::Wasm => ResourceKind::WasmFuel,
+        VmRuntime::Evm => ResourceKind::EvmFuel,
+    }
+}
+
+/// A component of a block, to which a part of the block size can be attributed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlockComponent {
+    /// The operations of the block.
+    Operations,
+    /// The incoming or outgoing messages of the block.
+    Messages,
+    /// The blobs published or read by the block.
+    Blobs,
+    /// The certificates included in the block.
+    Certificates,
+}
+
+/// A resource and its quantity, charged together with others by
+/// [`ResourceController::charge_all`].
+#[derive(Clone, Debug)]
+pub enum Charge {
+    /// Fuel for the given VM.
+    Fuel(u64, VmRuntime),
+    /// Read operations.
+    ReadOperations(u32),
+    /// Write operations.
+    WriteOperations(u32),
+    /// Bytes read from storage.
+    BytesRead(u64),
+    /// Bytes written to storage.
+    BytesWritten(u64),
+    /// An outgoing message created by the chain with the given ID.
+    Message(ChainId, OutgoingMessage),
+}
+
+/// Identifies a custom resource registered with
+/// [`ResourceController::register_custom_resource`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CustomResourceId(pub u32);
+
+/// A resource metered on behalf of an application platform, e.g. "AI tokens".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CustomResource {
+    /// The name of the resource, for diagnostics.
+    pub name: String,
+    /// The price per unit.
+    pub unit_price: Amount,
+    /// The maximum number of units per block.
+    pub max_per_block: u64,
+}
+
+/// The times of the first and last charges recorded in a [`ResourceTracker`]. They depend
+/// on the clock, so all values are equal: they are ignored when comparing trackers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChargeTimes {
+    /// When the first charge happened.
+    pub first: Option<Timestamp>,
+    /// When the last charge happened.
+    pub last: Option<Timestamp>,
+}
+
+impl ChargeTimes {
+    /// Returns whether no charge was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.first.is_none()
+    }
+
+    fn record(&mut self, now: Timestamp) {
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+    }
+}
+
+impl PartialEq for ChargeTimes {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ChargeTimes {}
+
+/// The writes of a batch tracked before it is committed, with the fees actually debited for
+/// them. See [`ResourceController::settle_writes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteEstimate {
+    /// The number of write operations.
+    pub operations: u32,
+    /// The number of bytes written.
+    pub bytes: u64,
+    /// The fees debited for the write operations.
+    pub operation_fees: Amount,
+    /// The fees debited for the bytes written.
+    pub byte_fees: Amount,
+}
+
+/// The fraction of each per-block budget of the policy consumed so far, between `0.0`
+/// and `1.0`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Utilization {
+    /// The fraction of the Wasm fuel budget used.
+    pub wasm_fuel: f64,
+    /// The fraction of the EVM fuel budget used.
+    pub evm_fuel: f64,
+    /// The fraction of the bytes-read budget used.
+    pub bytes_read: f64,
+    /// The fraction of the bytes-written budget used.
+    pub bytes_written: f64,
+    /// The fraction of the maximum block size used.
+    pub block_size: f64,
+    /// The fraction of the service-as-oracle execution time budget used.
+    pub service_oracle_execution: f64,
+}
+
+impl Utilization {
+    /// Returns the fraction used of each budget, with the kind of resource it limits.
+    pub fn by_kind(&self) -> [(ResourceKind, f64); 6] {
+        [
+            (ResourceKind::WasmFuel, self.wasm_fuel),
+            (ResourceKind::EvmFuel, self.evm_fuel),
+            (ResourceKind::BytesRead, self.bytes_read),
+            (ResourceKind::BytesWritten, self.bytes_written),
+            (ResourceKind::OracleTime, self.service_oracle_execution),
+            (ResourceKind::BlockSize, self.block_size),
+        ]
+    }
+}
+
+/// What a [`ResourceEvent`] was charged for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChargeKind {
+    /// Units of a kind of resource.
+    Resource(ResourceKind),
+    /// A fee that is not tied to a kind of resource, e.g. the base fee of a transaction or
+    /// a grant, with a label.
+    Fee(&'static str),
+}
+
+/// A charge made by a [`ResourceController`], as recorded in its event log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceEvent {
+    /// What was charged.
+    pub kind: ChargeKind,
+    /// The number of units charged, or 1 for fees that are not tied to a kind of resource.
+    pub quantity: u64,
+    /// The fees charged for these units, before rounding them up to the policy's
+    /// `fee_increment`.
+    pub fee: Amount,
+}
+
+/// A summary of what a transaction cost, for users to keep, assembled by
+/// [`ResourceController::receipt`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionReceipt {
+    /// The fees for each category of resources, e.g. `"fuel"` or `"storage"`.
+    pub fees: BTreeMap<String, Amount>,
+    /// The sum of the fees of all categories.
+    pub total_fee: Amount,
+    /// The Wasm fuel used.
+    pub wasm_fuel: u64,
+    /// The EVM fuel used.
+    pub evm_fuel: u64,
+    /// The number of bytes read from storage.
+    pub bytes_read: u64,
+    /// The number of bytes written to storage.
+    pub bytes_written: u64,
+    /// The kinds of resources whose per-block maximum was reached, in [`ResourceKind`] order.
+    pub limits_reached: Vec<ResourceKind>,
+}
+
+/// The resources used by a transaction, as one flat record, obtained with
+/// [`ResourceTracker::to_log_record`]. The fields are always serialized in the same order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// The total size of the block so far.
+    pub block_size: u64,
+    /// The Wasm fuel used.
+    pub wasm_fuel: u64,
+    /// The EVM fuel used.
+    pub evm_fuel: u64,
+    /// The number of read operations.
+    pub read_operations: u32,
+    /// The number of write operations.
+    pub write_operations: u32,
+    /// The number of bytes read from the runtime.
+    pub bytes_runtime: u32,
+    /// The number of bytes read from storage.
+    pub bytes_read: u64,
+    /// The number of bytes written to storage.
+    pub bytes_written: u64,
+    /// The number of bytes deserialized into typed values.
+    pub deserialized_bytes: u64,
+    /// The number of blobs read.
+    pub blobs_read: u32,
+    /// The number of blobs published.
+    pub blobs_published: u32,
+    /// The number of bytes of the blobs read.
+    pub blob_bytes_read: u64,
+    /// The number of bytes of the blobs published.
+    pub blob_bytes_published: u64,
+    /// The change in the number of bytes stored.
+    pub bytes_stored: i32,
+    /// The number of operations.
+    pub operations: u32,
+    /// The number of bytes in the arguments of the operations.
+    pub operation_bytes: u64,
+    /// The number of outgoing messages.
+    pub messages: u32,
+    /// The number of bytes in the outgoing messages.
+    pub message_bytes: u64,
+    /// The number of hops made by the outgoing messages.
+    pub outgoing_message_hops: u64,
+    /// The number of HTTP requests.
+    pub http_requests: u32,
+    /// The number of bytes in the bodies of the HTTP requests.
+    pub http_request_bytes: u64,
+    /// The number of bytes in the bodies of the HTTP responses.
+    pub http_response_bytes: u64,
+    /// The number of queries to services as oracles.
+    pub service_oracle_queries: u32,
+    /// The time spent executing services as oracles, in milliseconds.
+    pub service_oracle_execution_ms: u64,
+    /// The amount allocated to message grants, net of refunds.
+    pub grants: Amount,
+    /// The total amount debited from the account.
+    pub debited: Amount,
+    /// The total amount credited to the account.
+    pub credited: Amount,
+}
+
+impl LogRecord {
+    /// Writes this record as one line of JSON, e.g. to append it to a log file.
+    pub fn write_json_line(&self, mut writer: impl io::Write) -> io::Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"\n")
+    }
+}
+
+/// Returns `used / maximum`, capped at `1.0`. A zero maximum yields `0.0`.
+fn fraction(used: f64, maximum: f64) -> f64 {
+    if maximum == 0.0 {
+        0.0
+    } else {
+        (used / maximum).min(1.0)
+    }
+}
+
+// Read-only accessors for a ResourceController.
+impl<Account, Tracker> ResourceController<Account, Tracker>
+where
+    Tracker: AsRef<ResourceTracker>,
+{
+    /// Returns the charges made so far, in order, or `None` if the event log is disabled.
+    pub fn event_log(&self) -> Option<&[ResourceEvent]> {
+        self.tracker.as_ref().event_log.as_deref()
+    }
+
+    /// Returns the maximum amount of fuel a block can consume with the given VM.
+    pub fn max_fuel(&self, vm_runtime: VmRuntime) -> u64 {
+        self.policy.maximum_fuel_per_block(vm_runtime)
+    }
+
+    /// Returns the amount of fuel used so far with the given VM.
+    pub fn used_fuel(&self, vm_runtime: VmRuntime) -> u64 {
+        self.tracker.as_ref().fuel(vm_runtime)
+    }
+
+    /// Returns the fraction of each budget of the policy consumed so far.
+    pub fn utilization(&self) -> Utilization {
+        self.tracker.as_ref().utilization(&self.policy)
+    }
+
+    /// Returns the first kind of resource, in [`ResourceKind`] order, whose usage has
+    /// reached the per-block maximum of the policy, or `None` if there is still room for
+    /// every resource. Nothing is charged.
+    pub fn at_any_limit(&self) -> Option<ResourceKind> {
+        self.limits_reached().next()
+    }
+
+    /// Returns the kinds of resources, in [`ResourceKind`] order, whose usage has reached
+    /// the per-block maximum of the policy.
+    fn limits_reached(&self) -> impl Iterator<Item = ResourceKind> {
+        let tracker = self.tracker.as_ref();
+        let policy = &self.policy;
+        let oracle_limit = Duration::from_millis(policy.maximum_service_oracle_execution_ms);
+        [
+            (
+                ResourceKind::WasmFuel,
+                tracker.wasm_fuel >= policy.maximum_wasm_fuel_per_block,
+            ),
+            (
+                ResourceKind::EvmFuel,
+                tracker.evm_fuel >= policy.maximum_evm_fuel_per_block,
+            ),
+            (
+                ResourceKind::BytesRead,
+                tracker.bytes_read >= policy.maximum_bytes_read_per_block,
+            ),
+            (
+                ResourceKind::BytesWritten,
+                tracker.bytes_written >= policy.maximum_bytes_written_per_block,
+            ),
+            (
+                ResourceKind::BlobBytesRead,
+                tracker.blob_bytes_read >= policy.maximum_blob_bytes_read_per_block,
+            ),
+            (
+                ResourceKind::BlobBytesPublished,
+                tracker.blob_bytes_published >= policy.maximum_blob_bytes_published_per_block,
+            ),
+            (
+                ResourceKind::RuntimeBytes,
+                u64::from(tracker.bytes_runtime) >= policy.maximum_bytes_runtime_per_block,
+            ),
+            (
+                ResourceKind::OracleTime,
+                tracker.service_oracle_execution >= oracle_limit,
+            ),
+            (
+                ResourceKind::BlockSize,
+                tracker.block_size >= policy.maximum_block_size,
+            ),
+            (
+                ResourceKind::DeserializedBytes,
+                tracker.deserialized_bytes >= policy.maximum_bytes_deserialized_per_block,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(kind, reached)| reached.then_some(kind))
+    }
+
+    /// Returns a receipt summarizing the fees and the main resources used so far, according
+    /// to the policy.
+    pub fn receipt(&self) -> TransactionReceipt {
+        let tracker = self.tracker.as_ref();
+        let fees = tracker
+            .cost_breakdown(&self.policy)
+            .into_iter()
+            .map(|(category, amount)| (category.to_string(), amount))
+            .collect::<BTreeMap<_, _>>();
+        let total_fee = fees
+            .values()
+            .copied()
+            .fold(Amount::ZERO, Amount::saturating_add);
+        TransactionReceipt {
+            fees,
+            total_fee,
+            wasm_fuel: tracker.wasm_fuel,
+            evm_fuel: tracker.evm_fuel,
+            bytes_read: tracker.bytes_read,
+            bytes_written: tracker.bytes_written,
+            limits_reached: self.limits_reached().collect(),
+        }
+    }
+
+    /// Returns the number of distinct applications that executed user operations or
+    /// consumed fuel so far.
+    pub fn applications_executed(&self) -> usize {
+        self.tracker.as_ref().applications_executed.len()
+    }
+
+    /// Returns the amount granted to messages minus the amount refunded, i.e. what is
+    /// currently locked in outstanding grants.
+    pub fn net_grants(&self) -> Amount {
+        self.tracker.as_ref().grants
+    }
+
+    /// Returns the `n` most utilized budgets of the policy, most utilized first. Ties are
+    /// ordered by [`ResourceKind`].
+    pub fn tightest_constraints(&self, n: usize) -> Vec<(ResourceKind, f64)> {
+        let mut constraints = self.utilization().by_kind();
+        constraints.sort_by(|(kind1, fraction1), (kind2, fraction2)| {
+            fraction2.total_cmp(fraction1).then(kind1.cmp(kind2))
+        });
+        constraints.into_iter().take(n).collect()
+    }
+}
+
+/// How much more an execution can spend, as returned by
+/// [`ResourceController::spending_headroom`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendingHeadroom {
+    /// The fuel units that the balance affords and that are within the per-block limit.
+    pub fuel_units: u64,
+    /// The amount that can still be charged to the account.
+    pub fee_amount: Amount,
+}
+
+/// The fuel that can still be spent, as returned by
+/// [`ResourceController::remaining_fuel_detailed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemainingFuel {
+    /// The fuel that the balance affords.
+    pub by_balance: u64,
+    /// The fuel left before reaching the per-block maximum of the policy.
+    pub by_cap: u64,
+    /// The fuel that can actually be spent: the smaller of the two bounds.
+    pub effective: u64,
+}
+
+/// The state of a [`ResourceController`] captured by [`ResourceController::snapshot`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControllerSnapshot {
+    /// A copy of the tracker.
+    pub tracker: ResourceTracker,
+    /// The balance of the account.
+    pub balance: Amount,
 }
 
 /// How to access the balance of an account.
@@ -168,6 +1370,36 @@ pub trait BalanceHolder {
     fn try_add_assign(&mut self, other: Amount) -> Result<(), ArithmeticError>;
 
     fn try_sub_assign(&mut self, other: Amount) -> Result<(), ArithmeticError>;
+
+    /// Returns a short label identifying the kind of account, for diagnostics.
+    fn description(&self) -> &'static str;
+
+    /// Returns the balance of each sub-account, e.g. to display them separately. By
+    /// default, the account has a single sub-account, and none if its balance cannot be
+    /// computed.
+    fn balances(&self) -> Vec<Amount> {
+        self.balance().into_iter().collect()
+    }
+
+    /// Adds an amount to the sub-account selected by `target`, instead of the default one.
+    /// By default, the account has a single sub-account, which receives everything.
+    fn try_add_assign_to(
+        &mut self,
+        _target: RefundTarget,
+        other: Amount,
+    ) -> Result<(), ArithmeticError> {
+        self.try_add_assign(other)
+    }
+
+    /// Subtracts an amount like [`Self::try_sub_assign`], and returns how much each owner
+    /// account paid. By default, the account does not belong to any owner.
+    fn try_sub_assign_by_owner(
+        &mut self,
+        other: Amount,
+    ) -> Result<Vec<(AccountOwner, Amount)>, ArithmeticError> {
+        self.try_sub_assign(other)?;
+        Ok(Vec::new())
+    }
 }
 
 // The main accounting functions for a ResourceController.
@@ -182,110 +1414,824 @@ where
         self.account.balance()
     }
 
+    /// Returns the part of the balance that is not earmarked for outstanding message grants.
+    /// Grants are deducted from the balance as soon as they are allocated, so this is the
+    /// same as [`Self::balance`].
+    pub fn spendable_balance(&self) -> Result<Amount, ArithmeticError> {
+        self.balance()
+    }
+
+    /// Returns a short label identifying the kind of account being charged, for diagnostics.
+    pub fn account_description(&self) -> &'static str {
+        self.account.description()
+    }
+
+    /// Returns whether the account could pay the given fee, in addition to the charges that
+    /// are still pending. Nothing is charged. If the balance cannot be computed, the fee is
+    /// considered unaffordable.
+    pub fn can_afford(&self, fee: Amount) -> bool {
+        self.balance_for_fees()
+            .is_ok_and(|balance| balance.saturating_sub(self.pending_fees()) >= fee)
+    }
+
+    /// Returns the balance that fees can be paid from: unlimited if the account is
+    /// fee-exempt, since nothing is ever debited from it.
+    fn balance_for_fees(&self) -> Result<Amount, ExecutionError> {
+        if self.fee_exempt {
+            return Ok(Amount::MAX);
+        }
+        self.balance()
+    }
+
+    /// Returns the fees of the charges that are still pending, i.e. that will be charged by
+    /// [`Self::flush_pending_charges`] at the latest. This saturates at `Amount::MAX`.
+    fn pending_fees(&self) -> Amount {
+        self.policy
+            .bytes_read_price(self.tracker.as_ref().pending_bytes_read)
+            .unwrap_or(Amount::MAX)
+    }
+
+    /// Captures the tracker and the balance of the account, to be restored later with
+    /// [`Self::restore`].
+    pub fn snapshot(&self) -> Result<ControllerSnapshot, ArithmeticError> {
+        Ok(ControllerSnapshot {
+            tracker: self.tracker.as_ref().clone(),
+            balance: self.balance()?,
+        })
+    }
+
+    /// Resets the tracker and the balance of the account to a previous snapshot.
+    ///
+    /// The balance is restored by crediting or debiting the difference. For [`Sources`], only
+    /// the total balance is restored: how it is distributed between the sources depends on
+    /// the [`RefundTarget`] and may differ from the snapshot.
+    pub fn restore(&mut self, snapshot: ControllerSnapshot) -> Result<(), ArithmeticError> {
+        let balance = self.balance()?;
+        if snapshot.balance <= balance {
+            self.account
+                .try_sub_assign(balance.try_sub(snapshot.balance)?)?;
+        } else {
+            self.account
+                .try_add_assign(snapshot.balance.try_sub(balance)?)?;
+        }
+        *self.tracker.as_mut() = snapshot.tracker;
+        Ok(())
+    }
+
     /// Operates a 3-way merge by transferring the difference between `initial`
-    /// and `other` to `self`.
+    /// and `other` to `self`. The amount transferred is recorded in the tracker.
+    ///
+    /// Merges are transfers, not fees or refunds: they are only recorded in
+    /// [`ResourceTracker::merged_balance_credits`] and
+    /// [`ResourceTracker::merged_balance_debits`], and are not subject to fee exemptions or
+    /// the refund allowance. They do count toward the fee cap, like debits and credits.
     pub fn merge_balance(&mut self, initial: Amount, other: Amount) -> Result<(), ExecutionError> {
         if other <= initial {
             let sub_amount = initial.try_sub(other).expect("other <= initial");
+            let capped_fees = self.tracker.as_ref().capped_fees_after(sub_amount)?;
             self.account.try_sub_assign(sub_amount).map_err(|_| {
                 ExecutionError::FeesExceedFunding {
                     fees: sub_amount,
                     balance: self.balance().unwrap_or(Amount::MAX),
                 }
             })?;
+            let tracker = self.tracker.as_mut();
+            tracker.capped_fees = capped_fees;
+            tracker.merged_balance_debits.try_add_assign(sub_amount)?;
         } else {
-            self.account
-                .try_add_assign(other.try_sub(initial).expect("other > initial"))?;
+            let add_amount = other.try_sub(initial).expect("other > initial");
+            self.account.try_add_assign(add_amount)?;
+            let tracker = self.tracker.as_mut();
+            tracker.uncap_fees(add_amount);
+            tracker.merged_balance_credits.try_add_assign(add_amount)?;
         }
         Ok(())
     }
 
-    /// Subtracts an amount from a balance and reports an error if that is impossible.
-    fn update_balance(&mut self, fees: Amount) -> Result<(), ExecutionError> {
-        self.account
-            .try_sub_assign(fees)
-            .map_err(|_| ExecutionError::FeesExceedFunding {
+    /// Adds an amount to a balance, checking that the credits do not exceed the debits if
+    /// [`ResourceTracker::refund_allowance`] is set.
+    fn credit(&mut self, amount: Amount) -> Result<(), ExecutionError> {
+        self.credit_to(amount, None)
+    }
+
+    /// Like [`Self::credit`], but credits the given sub-account of the account, if any,
+    /// instead of the default one. Nothing is credited to a fee-exempt account, since no
+    /// fees were debited from it in the first place.
+    fn credit_to(
+        &mut self,
+        amount: Amount,
+        target: Option<RefundTarget>,
+    ) -> Result<(), ExecutionError> {
+        if self.fee_exempt {
+            return Ok(());
+        }
+        let tracker = self.tracker.as_ref();
+        let credited = tracker.credited.try_add(amount)?;
+        if let Some(allowance) = tracker.refund_allowance {
+            ensure!(
+                credited <= tracker.debited.saturating_add(allowance),
+                ExecutionError::RefundExceedsCharges
+            );
+        }
+        match target {
+            Some(target) => self.account.try_add_assign_to(target, amount)?,
+            None => self.account.try_add_assign(amount)?,
+        }
+        let tracker = self.tracker.as_mut();
+        tracker.credited = credited;
+        tracker.uncap_fees(amount);
+        Ok(())
+    }
+
+    /// Burns an amount instead of crediting it, e.g. a refund: no source receives it, and
+    /// it is only recorded in [`ResourceTracker::burned`].
+    pub fn burn(&mut self, amount: Amount) -> Result<(), ExecutionError> {
+        self.tracker.as_mut().burned.try_add_assign(amount)?;
+        Ok(())
+    }
+
+    /// Subtracts an amount from a balance and reports an error if that is impossible. The
+    /// charge is recorded in the event log, if enabled, as `quantity` units of `kind`.
+    fn update_balance(
+        &mut self,
+        kind: ChargeKind,
+        quantity: u64,
+        fees: Amount,
+    ) -> Result<(), ExecutionError> {
+        if !self.fee_exempt {
+            self.debit(fees)?;
+        }
+        if let Some(event_log) = &mut self.tracker.as_mut().event_log {
+            event_log.push(ResourceEvent {
+                kind,
+                quantity,
+                fee: fees,
+            });
+        }
+        Ok(())
+    }
+
+    /// Subtracts fees from the account, within the fee cap, and records them as debited.
+    fn debit(&mut self, fees: Amount) -> Result<(), ExecutionError> {
+        let raw_fees = fees;
+        let fees = self.policy.round_up_fees(raw_fees)?;
+        let capped_fees = self.tracker.as_ref().capped_fees_after(fees)?;
+        let paid_by_owner = self.account.try_sub_assign_by_owner(fees).map_err(|_| {
+            ExecutionError::FeesExceedFunding {
                 fees,
                 balance: self.balance().unwrap_or(Amount::MAX),
-            })?;
+            }
+        })?;
+        let tracker = self.tracker.as_mut();
+        tracker.record_payments(paid_by_owner);
+        tracker.capped_fees = capped_fees;
+        tracker.debited = tracker.debited.saturating_add(fees);
+        tracker.rounding_residue = tracker
+            .rounding_residue
+            .saturating_add(fees.saturating_sub(raw_fees));
+        if let Some(clock) = &self.settings.clock {
+            tracker.charge_times.record(clock());
+        }
+        Ok(())
+    }
+
+    /// Charges for `quantity` units of a resource at the unit price of the policy. Nothing
+    /// is charged if the resource is not chargeable.
+    fn charge_resource(&mut self, kind: ResourceKind, quantity: u64) -> Result<(), ExecutionError> {
+        let Some(unit_price) = self.policy.unit_price(kind) else {
+            return Ok(());
+        };
+        let fees = unit_price.try_mul(u128::from(quantity))?;
+        self.charge_resource_with_base(kind, quantity, unit_price, fees)
+    }
+
+    /// Charges `fees` for `quantity` units of a resource, where the fees may include a
+    /// base price in addition to the unit price. Emits a `debug` event for each charge.
+    fn charge_resource_with_base(
+        &mut self,
+        kind: ResourceKind,
+        quantity: u64,
+        unit_price: Amount,
+        fees: Amount,
+    ) -> Result<(), ExecutionError> {
+        self.update_balance(ChargeKind::Resource(kind), quantity, fees)?;
+        let tracker = self.tracker.as_mut();
+        tracker.record_charge(kind, fees);
+        let max_charge = tracker.max_single_charge.entry(kind).or_default();
+        *max_charge = (*max_charge).max(fees);
+        tracing::debug!(
+            ?kind,
+            quantity,
+            %unit_price,
+            %fees,
+            balance = %self.balance().unwrap_or(Amount::MAX),
+            "Charged fees for resource"
+        );
         Ok(())
     }
 
+    /// Returns how much more the execution can spend with the given VM before running out
+    /// of funds or reaching the per-block fuel limit.
+    pub fn spending_headroom(&self, vm_runtime: VmRuntime) -> SpendingHeadroom {
+        SpendingHeadroom {
+            fuel_units: self.remaining_fuel(vm_runtime),
+            fee_amount: self.balance_for_fees().unwrap_or(Amount::MAX),
+        }
+    }
+
     /// Obtains the amount of fuel that could be spent by consuming the entire balance.
     pub(crate) fn remaining_fuel(&self, vm_runtime: VmRuntime) -> u64 {
-        let balance = self.balance().unwrap_or(Amount::MAX);
-        let fuel = self.tracker.as_ref().fuel(vm_runtime);
-        let maximum_fuel_per_block = self.policy.maximum_fuel_per_block(vm_runtime);
-        self.policy
-            .remaining_fuel(balance, vm_runtime)
-            .min(maximum_fuel_per_block.saturating_sub(fuel))
+        self.remaining_fuel_detailed(vm_runtime).effective
     }
 
-    /// Tracks the allocation of a grant.
+    /// Returns the amount of fuel that could still be spent, together with the two bounds
+    /// it is the minimum of, to show which one is binding.
+    pub fn remaining_fuel_detailed(&self, vm_runtime: VmRuntime) -> RemainingFuel {
+        let balance = self
+            .balance_for_fees()
+            .unwrap_or(Amount::MAX)
+            .saturating_sub(self.pending_fees());
+        let tracker = self.tracker.as_ref();
+        let fuel = tracker.fuel(vm_runtime);
+        let by_cap = if self.settings.analytics_mode {
+            u64::MAX
+        } else {
+            self.policy
+                .maximum_fuel_per_block(vm_runtime)
+                .saturating_sub(fuel)
+        };
+        let by_balance = self.policy.affordable_fuel(balance, vm_runtime);
+        RemainingFuel {
+            by_balance,
+            by_cap,
+            effective: by_balance.min(by_cap),
+        }
+    }
+
+    /// Counts a charge rejected for a lack of funds, and fails once the policy's
+    /// `maximum_failed_charge_attempts` is exceeded.
+    fn record_failed_charge_attempt(&mut self) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        tracker.failed_charge_attempts = tracker.failed_charge_attempts.saturating_add(1);
+        self.settings.check_limit(
+            u64::from(tracker.failed_charge_attempts) <= self.policy.maximum_failed_charge_attempts,
+            ExecutionError::TooManyFailedChargeAttempts,
+        )
+    }
+
+    /// Fails with [`ExecutionError::FeesExceedFunding`] if strict mode is enabled and the
+    /// account is already empty, before any counter is updated. Fee-exempt accounts never
+    /// fail this check.
+    fn check_strict_empty_account(&self) -> Result<(), ExecutionError> {
+        if self.settings.strict_empty_account && !self.fee_exempt {
+            let balance = self.balance()?;
+            ensure!(
+                balance > Amount::ZERO,
+                ExecutionError::FeesExceedFunding {
+                    fees: Amount::ZERO,
+                    balance,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Tracks the allocation of a grant in the policy's grant denomination. It is
+    /// converted into native tokens, rounding up, before being deducted.
     pub fn track_grant(&mut self, grant: Amount) -> Result<(), ExecutionError> {
+        self.allocate_grant(grant)?;
+        Ok(())
+    }
+
+    /// Converts a grant in the policy's grant denomination into native tokens, rounding up,
+    /// and deducts it from the balance. Returns the grant in native tokens.
+    fn allocate_grant(&mut self, grant: Amount) -> Result<Amount, ExecutionError> {
+        let grant = self.policy.grant_in_native_tokens(grant)?;
+        self.allocate_native_grant(grant)?;
+        Ok(grant)
+    }
+
+    /// Deducts a grant that is already in native tokens from the balance.
+    fn allocate_native_grant(&mut self, grant: Amount) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().grants.try_add_assign(grant)?;
-        self.update_balance(grant)
+        self.update_balance(ChargeKind::Fee("grant"), 1, grant)
+    }
+
+    /// Tracks the allocation of a grant in the policy's grant denomination to the outgoing
+    /// message with the given index. Returns the grant in native tokens, which is what the
+    /// message carries.
+    pub fn track_grant_for(
+        &mut self,
+        message_index: usize,
+        grant: Amount,
+    ) -> Result<Amount, ExecutionError> {
+        let grant = self.policy.grant_in_native_tokens(grant)?;
+        self.track_native_grant_for(message_index, grant)?;
+        Ok(grant)
+    }
+
+    /// Like [`Self::track_grant_for`], but for a grant that is already in native tokens,
+    /// e.g. the price of the resources granted to a message.
+    pub fn track_native_grant_for(
+        &mut self,
+        message_index: usize,
+        grant: Amount,
+    ) -> Result<(), ExecutionError> {
+        self.allocate_native_grant(grant)?;
+        let grants = &mut self.tracker.as_mut().grants_by_message;
+        match grants.iter_mut().find(|(index, _)| *index == message_index) {
+            Some((_, granted)) => granted.try_add_assign(grant)?,
+            None => grants.push((message_index, grant)),
+        }
+        Ok(())
+    }
+
+    /// Refunds part of the grant of the outgoing message with the given index to the
+    /// account. The refund may not exceed what is left of that message's grant.
+    pub fn refund_grant_for(
+        &mut self,
+        message_index: usize,
+        refund: Amount,
+    ) -> Result<(), ExecutionError> {
+        let remaining = self
+            .tracker
+            .as_ref()
+            .grants_by_message
+            .iter()
+            .find(|(index, _)| *index == message_index)
+            .and_then(|(_, granted)| granted.try_sub(refund).ok())
+            .ok_or(ExecutionError::RefundExceedsCharges)?;
+        self.credit(refund)?;
+        let tracker = self.tracker.as_mut();
+        tracker.grants.try_sub_assign(refund)?;
+        for (index, granted) in &mut tracker.grants_by_message {
+            if *index == message_index {
+                *granted = remaining;
+            }
+        }
+        Ok(())
+    }
+
+    /// Refunds the unused part of the grant of a message whose execution failed, given the
+    /// fees that were charged to the grant before the failure. Fees beyond the grant were
+    /// paid by the account itself and are not refunded. Returns the amount refunded.
+    pub fn on_message_failure(
+        &mut self,
+        grant: Amount,
+        charged_to_grant: Amount,
+    ) -> Result<Amount, ExecutionError> {
+        let refund = grant.saturating_sub(charged_to_grant);
+        if refund == Amount::ZERO {
+            return Ok(refund);
+        }
+        self.credit(refund)?;
+        self.tracker.as_mut().grants.try_sub_assign(refund)?;
+        Ok(refund)
+    }
+
+    /// Runs `f` with a temporary controller funded by `grant`, e.g. to execute a message.
+    /// The grant, rounded up to the policy's `fee_increment`, is debited from the account up
+    /// front, and the unspent part of it is credited back on success. If `f` fails, nothing
+    /// is refunded and the error is returned. Only the charges made by `f` are recorded in
+    /// the event log, not the grant itself.
+    pub fn execute_with_grant<T>(
+        &mut self,
+        grant: Amount,
+        f: impl FnOnce(
+            &mut ResourceController<Amount, &mut ResourceTracker>,
+        ) -> Result<T, ExecutionError>,
+    ) -> Result<T, ExecutionError> {
+        let tracker = self.tracker.as_ref();
+        let (debited, capped_fees) = (tracker.debited, tracker.capped_fees);
+        let grant = self.policy.round_up_fees(grant)?;
+        if !self.fee_exempt {
+            self.debit(grant)?;
+        }
+        // The fees paid from the grant were already debited with it. Only count them once,
+        // while still checking them against the fee cap as they are charged.
+        let tracker = self.tracker.as_mut();
+        let with_grant = (tracker.debited, tracker.capped_fees);
+        (tracker.debited, tracker.capped_fees) = (debited, capped_fees);
+        let mut controller = ResourceController {
+            policy: self.policy.clone(),
+            tracker: &mut *tracker,
+            account: grant,
+            fee_exempt: false,
+            settings: self.settings.clone(),
+        };
+        let result = f(&mut controller);
+        let remainder = controller.account;
+        (tracker.debited, tracker.capped_fees) = with_grant;
+        let value = result?;
+        self.credit(remainder)?;
+        Ok(value)
+    }
+
+    /// Runs `f` with a temporary controller funded by `budget`, and returns its result
+    /// together with what is left of the budget. Resource usage is recorded in this
+    /// controller's tracker, and the part of the budget that was spent is then taken from the
+    /// account. Spending more than the budget, or than the account holds, fails with
+    /// [`ExecutionError::FeesExceedFunding`]. If anything fails, the tracker and the account
+    /// are left unchanged.
+    pub fn with_budget<T>(
+        &mut self,
+        budget: Amount,
+        f: impl FnOnce(
+            &mut ResourceController<Amount, &mut ResourceTracker>,
+        ) -> Result<T, ExecutionError>,
+    ) -> Result<(T, Amount), ExecutionError> {
+        let snapshot = self.tracker.as_ref().clone();
+        let result = self.run_with_budget(budget, f);
+        if result.is_err() {
+            *self.tracker.as_mut() = snapshot;
+        }
+        result
+    }
+
+    /// Runs [`Self::with_budget`], without restoring the tracker on error.
+    fn run_with_budget<T>(
+        &mut self,
+        budget: Amount,
+        f: impl FnOnce(
+            &mut ResourceController<Amount, &mut ResourceTracker>,
+        ) -> Result<T, ExecutionError>,
+    ) -> Result<(T, Amount), ExecutionError> {
+        let mut controller = ResourceController {
+            policy: self.policy.clone(),
+            tracker: self.tracker.as_mut(),
+            account: budget,
+            fee_exempt: self.fee_exempt,
+            settings: self.settings.clone(),
+        };
+        let value = f(&mut controller)?;
+        let leftover = controller.account;
+        // The fees were already counted as debited when they were charged to the budget.
+        let spent = budget.try_sub(leftover)?;
+        let paid_by_owner = self.account.try_sub_assign_by_owner(spent).map_err(|_| {
+            ExecutionError::FeesExceedFunding {
+                fees: spent,
+                balance: self.balance().unwrap_or(Amount::MAX),
+            }
+        })?;
+        self.tracker.as_mut().record_payments(paid_by_owner);
+        Ok((value, leftover))
+    }
+
+    /// Charges all the given resources, or none of them if any charge fails, e.g. because
+    /// of a limit or a lack of funds.
+    pub fn charge_all(&mut self, items: &[Charge]) -> Result<(), ExecutionError> {
+        // Apply the charges to copies of the tracker and the balance first, so that nothing
+        // changes on error.
+        let balance = self.balance()?;
+        let mut tracker = self.tracker.as_ref().clone();
+        let mut controller = ResourceController {
+            policy: self.policy.clone(),
+            tracker: &mut tracker,
+            account: balance,
+            fee_exempt: self.fee_exempt,
+            settings: self.settings.clone(),
+        };
+        for item in items {
+            controller.charge_item(item)?;
+        }
+        let fees = balance.try_sub(controller.account)?;
+        let paid_by_owner = self.account.try_sub_assign_by_owner(fees)?;
+        tracker.record_payments(paid_by_owner);
+        *self.tracker.as_mut() = tracker;
+        Ok(())
+    }
+
+    /// Tracks a single item of [`Self::charge_all`].
+    fn charge_item(&mut self, item: &Charge) -> Result<(), ExecutionError> {
+        match item {
+            Charge::Fuel(fuel, vm_runtime) => self.track_fuel(*fuel, *vm_runtime),
+            Charge::ReadOperations(count) => self.track_read_operations(*count),
+            Charge::WriteOperations(count) => self.track_write_operations(*count),
+            Charge::BytesRead(count) => self.track_bytes_read(*count),
+            Charge::BytesWritten(count) => self.track_bytes_written(*count),
+            Charge::Message(chain_id, message) => self.track_message(*chain_id, message),
+        }
+    }
+
+    /// Checks that an operation could be tracked by [`Self::track_operation`] without
+    /// overflowing the counters or exceeding the balance. Nothing is charged.
+    pub fn check_operation_fits(&self, operation: &Operation) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_ref();
+        tracker
+            .operations
+            .checked_add(1)
+            .ok_or(ArithmeticError::Overflow)?;
+        let size = self.priced_operation_size(operation)?;
+        if let Some(size) = size {
+            tracker
+                .operation_bytes
+                .checked_add(size)
+                .ok_or(ArithmeticError::Overflow)?;
+        }
+        if tracker.warmup_used < self.policy.warmup_operations {
+            return Ok(());
+        }
+        let fees = self
+            .computed_operation_fees(operation.discriminant(), size)?
+            .max(self.policy.minimum_operation_fee);
+        let balance = self.balance_for_fees()?;
+        ensure!(
+            fees <= balance,
+            ExecutionError::FeesExceedFunding { fees, balance }
+        );
+        Ok(())
     }
 
     /// Tracks the execution of an operation in block.
     pub fn track_operation(&mut self, operation: &Operation) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        if let Operation::User { application_id, .. } = operation {
+            self.track_application(*application_id);
+        }
         self.tracker.as_mut().operations = self
             .tracker
             .as_mut()
             .operations
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.operation)?;
-        match operation {
-            Operation::System(_) => Ok(()),
-            Operation::User { bytes, .. } => {
-                let size = bytes.len();
-                self.tracker.as_mut().operation_bytes = self
-                    .tracker
-                    .as_mut()
-                    .operation_bytes
-                    .checked_add(size as u64)
-                    .ok_or(ArithmeticError::Overflow)?;
-                self.update_balance(self.policy.operation_bytes_price(size as u64)?)?;
-                Ok(())
+        let discriminant = operation.discriminant();
+        *self
+            .tracker
+            .as_mut()
+            .operations_by_type
+            .entry(discriminant)
+            .or_default() += 1;
+        let size = self.priced_operation_size(operation)?;
+        if let Some(size) = size {
+            self.tracker.as_mut().operation_bytes = self
+                .tracker
+                .as_mut()
+                .operation_bytes
+                .checked_add(size)
+                .ok_or(ArithmeticError::Overflow)?;
+        }
+        // The first operations are free during the policy's warmup window.
+        let tracker = self.tracker.as_mut();
+        if tracker.warmup_used < self.policy.warmup_operations {
+            tracker.warmup_used += 1;
+            return Ok(());
+        }
+        if self.policy.unit_price(ResourceKind::Operations).is_some() {
+            let unit_price = self.policy.operation_price(discriminant);
+            self.charge_resource_with_base(ResourceKind::Operations, 1, unit_price, unit_price)?;
+        }
+        if let Some(size) = size {
+            self.charge_resource(ResourceKind::OperationBytes, size)?;
+        }
+        // Top up the fees of cheap operations to the minimum.
+        let computed_fees = self.computed_operation_fees(discriminant, size)?;
+        if let Ok(surcharge) = self.policy.minimum_operation_fee.try_sub(computed_fees) {
+            if surcharge > Amount::ZERO {
+                self.update_balance(ChargeKind::Fee("minimum_operation_fee"), 1, surcharge)?;
+                let tracker = self.tracker.as_mut();
+                tracker.minimum_fee_surcharges.try_add_assign(surcharge)?;
+                tracker.record_charge(ResourceKind::Operations, surcharge);
             }
         }
+        Ok(())
+    }
+
+    /// Charges the flat base fee of the policy for a transaction, independently of the
+    /// resources it uses. The fee is charged at most once per transaction: further calls
+    /// do nothing and succeed until [`Self::start_transaction`] is called.
+    pub fn track_transaction_base(&mut self) -> Result<(), ExecutionError> {
+        if self.tracker.as_ref().base_fee_charged {
+            return Ok(());
+        }
+        let fee = self.policy.base_transaction_fee;
+        if fee > Amount::ZERO {
+            self.check_strict_empty_account()?;
+            self.update_balance(ChargeKind::Fee("base_transaction_fee"), 1, fee)?;
+        }
+        let tracker = self.tracker.as_mut();
+        tracker.base_fees.try_add_assign(fee)?;
+        tracker.base_fee_charged = true;
+        Ok(())
+    }
+
+    /// Tracks the start of a nested application call, failing if this exceeds the policy's
+    /// maximum call depth. Unlike the per-block limits, this is enforced in analytics mode
+    /// too, since it protects the execution itself.
+    pub fn enter_call(&mut self) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        let depth = tracker
+            .call_depth
+            .checked_add(1)
+            .ok_or(ArithmeticError::Overflow)?;
+        ensure!(
+            u64::from(depth) <= self.policy.maximum_call_depth,
+            ExecutionError::CallDepthExceeded
+        );
+        tracker.call_depth = depth;
+        tracker.max_call_depth_reached = tracker.max_call_depth_reached.max(depth);
+        Ok(())
+    }
+
+    /// Tracks the end of a nested application call started with [`Self::enter_call`]. Fails
+    /// if there is no such call.
+    pub fn exit_call(&mut self) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        tracker.call_depth = tracker
+            .call_depth
+            .checked_sub(1)
+            .ok_or(ArithmeticError::Underflow)?;
+        Ok(())
+    }
+
+    /// Returns the fees for an operation of the given type with `size` priced bytes, before
+    /// applying the policy's `minimum_operation_fee`.
+    fn computed_operation_fees(
+        &self,
+        discriminant: OperationDiscriminant,
+        size: Option<u64>,
+    ) -> Result<Amount, ExecutionError> {
+        let mut fees = if self.policy.unit_price(ResourceKind::Operations).is_some() {
+            self.policy.operation_price(discriminant)
+        } else {
+            Amount::ZERO
+        };
+        if let Some(size) = size {
+            fees.try_add_assign(self.policy.operation_bytes_price(size)?)?;
+        }
+        Ok(fees)
     }
 
-    /// Tracks the creation of an outgoing message.
-    pub fn track_message(&mut self, message: &Message) -> Result<(), ExecutionError> {
+    /// Returns the number of bytes of an operation that are priced: the whole serialized
+    /// operation if the policy says so, otherwise the argument of a user operation only.
+    fn priced_operation_size(&self, operation: &Operation) -> Result<Option<u64>, ExecutionError> {
+        if self.policy.price_operations_by_serialized_size {
+            return Ok(Some(bcs::serialized_size(operation)? as u64));
+        }
+        match operation {
+            Operation::System(_) => Ok(None),
+            Operation::User { .. } => Ok(Some(operation_size(operation) as u64)),
+        }
+    }
+
+    /// Records that the given application is executed in this block. System operations are
+    /// not attributed to any application.
+    pub(crate) fn track_application(&mut self, application_id: ApplicationId) {
+        self.tracker
+            .as_mut()
+            .applications_executed
+            .insert(application_id);
+    }
+
+    /// Tracks the creation of an outgoing message by the chain with the given ID.
+    pub fn track_message(
+        &mut self,
+        chain_id: ChainId,
+        message: &OutgoingMessage,
+    ) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().messages = self
             .tracker
             .as_mut()
             .messages
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.message)?;
-        match message {
+        self.charge_resource(ResourceKind::Messages, 1)?;
+        // Messages are delivered directly to their destination, so they make one hop unless
+        // they stay on the sending chain.
+        let hops = u64::from(message.destination != chain_id);
+        if hops > 0 {
+            self.tracker.as_mut().outgoing_message_hops = self
+                .tracker
+                .as_mut()
+                .outgoing_message_hops
+                .checked_add(hops)
+                .ok_or(ArithmeticError::Overflow)?;
+            self.charge_resource(ResourceKind::MessageHops, hops)?;
+        }
+        match &message.message {
             Message::System(_) => Ok(()),
-            Message::User { bytes, .. } => {
-                let size = bytes.len();
+            Message::User { .. } => {
+                let size = message_size(&message.message);
                 self.tracker.as_mut().message_bytes = self
                     .tracker
                     .as_mut()
                     .message_bytes
                     .checked_add(size as u64)
                     .ok_or(ArithmeticError::Overflow)?;
-                self.update_balance(self.policy.message_bytes_price(size as u64)?)?;
+                self.charge_resource(ResourceKind::MessageBytes, size as u64)?;
                 Ok(())
             }
         }
     }
 
-    /// Tracks the execution of an HTTP request.
-    pub fn track_http_request(&mut self) -> Result<(), ExecutionError> {
-        self.tracker.as_mut().http_requests = self
+    /// Registers a custom resource, to be tracked with [`Self::track_custom`] and charged
+    /// and limited like the resources of the policy.
+    pub fn register_custom_resource(
+        &mut self,
+        name: String,
+        unit_price: Amount,
+        max_per_block: u64,
+    ) -> CustomResourceId {
+        let custom_resources = Arc::make_mut(&mut self.settings.custom_resources);
+        let id = CustomResourceId(
+            u32::try_from(custom_resources.len()).expect("too many custom resources"),
+        );
+        custom_resources.push(CustomResource {
+            name,
+            unit_price,
+            max_per_block,
+        });
+        id
+    }
+
+    /// Tracks a number of units of a custom resource. They are charged as
+    /// [`ResourceKind::Custom`], unless that kind is one of the policy's uncharged
+    /// resources. Nothing is counted if the limit is exceeded or the charge fails.
+    pub fn track_custom(&mut self, id: CustomResourceId, count: u64) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        let tracker = self.tracker.as_ref();
+        let resource = usize::try_from(id.0)
+            .ok()
+            .and_then(|index| self.settings.custom_resources.get(index))
+            .ok_or(ExecutionError::UnknownCustomResource(id))?;
+        let used = tracker
+            .custom_resource_counts
+            .get(&id)
+            .copied()
+            .unwrap_or_default()
+            .checked_add(count)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.settings.check_limit(
+            used <= resource.max_per_block,
+            ExecutionError::MaximumCustomResourceExceeded(resource.name.clone()),
+        )?;
+        let unit_price = resource.unit_price;
+        if !self
+            .policy
+            .uncharged_resources
+            .contains(&ResourceKind::Custom)
+        {
+            let fees = ResourceControlPolicy::price_for(unit_price, count)?;
+            self.charge_resource_with_base(ResourceKind::Custom, count, unit_price, fees)?;
+        }
+        self.tracker
+            .as_mut()
+            .custom_resource_counts
+            .insert(id, used);
+        Ok(())
+    }
+
+    /// Charges a one-off fee that is not tied to a tracked resource, e.g. a registration
+    /// fee. The label and the amount are recorded in the tracker.
+    pub fn charge(&mut self, label: &'static str, amount: Amount) -> Result<(), ExecutionError> {
+        self.update_balance(ChargeKind::Fee(label), 1, amount)?;
+        self.tracker.as_mut().custom_charges.push((label, amount));
+        Ok(())
+    }
+
+    /// Tracks the execution of an HTTP request.
+    pub fn track_http_request(&mut self) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        self.tracker.as_mut().http_requests = self
+            .tracker
+            .as_ref()
+            .http_requests
+            .checked_add(1)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.charge_resource(ResourceKind::HttpRequests, 1)
+    }
+
+    /// Tracks the execution of an HTTP request with a body of `request_bytes` bytes.
+    pub fn track_http_request_with_body(
+        &mut self,
+        request_bytes: usize,
+    ) -> Result<(), ExecutionError> {
+        self.track_http_request()?;
+        let size = request_bytes as u64;
+        let tracker = self.tracker.as_mut();
+        tracker.http_request_bytes = tracker
+            .http_request_bytes
+            .checked_add(size)
+            .ok_or(ArithmeticError::Overflow)?;
+        self.settings.check_limit(
+            tracker.http_request_bytes <= self.policy.maximum_http_request_bytes_per_block,
+            ExecutionError::ExcessiveHttpRequestBytes,
+        )?;
+        self.charge_resource(ResourceKind::HttpRequestBytes, size)
+    }
+
+    /// Tracks the body of an HTTP response.
+    pub fn track_http_response(&mut self, size: usize) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        let size = size as u64;
+        self.tracker.as_mut().http_response_bytes = self
             .tracker
             .as_ref()
-            .http_requests
-            .checked_add(1)
+            .http_response_bytes
+            .checked_add(size)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.http_request)
+        self.charge_resource(ResourceKind::HttpResponseBytes, size)
     }
 
     /// Tracks a number of fuel units used.
@@ -294,6 +2240,64 @@ where
         fuel: u64,
         vm_runtime: VmRuntime,
     ) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        // Report a lack of funds before the fuel limit, so that the VM can tell why it halted.
+        let needed = self.policy.fuel_price(fuel, vm_runtime)?;
+        let balance = self.balance_for_fees()?;
+        ensure!(
+            needed <= balance,
+            ExecutionError::InsufficientFundsForFuel {
+                fuel,
+                needed,
+                balance
+            }
+        );
+        self.add_fuel(fuel, vm_runtime)?;
+        self.charge_resource(fuel_kind(vm_runtime), fuel)
+    }
+
+    /// Tracks as many of the `requested` fuel units as the balance and the per-block limit
+    /// allow, and returns how many were granted. Instead of failing when the budget runs
+    /// out, this lets the VM halt after using the granted fuel.
+    pub fn track_fuel_best_effort(
+        &mut self,
+        requested: u64,
+        vm_runtime: VmRuntime,
+    ) -> Result<u64, ExecutionError> {
+        self.check_strict_empty_account()?;
+        let balance = self.balance_for_fees().unwrap_or(Amount::MAX);
+        if requested > self.policy.affordable_fuel(balance, vm_runtime) {
+            self.record_failed_charge_attempt()?;
+        }
+        let granted = requested.min(self.remaining_fuel(vm_runtime));
+        self.add_fuel(granted, vm_runtime)?;
+        self.charge_resource(fuel_kind(vm_runtime), granted)?;
+        Ok(granted)
+    }
+
+    /// Charges for all the fuel that could still be used with the given VM, and returns a
+    /// [`FuelReservation`] to settle the actual usage with.
+    pub fn reserve_fuel(
+        &mut self,
+        vm_runtime: VmRuntime,
+    ) -> Result<FuelReservation<'_, Account, Tracker>, ExecutionError> {
+        let fuel = self.remaining_fuel(vm_runtime);
+        let price = self.policy.fuel_price(fuel, vm_runtime)?;
+        self.update_balance(ChargeKind::Resource(fuel_kind(vm_runtime)), fuel, price)?;
+        self.tracker
+            .as_mut()
+            .record_charge(fuel_kind(vm_runtime), price);
+        Ok(FuelReservation {
+            controller: self,
+            vm_runtime,
+            fuel,
+            price,
+            settled: false,
+        })
+    }
+
+    /// Adds a number of fuel units to the tracker without charging for them.
+    fn add_fuel(&mut self, fuel: u64, vm_runtime: VmRuntime) -> Result<(), ExecutionError> {
         match vm_runtime {
             VmRuntime::Wasm => {
                 self.tracker.as_mut().wasm_fuel = self
@@ -302,10 +2306,11 @@ where
                     .wasm_fuel
                     .checked_add(fuel)
                     .ok_or(ArithmeticError::Overflow)?;
-                ensure!(
-                    self.tracker.as_ref().wasm_fuel <= self.policy.maximum_wasm_fuel_per_block,
-                    ExecutionError::MaximumFuelExceeded(vm_runtime)
-                );
+                let tracker = self.tracker.as_ref();
+                self.settings.check_limit(
+                    tracker.wasm_fuel <= self.policy.maximum_wasm_fuel_per_block,
+                    ExecutionError::MaximumFuelExceeded(vm_runtime),
+                )?;
             }
             VmRuntime::Evm => {
                 self.tracker.as_mut().evm_fuel = self
@@ -314,13 +2319,14 @@ where
                     .evm_fuel
                     .checked_add(fuel)
                     .ok_or(ArithmeticError::Overflow)?;
-                ensure!(
-                    self.tracker.as_ref().evm_fuel <= self.policy.maximum_evm_fuel_per_block,
-                    ExecutionError::MaximumFuelExceeded(vm_runtime)
-                );
+                let tracker = self.tracker.as_ref();
+                self.settings.check_limit(
+                    tracker.evm_fuel <= self.policy.maximum_evm_fuel_per_block,
+                    ExecutionError::MaximumFuelExceeded(vm_runtime),
+                )?;
             }
         }
-        self.update_balance(self.policy.fuel_price(fuel, vm_runtime)?)
+        Ok(())
     }
 
     /// Tracks runtime reading of `ChainId`
@@ -352,6 +2358,16 @@ where
         self.track_size_runtime_operations(RUNTIME_TIMESTAMP_SIZE)
     }
 
+    /// Tracks runtime reading of `n` `Timestamp`s at once.
+    pub fn track_runtime_timestamps(&mut self, n: usize) -> Result<(), ExecutionError> {
+        self.track_size_runtime_operations(runtime_size_of_many(n, RUNTIME_TIMESTAMP_SIZE)?)
+    }
+
+    /// Tracks runtime reading of `n` `BlockHeight`s at once.
+    pub fn track_runtime_block_heights(&mut self, n: usize) -> Result<(), ExecutionError> {
+        self.track_size_runtime_operations(runtime_size_of_many(n, RUNTIME_BLOCK_HEIGHT_SIZE)?)
+    }
+
     /// Tracks runtime reading of balance
     pub(crate) fn track_runtime_balance(&mut self) -> Result<(), ExecutionError> {
         self.track_size_runtime_operations(RUNTIME_AMOUNT_SIZE)
@@ -397,91 +2413,326 @@ where
         self.track_size_runtime_operations(size)
     }
 
+    /// Tracks runtime reading of any value, according to its serialized size.
+    pub fn track_runtime_value<T: Serialize>(&mut self, value: &T) -> Result<(), ExecutionError> {
+        let size =
+            u32::try_from(bcs::serialized_size(value)?).map_err(|_| ArithmeticError::Overflow)?;
+        self.track_size_runtime_operations(size)
+    }
+
     /// Tracks runtime operations.
     fn track_size_runtime_operations(&mut self, size: u32) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().bytes_runtime = self
             .tracker
             .as_mut()
             .bytes_runtime
             .checked_add(size)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.bytes_runtime_price(size)?)
+        let tracker = self.tracker.as_ref();
+        self.settings.check_limit(
+            u64::from(tracker.bytes_runtime) <= self.policy.maximum_bytes_runtime_per_block,
+            ExecutionError::ExcessiveRuntimeReads,
+        )?;
+        self.charge_resource(ResourceKind::RuntimeBytes, size.into())
     }
 
     /// Tracks a read operation.
     pub(crate) fn track_read_operation(&mut self) -> Result<(), ExecutionError> {
+        self.track_read_operations(1)
+    }
+
+    /// Tracks a number of read operations.
+    pub(crate) fn track_read_operations(&mut self, count: u32) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().read_operations = self
             .tracker
             .as_mut()
             .read_operations
-            .checked_add(1)
+            .checked_add(count)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.read_operations_price(1)?)
+        self.charge_resource(ResourceKind::ReadOperations, count.into())
     }
 
     /// Tracks a write operation.
     pub(crate) fn track_write_operations(&mut self, count: u32) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().write_operations = self
             .tracker
             .as_mut()
             .write_operations
             .checked_add(count)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.write_operations_price(count)?)
+        self.charge_resource(ResourceKind::WriteOperations, count.into())
     }
 
     /// Tracks a number of bytes read.
     pub(crate) fn track_bytes_read(&mut self, count: u64) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().bytes_read = self
             .tracker
             .as_mut()
             .bytes_read
             .checked_add(count)
             .ok_or(ArithmeticError::Overflow)?;
-        if self.tracker.as_mut().bytes_read >= self.policy.maximum_bytes_read_per_block {
-            return Err(ExecutionError::ExcessiveRead);
+        let tracker = self.tracker.as_mut();
+        self.settings.check_limit(
+            tracker.bytes_read < self.policy.maximum_bytes_read_per_block,
+            ExecutionError::ExcessiveRead,
+        )?;
+        let granularity = self.policy.read_charge_granularity;
+        if granularity == 0 {
+            return self.charge_resource(ResourceKind::BytesRead, count);
+        }
+        // Only charge whole multiples of the granularity, and keep the rest for later.
+        let tracker = self.tracker.as_mut();
+        let pending = tracker
+            .pending_bytes_read
+            .checked_add(count)
+            .ok_or(ArithmeticError::Overflow)?;
+        tracker.pending_bytes_read = pending % granularity;
+        let charged = pending - tracker.pending_bytes_read;
+        if charged > 0 {
+            self.charge_resource(ResourceKind::BytesRead, charged)?;
+        }
+        Ok(())
+    }
+
+    /// Charges the bytes read that are still pending because of the policy's
+    /// `read_charge_granularity`. This must be called at the end of each user action, so
+    /// that the account that read the bytes pays for them.
+    pub fn flush_pending_charges(&mut self) -> Result<(), ExecutionError> {
+        let pending = std::mem::take(&mut self.tracker.as_mut().pending_bytes_read);
+        if pending > 0 {
+            self.charge_resource(ResourceKind::BytesRead, pending)?;
         }
-        self.update_balance(self.policy.bytes_read_price(count)?)?;
         Ok(())
     }
 
     /// Tracks a number of bytes written.
     pub(crate) fn track_bytes_written(&mut self, count: u64) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().bytes_written = self
             .tracker
             .as_mut()
             .bytes_written
             .checked_add(count)
             .ok_or(ArithmeticError::Overflow)?;
-        if self.tracker.as_mut().bytes_written >= self.policy.maximum_bytes_written_per_block {
-            return Err(ExecutionError::ExcessiveWrite);
+        let tracker = self.tracker.as_mut();
+        self.settings.check_limit(
+            tracker.bytes_written < self.policy.maximum_bytes_written_per_block,
+            ExecutionError::ExcessiveWrite,
+        )?;
+        self.charge_resource(ResourceKind::BytesWritten, count)?;
+        Ok(())
+    }
+
+    /// Tracks a number of bytes deserialized into typed values. This is charged in addition
+    /// to reading the bytes, for the work of decoding them.
+    pub fn track_deserialization(&mut self, bytes: u64) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        self.tracker.as_mut().deserialized_bytes = self
+            .tracker
+            .as_mut()
+            .deserialized_bytes
+            .checked_add(bytes)
+            .ok_or(ArithmeticError::Overflow)?;
+        let tracker = self.tracker.as_mut();
+        self.settings.check_limit(
+            tracker.deserialized_bytes < self.policy.maximum_bytes_deserialized_per_block,
+            ExecutionError::ExcessiveDeserialization,
+        )?;
+        let fees = self.policy.deserialization_price(bytes)?;
+        let unit_price = self.policy.byte_deserialized;
+        self.charge_resource_with_base(ResourceKind::DeserializedBytes, bytes, unit_price, fees)
+    }
+
+    /// Tracks one write operation storing `value` under `key`. The bytes written are the
+    /// key and the serialized value.
+    pub fn track_write_value<T: Serialize>(
+        &mut self,
+        key: &[u8],
+        value: &T,
+    ) -> Result<(), ExecutionError> {
+        let size = key.len() + bcs::serialized_size(value)?;
+        self.track_write_operations(1)?;
+        self.track_bytes_written(size as u64)
+    }
+
+    /// Tracks the writes of a batch before it is committed, and records them as the
+    /// estimate to be reconciled by [`Self::settle_writes`].
+    pub fn estimate_writes(&mut self, batch: &Batch) -> Result<(), ExecutionError> {
+        let (operations, bytes) = batch_writes(batch)?;
+        let debited = self.tracker.as_ref().debited;
+        self.track_write_operations(operations)?;
+        let debited_for_operations = self.tracker.as_ref().debited;
+        self.track_bytes_written(bytes)?;
+        let tracker = self.tracker.as_mut();
+        tracker.write_estimate = Some(WriteEstimate {
+            operations,
+            bytes,
+            operation_fees: debited_for_operations.saturating_sub(debited),
+            byte_fees: tracker.debited.saturating_sub(debited_for_operations),
+        });
+        Ok(())
+    }
+
+    /// Reconciles the writes estimated by the last call to [`Self::estimate_writes`] with the
+    /// batch that is actually committed. Writes missing from the estimate are charged, and
+    /// estimated writes that are not committed are refunded. A refund never exceeds the fees
+    /// debited for the estimate, so surcharges such as the minimum operation fee and fees
+    /// that were not debited, e.g. for an exempt account, are never refunded.
+    pub fn settle_writes(&mut self, batch: &Batch) -> Result<(), ExecutionError> {
+        let (operations, bytes) = batch_writes(batch)?;
+        let estimate = self
+            .tracker
+            .as_mut()
+            .write_estimate
+            .take()
+            .unwrap_or_default();
+        let extra_operations = estimate.operations.saturating_sub(operations);
+        let extra_bytes = estimate.bytes.saturating_sub(bytes);
+        let missing_operations = operations.saturating_sub(estimate.operations);
+        let missing_bytes = bytes.saturating_sub(estimate.bytes);
+        let operations_refund = self
+            .policy
+            .write_operations_price(extra_operations)?
+            .min(estimate.operation_fees);
+        let bytes_refund = self
+            .policy
+            .bytes_written_price(extra_bytes)?
+            .min(estimate.byte_fees);
+        let refund = operations_refund.try_add(bytes_refund)?;
+        if refund > Amount::ZERO {
+            self.credit(refund)?;
+        }
+        let tracker = self.tracker.as_mut();
+        tracker.write_operations -= extra_operations;
+        tracker.bytes_written -= extra_bytes;
+        tracker.record_refund(ResourceKind::WriteOperations, operations_refund);
+        tracker.record_refund(ResourceKind::BytesWritten, bytes_refund);
+        if missing_operations > 0 {
+            self.track_write_operations(missing_operations)?;
+        }
+        if missing_bytes > 0 {
+            self.track_bytes_written(missing_bytes)?;
         }
-        self.update_balance(self.policy.bytes_written_price(count)?)?;
         Ok(())
     }
 
-    /// Tracks a number of blob bytes written.
+    /// Tracks a blob read of `count` bytes.
     pub(crate) fn track_blob_read(&mut self, count: u64) -> Result<(), ExecutionError> {
+        self.track_blobs_read(&[count])
+    }
+
+    /// Tracks a batch of blob reads with the given sizes. This costs the same as reading
+    /// the blobs one by one, but checks the limit and charges the balance only once.
+    pub(crate) fn track_blobs_read(&mut self, sizes: &[u64]) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        let count = u32::try_from(sizes.len()).map_err(|_| ArithmeticError::Overflow)?;
+        let total_bytes = sizes
+            .iter()
+            .try_fold(0u64, |total, size| total.checked_add(*size))
+            .ok_or(ArithmeticError::Overflow)?;
         {
             let tracker = self.tracker.as_mut();
             tracker.blob_bytes_read = tracker
                 .blob_bytes_read
-                .checked_add(count)
+                .checked_add(total_bytes)
                 .ok_or(ArithmeticError::Overflow)?;
             tracker.blobs_read = tracker
                 .blobs_read
-                .checked_add(1)
+                .checked_add(count)
                 .ok_or(ArithmeticError::Overflow)?;
+            self.settings.check_limit(
+                tracker.blob_bytes_read <= self.policy.maximum_blob_bytes_read_per_block,
+                ExecutionError::ExcessiveBlobBytesRead,
+            )?;
+        }
+        if sizes.is_empty() {
+            return Ok(());
+        }
+        self.charge_resource_with_base(
+            ResourceKind::BlobBytesRead,
+            total_bytes,
+            self.policy
+                .unit_price(ResourceKind::BlobBytesRead)
+                .unwrap_or(Amount::ZERO),
+            self.policy
+                .price_of(ResourceKind::BlobBytesRead, total_bytes)?
+                .try_add(ResourceControlPolicy::price_for(
+                    self.policy.blob_read,
+                    count.into(),
+                )?)?
+                .try_add(ResourceControlPolicy::price_for(
+                    self.policy.blob_read_fixed,
+                    count.into(),
+                )?)?,
+        )?;
+        Ok(())
+    }
+
+    /// Locks the refundable deposit for publishing the given blob. Committee blobs and
+    /// fee-exempt accounts pay no deposit. Returns the deposit, to be recorded in the chain
+    /// state.
+    pub fn track_blob_deposit(&mut self, blob: &Blob) -> Result<Amount, ExecutionError> {
+        self.check_strict_empty_account()?;
+        if self.fee_exempt {
+            return Ok(Amount::ZERO);
+        }
+        let deposit = self.blob_deposit(blob)?;
+        if deposit == Amount::ZERO {
+            return Ok(deposit);
         }
-        self.update_balance(self.policy.blob_read_price(count)?)?;
+        self.update_balance(ChargeKind::Fee("blob_deposit"), 1, deposit)?;
+        self.tracker
+            .as_mut()
+            .blob_deposits_held
+            .try_add_assign(deposit)?;
+        Ok(deposit)
+    }
+
+    /// Releases a deposit taken from the chain state by
+    /// [`ResourceController::release_blob_deposit`]. It is returned funds rather than a
+    /// refund of this block's fees, so it is only recorded in
+    /// [`ResourceTracker::blob_deposits_released`].
+    fn refund_blob_deposit(&mut self, deposit: Amount) -> Result<(), ExecutionError> {
+        self.account.try_add_assign(deposit)?;
+        self.tracker
+            .as_mut()
+            .blob_deposits_released
+            .try_add_assign(deposit)?;
         Ok(())
     }
 
+    /// Returns the deposit for publishing the given blob.
+    fn blob_deposit(&self, blob: &Blob) -> Result<Amount, ArithmeticError> {
+        if blob.is_committee_blob() {
+            return Ok(Amount::ZERO);
+        }
+        self.policy
+            .blob_deposit_price(blob.content().bytes().len() as u64)
+    }
+
     /// Tracks a number of blob bytes published.
     pub fn track_blob_published(&mut self, blob: &Blob) -> Result<(), ExecutionError> {
-        self.policy.check_blob_size(blob.content())?;
+        self.check_strict_empty_account()?;
+        if !self.settings.analytics_mode {
+            self.policy.check_blob_size(blob.content())?;
+        }
         let size = blob.content().bytes().len() as u64;
+        let blob_type = blob.content().blob_type();
         if blob.is_committee_blob() {
+            // Committee blobs are counted but not charged.
+            let tracker = self.tracker.as_mut();
+            tracker.committee_blob_bytes_published = tracker
+                .committee_blob_bytes_published
+                .checked_add(size)
+                .ok_or(ArithmeticError::Overflow)?;
+            tracker.committee_blobs_published = tracker
+                .committee_blobs_published
+                .checked_add(1)
+                .ok_or(ArithmeticError::Overflow)?;
             return Ok(());
         }
         {
@@ -494,28 +2745,99 @@ where
                 .blobs_published
                 .checked_add(1)
                 .ok_or(ArithmeticError::Overflow)?;
+            self.settings.check_limit(
+                tracker.blob_bytes_published <= self.policy.maximum_blob_bytes_published_per_block,
+                ExecutionError::ExcessiveBlobBytesPublished,
+            )?;
         }
-        self.update_balance(self.policy.blob_published_price(size)?)?;
+        self.charge_resource_with_base(
+            ResourceKind::BlobBytesPublished,
+            size,
+            self.policy
+                .unit_price(ResourceKind::BlobBytesPublished)
+                .unwrap_or(Amount::ZERO)
+                .try_mul(u128::from(self.policy.blob_type_multiplier(blob_type)))?,
+            self.policy
+                .blob_published_price_for(blob_type, size)?
+                .try_add(ResourceControlPolicy::price_for(
+                    self.policy.blob_published_fixed,
+                    1,
+                )?)?,
+        )?;
         Ok(())
     }
 
-    /// Tracks a change in the number of bytes stored.
+    /// Tracks a change in the number of bytes stored. Newly stored bytes are charged. If
+    /// storage is freed, the refund goes to the [`StorageRefundTarget`] of the policy, but
+    /// never exceeds what was charged for stored bytes so far.
     // TODO(#1536): This is not fully implemented.
     #[allow(dead_code)]
     pub(crate) fn track_stored_bytes(&mut self, delta: i32) -> Result<(), ExecutionError> {
-        self.tracker.as_mut().bytes_stored = self
+        self.check_strict_empty_account()?;
+        let bytes_stored = self
             .tracker
-            .as_mut()
+            .as_ref()
             .bytes_stored
             .checked_add(delta)
             .ok_or(ArithmeticError::Overflow)?;
+        let fees = self
+            .policy
+            .bytes_stored_price(u64::from(delta.unsigned_abs()))?;
+        if delta > 0 {
+            self.update_balance(
+                ChargeKind::Fee("bytes_stored"),
+                u64::from(delta.unsigned_abs()),
+                fees,
+            )?;
+            self.tracker
+                .as_mut()
+                .bytes_stored_charged
+                .try_add_assign(fees)?;
+        } else if delta < 0 {
+            let refund = fees.min(self.tracker.as_ref().bytes_stored_charged);
+            match self.policy.storage_refund_target {
+                StorageRefundTarget::OwnerAccount => {
+                    self.credit_to(refund, Some(RefundTarget::LastSource))?
+                }
+                StorageRefundTarget::ChainAccount => {
+                    self.credit_to(refund, Some(RefundTarget::ChainAccount))?
+                }
+                StorageRefundTarget::Burn => self.burn(refund)?,
+            }
+            self.tracker
+                .as_mut()
+                .bytes_stored_charged
+                .try_sub_assign(refund)?;
+        }
+        self.tracker.as_mut().bytes_stored = bytes_stored;
+        Ok(())
+    }
+
+    /// Charges the rent of the policy for keeping `stored_bytes` bytes stored during
+    /// `elapsed`. Nothing is charged if no time elapsed.
+    pub fn track_storage_rent(
+        &mut self,
+        stored_bytes: u64,
+        elapsed: Duration,
+    ) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
+        let rent = self.policy.storage_rent_price(stored_bytes, elapsed)?;
+        if rent == Amount::ZERO {
+            return Ok(());
+        }
+        self.update_balance(ChargeKind::Fee("storage_rent"), stored_bytes, rent)?;
+        self.tracker.as_mut().rent_charged.try_add_assign(rent)?;
         Ok(())
     }
 
-    /// Returns the remaining time services can spend executing as oracles.
+    /// Returns the remaining time services can spend executing as oracles. This is
+    /// unlimited in analytics mode.
     pub(crate) fn remaining_service_oracle_execution_time(
         &self,
     ) -> Result<Duration, ExecutionError> {
+        if self.settings.analytics_mode {
+            return Ok(Duration::MAX);
+        }
         let tracker = self.tracker.as_ref();
         let spent_execution_time = tracker.service_oracle_execution;
         let limit = Duration::from_millis(self.policy.maximum_service_oracle_execution_ms);
@@ -527,30 +2849,48 @@ where
 
     /// Tracks a call to a service to run as an oracle.
     pub(crate) fn track_service_oracle_call(&mut self) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         self.tracker.as_mut().service_oracle_queries = self
             .tracker
             .as_mut()
             .service_oracle_queries
             .checked_add(1)
             .ok_or(ArithmeticError::Overflow)?;
-        self.update_balance(self.policy.service_as_oracle_query)
+        self.charge_resource(ResourceKind::OracleQueries, 1)
     }
 
     /// Tracks the time spent executing the service as an oracle.
+    ///
+    /// If a limit is reached and the policy's [`OracleTimeoutPolicy`] is `Truncate`, the
+    /// time is only recorded up to the limit and `oracle_time_truncated` is set, instead of
+    /// failing.
     pub(crate) fn track_service_oracle_execution(
         &mut self,
         execution_time: Duration,
     ) -> Result<(), ExecutionError> {
+        self.check_strict_empty_account()?;
         let tracker = self.tracker.as_mut();
-        let spent_execution_time = &mut tracker.service_oracle_execution;
         let limit = Duration::from_millis(self.policy.maximum_service_oracle_execution_ms);
+        let truncate = self.policy.oracle_timeout_policy == OracleTimeoutPolicy::Truncate
+            && !self.settings.analytics_mode;
 
-        *spent_execution_time = spent_execution_time.saturating_add(execution_time);
+        if tracker.oracle_latencies.len() < MAX_ORACLE_LATENCY_SAMPLES {
+            tracker.oracle_latencies.push(execution_time);
+        }
 
-        ensure!(
-            *spent_execution_time < limit,
-            ExecutionError::MaximumServiceOracleExecutionTimeExceeded
-        );
+        tracker.service_oracle_execution = tracker
+            .service_oracle_execution
+            .saturating_add(execution_time);
+
+        if truncate && tracker.service_oracle_execution >= limit {
+            tracker.service_oracle_execution = limit;
+            tracker.oracle_time_truncated = true;
+        } else {
+            self.settings.check_limit(
+                tracker.service_oracle_execution < limit,
+                ExecutionError::MaximumServiceOracleExecutionTimeExceeded,
+            )?;
+        }
 
         Ok(())
     }
@@ -560,21 +2900,210 @@ where
         &mut self,
         response_bytes: usize,
     ) -> Result<(), ExecutionError> {
-        ensure!(
+        self.check_strict_empty_account()?;
+        self.settings.check_limit(
             response_bytes as u64 <= self.policy.maximum_oracle_response_bytes,
-            ExecutionError::ServiceOracleResponseTooLarge
+            ExecutionError::ServiceOracleResponseTooLarge,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Returns the number of write operations and of bytes written of a batch.
+fn batch_writes(batch: &Batch) -> Result<(u32, u64), ExecutionError> {
+    let operations =
+        u32::try_from(batch.num_operations()).map_err(|_| ArithmeticError::Overflow)?;
+    let bytes = u64::try_from(batch.size()).map_err(|_| ArithmeticError::Overflow)?;
+    Ok((operations, bytes))
+}
+
+/// Appends `value` to `bytes` as a LEB128 varint.
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads the fields encoded by [`ResourceTracker::to_compact_bytes`].
+struct CompactReader<'a>(&'a [u8]);
+
+impl CompactReader<'_> {
+    /// Reads a LEB128 varint. As in BCS, only the shortest encoding of each value is
+    /// accepted, so that every tracker has a single encoding.
+    fn varint(&mut self) -> Result<u64, ExecutionError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .0
+                .split_first()
+                .ok_or(ExecutionError::InvalidCompactResourceTracker)?;
+            self.0 = rest;
+            let bits = u64::from(byte & 0x7f);
+            ensure!(
+                bits << shift >> shift == bits,
+                ExecutionError::InvalidCompactResourceTracker
+            );
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                // A last byte of zero could have been left out.
+                ensure!(
+                    byte != 0 || shift == 0,
+                    ExecutionError::InvalidCompactResourceTracker
+                );
+                return Ok(value);
+            }
+        }
+        Err(ExecutionError::InvalidCompactResourceTracker)
+    }
+
+    /// Reads a LEB128 varint that must fit in a `u32`.
+    fn small_varint(&mut self) -> Result<u32, ExecutionError> {
+        u32::try_from(self.varint()?).map_err(|_| ExecutionError::InvalidCompactResourceTracker)
+    }
+
+    /// Reads `N` bytes.
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], ExecutionError> {
+        ensure!(
+            self.0.len() >= N,
+            ExecutionError::InvalidCompactResourceTracker
         );
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(bytes.try_into().expect("slice has the right length"))
+    }
+}
+
+/// Fuel paid for in advance by [`ResourceController::reserve_fuel`].
+///
+/// Calling [`FuelReservation::consume`] records the fuel actually used and refunds the
+/// rest. Dropping the reservation without consuming it charges the full reservation, i.e.
+/// all the reserved fuel is recorded as used.
+#[must_use]
+pub struct FuelReservation<'a, Account, Tracker>
+where
+    Account: BalanceHolder,
+    Tracker: AsRef<ResourceTracker> + AsMut<ResourceTracker>,
+{
+    controller: &'a mut ResourceController<Account, Tracker>,
+    vm_runtime: VmRuntime,
+    fuel: u64,
+    price: Amount,
+    settled: bool,
+}
 
+impl<Account, Tracker> FuelReservation<'_, Account, Tracker>
+where
+    Account: BalanceHolder,
+    Tracker: AsRef<ResourceTracker> + AsMut<ResourceTracker>,
+{
+    /// Returns the amount of fuel reserved.
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
+
+    /// Records that `used` units of fuel were actually spent and refunds the price of the
+    /// rest.
+    pub fn consume(mut self, used: u64) -> Result<(), ExecutionError> {
+        self.settled = true;
+        let vm_runtime = self.vm_runtime;
+        ensure!(
+            used <= self.fuel,
+            ExecutionError::MaximumFuelExceeded(vm_runtime)
+        );
+        let controller = &mut *self.controller;
+        controller.add_fuel(used, vm_runtime)?;
+        let refund = self
+            .price
+            .try_sub(controller.policy.fuel_price(used, vm_runtime)?)?;
+        controller.credit(refund)?;
+        controller
+            .tracker
+            .as_mut()
+            .record_refund(fuel_kind(vm_runtime), refund);
         Ok(())
     }
 }
 
+impl<Account, Tracker> Drop for FuelReservation<'_, Account, Tracker>
+where
+    Account: BalanceHolder,
+    Tracker: AsRef<ResourceTracker> + AsMut<ResourceTracker>,
+{
+    fn drop(&mut self) {
+        if !self.settled {
+            // The reserved fuel fits in the block, so this can only fail if the limit was
+            // already exceeded, which has been reported before.
+            let _ = self.controller.add_fuel(self.fuel, self.vm_runtime);
+        }
+    }
+}
+
 impl<Account, Tracker> ResourceController<Account, Tracker>
 where
     Tracker: AsMut<ResourceTracker>,
 {
+    /// Resets all the counters of the tracker, keeping the policy and the account. This
+    /// allows reusing the same controller for the next block.
+    pub fn reset_tracker(&mut self) {
+        let tracker = self.tracker.as_mut();
+        *tracker = ResourceTracker {
+            event_log: tracker.event_log.as_ref().map(|_| Vec::new()),
+            ..ResourceTracker::default()
+        };
+    }
+
+    /// Enables recording every charge in an event log, to see exactly what was charged and
+    /// in which order. The log is disabled by default.
+    pub fn with_event_log(mut self) -> Self {
+        self.tracker.as_mut().event_log = Some(Vec::new());
+        self
+    }
+
+    /// Marks the start of a new transaction, whose base fee has not been charged yet.
+    pub fn start_transaction(&mut self) {
+        self.tracker.as_mut().base_fee_charged = false;
+    }
+
+    /// Sets the maximum fees that may be charged from now on, e.g. for the next operation
+    /// submitted by a user, or removes the cap if `None`. Once the cap would be exceeded,
+    /// charges fail with [`ExecutionError::FeeCapExceeded`] and nothing more is deducted.
+    pub fn set_fee_cap(&mut self, fee_cap: Option<Amount>) {
+        let tracker = self.tracker.as_mut();
+        tracker.fee_cap = fee_cap;
+        tracker.capped_fees = Amount::ZERO;
+    }
+
+    /// Rejects credits to the account, e.g. refunds, that would exceed the debits so far
+    /// plus the given initial grants, with [`ExecutionError::RefundExceedsCharges`].
+    pub fn check_refunds(&mut self, initial_grants: Amount) {
+        self.tracker.as_mut().refund_allowance = Some(initial_grants);
+    }
+
+    /// Checks that the credits to the account so far do not exceed the debits. This is a
+    /// safety net to call at the end of a block's execution.
+    pub fn finalize(&mut self) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        ensure!(
+            tracker.credited <= tracker.debited,
+            ExecutionError::NegativeNetFees {
+                debited: tracker.debited,
+                credited: tracker.credited,
+            }
+        );
+        Ok(())
+    }
+
     /// Tracks the serialized size of a block, or parts of it.
     pub fn track_block_size_of(&mut self, data: &impl Serialize) -> Result<(), ExecutionError> {
+        // Avoid serializing the data if the block is already full.
+        let tracker = self.tracker.as_mut();
+        self.settings.check_limit(
+            tracker.block_size < self.policy.maximum_block_size,
+            ExecutionError::BlockTooLarge,
+        )?;
         self.track_block_size(bcs::serialized_size(data)?)
     }
 
@@ -585,15 +3114,107 @@ where
             .ok()
             .and_then(|size| tracker.block_size.checked_add(size))
             .ok_or(ExecutionError::BlockTooLarge)?;
-        ensure!(
+        self.settings.check_limit(
             tracker.block_size <= self.policy.maximum_block_size,
-            ExecutionError::BlockTooLarge
-        );
+            ExecutionError::BlockTooLarge,
+        )?;
+        Ok(())
+    }
+
+    /// Tracks a part of a block like [`Self::track_block_size`], and attributes its size to
+    /// the given component in [`ResourceTracker::block_size_by_component`]. The size is
+    /// attributed even if the block becomes too large, to show what made it so.
+    pub fn track_block_size_labeled(
+        &mut self,
+        component: BlockComponent,
+        size: usize,
+    ) -> Result<(), ExecutionError> {
+        let total = self
+            .tracker
+            .as_mut()
+            .block_size_by_component
+            .entry(component)
+            .or_default();
+        *total = total.saturating_add(size as u64);
+        self.track_block_size(size)
+    }
+
+    /// Tracks a part of a block that is stored compressed: only the size returned by
+    /// `compressor` for its serialization counts towards the block size. Both the compressed
+    /// and the uncompressed sizes are recorded in the tracker.
+    pub fn track_block_size_compressed(
+        &mut self,
+        uncompressed: &impl Serialize,
+        compressor: &dyn Fn(&[u8]) -> usize,
+    ) -> Result<(), ExecutionError> {
+        // Avoid serializing the data if the block is already full.
+        let tracker = self.tracker.as_mut();
+        self.settings.check_limit(
+            tracker.block_size < self.policy.maximum_block_size,
+            ExecutionError::BlockTooLarge,
+        )?;
+        let bytes = bcs::to_bytes(uncompressed)?;
+        let compressed = compressor(&bytes);
+        let tracker = self.tracker.as_mut();
+        tracker.uncompressed_block_size = tracker
+            .uncompressed_block_size
+            .saturating_add(bytes.len() as u64);
+        tracker.compressed_block_size = tracker
+            .compressed_block_size
+            .saturating_add(compressed as u64);
+        self.track_block_size(compressed)
+    }
+
+    /// Tracks a part of a block like [`Self::track_block_size`], but allows exceeding the
+    /// maximum block size by up to `grace` bytes, e.g. for a final message closing the block.
+    /// The grace can only be used once: afterwards the limit applies strictly.
+    pub fn track_block_size_with_grace(
+        &mut self,
+        size: usize,
+        grace: u64,
+    ) -> Result<(), ExecutionError> {
+        let tracker = self.tracker.as_mut();
+        if tracker.block_size_grace_used {
+            return self.track_block_size(size);
+        }
+        tracker.block_size = u64::try_from(size)
+            .ok()
+            .and_then(|size| tracker.block_size.checked_add(size))
+            .ok_or(ExecutionError::BlockTooLarge)?;
+        if tracker.block_size <= self.policy.maximum_block_size {
+            return Ok(());
+        }
+        self.settings.check_limit(
+            tracker.block_size <= self.policy.maximum_block_size.saturating_add(grace),
+            ExecutionError::BlockTooLarge,
+        )?;
+        tracker.block_size_grace_used = true;
         Ok(())
     }
 }
 
 impl ResourceController<Option<AccountOwner>, ResourceTracker> {
+    /// Releases the deposit locked by [`ResourceController::track_blob_deposit`] for the
+    /// given blob, e.g. when the blob is removed, and credits it to the account. The deposit
+    /// is the one recorded in the chain state when the blob was published, not the price
+    /// under the current policy, and it is removed from the chain state so that it cannot be
+    /// released twice. Returns the released deposit, or zero if none was held.
+    pub async fn release_blob_deposit<C>(
+        &mut self,
+        view: &mut SystemExecutionStateView<C>,
+        blob_id: &BlobId,
+    ) -> Result<Amount, ExecutionError>
+    where
+        C: Context + Clone + Send + Sync + 'static,
+        C::Extra: ExecutionRuntimeContext,
+    {
+        let Some(deposit) = view.take_blob_deposit(blob_id).await? else {
+            return Ok(Amount::ZERO);
+        };
+        self.with_state(view).await?.refund_blob_deposit(deposit)?;
+        Ok(deposit)
+    }
+
     /// Provides a reference to the current execution state and obtains a temporary object
     /// where the accounting functions of [`ResourceController`] are available.
     pub async fn with_state<'a, C>(
@@ -618,25 +3239,39 @@ impl ResourceController<Option<AccountOwner>, ResourceTracker> {
         C: Context + Clone + Send + Sync + 'static,
     {
         let mut sources = Vec::new();
+        let mut owners = vec![None];
+        let mut chain_account = None;
         // First, use the grant (e.g. for messages) and otherwise use the chain account
         // (e.g. for blocks and operations).
         if let Some(grant) = grant {
             sources.push(grant);
+            chain_account = Some(view.balance.get_mut());
         } else {
             sources.push(view.balance.get_mut());
         }
-        // Then the local account, if any. Currently, any negative fee (e.g. storage
+        // Then the local account, if any. By default, any negative fee (e.g. storage
         // refund) goes preferably to this account.
         if let Some(owner) = &self.account {
             if let Some(balance) = view.balances.get_mut(owner).await? {
                 sources.push(balance);
+                owners.push(Some(*owner));
             }
         }
 
+        let fee_exempt = self
+            .account
+            .is_some_and(|owner| self.policy.fee_exempt_owners.contains(&owner));
         Ok(ResourceController {
             policy: self.policy.clone(),
             tracker: &mut self.tracker,
-            account: Sources { sources },
+            account: Sources {
+                sources,
+                owners,
+                refund_target: RefundTarget::default(),
+                chain_account,
+            },
+            fee_exempt,
+            settings: self.settings.clone(),
         })
     }
 }
@@ -654,6 +3289,10 @@ impl BalanceHolder for Amount {
     fn try_sub_assign(&mut self, other: Amount) -> Result<(), ArithmeticError> {
         self.try_sub_assign(other)
     }
+
+    fn description(&self) -> &'static str {
+        "amount"
+    }
 }
 
 // This is also needed for the default instantiation `ResourceController<Amount, ResourceTracker>`.
@@ -673,22 +3312,156 @@ impl AsRef<ResourceTracker> for ResourceTracker {
 /// A temporary object holding a number of references to funding sources.
 pub struct Sources<'a> {
     sources: Vec<&'a mut Amount>,
+    /// The owner of each source, if it is an owner account. Sources beyond the end of this
+    /// list have no owner.
+    owners: Vec<Option<AccountOwner>>,
+    refund_target: RefundTarget,
+    /// The chain balance, if it is not one of the sources, e.g. because a grant pays
+    /// instead. It only receives credits for [`RefundTarget::ChainAccount`].
+    chain_account: Option<&'a mut Amount>,
+}
+
+/// Which funding sources of a [`Sources`] receive credits, e.g. refunds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RefundTarget {
+    /// Credit the last source, i.e. the owner account if there is one.
+    #[default]
+    LastSource,
+    /// Credit the first source, i.e. the grant or the chain balance.
+    FirstSource,
+    /// Split credits evenly between all sources. Any remainder goes to the last source.
+    SplitEvenly,
+    /// Credit the chain balance: the separate chain account if there is one, and
+    /// otherwise the first source.
+    ChainAccount,
+}
+
+impl<'a> Sources<'a> {
+    /// Creates a holder drawing from the given sources in order, and crediting the last
+    /// one. Fails if there are no sources.
+    pub fn new(sources: Vec<&'a mut Amount>) -> Result<Self, ExecutionError> {
+        ensure!(!sources.is_empty(), ExecutionError::NoFundingSources);
+        Ok(Sources {
+            sources,
+            owners: Vec::new(),
+            refund_target: RefundTarget::default(),
+            chain_account: None,
+        })
+    }
+
+    /// Sets the owner of each source, in order, for sources that are owner accounts. The
+    /// fees they pay are recorded in [`ResourceTracker::paid_by_owner`].
+    pub fn with_owners(mut self, owners: Vec<Option<AccountOwner>>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    /// Sets which sources receive credits.
+    pub fn with_refund_target(mut self, refund_target: RefundTarget) -> Self {
+        self.refund_target = refund_target;
+        self
+    }
+
+    /// Sets the chain balance when it is not one of the sources, so that credits for
+    /// [`RefundTarget::ChainAccount`] still reach it.
+    pub fn with_chain_account(mut self, chain_account: &'a mut Amount) -> Self {
+        self.chain_account = Some(chain_account);
+        self
+    }
 }
 
 impl BalanceHolder for Sources<'_> {
+    /// Returns the sum of all sources. The sum saturates at [`Amount::MAX`] instead of
+    /// failing: the total supply makes an overflow impossible in practice, so this never
+    /// returns an error.
     fn balance(&self) -> Result<Amount, ArithmeticError> {
         let mut amount = Amount::ZERO;
         for source in self.sources.iter() {
-            amount.try_add_assign(**source)?;
+            amount.saturating_add_assign(**source);
+            if amount == Amount::MAX {
+                break;
+            }
         }
         Ok(amount)
     }
 
+    fn balances(&self) -> Vec<Amount> {
+        self.sources.iter().map(|source| **source).collect()
+    }
+
+    fn try_add_assign_to(
+        &mut self,
+        target: RefundTarget,
+        other: Amount,
+    ) -> Result<(), ArithmeticError> {
+        let default_target = mem::replace(&mut self.refund_target, target);
+        let result = self.try_add_assign(other);
+        self.refund_target = default_target;
+        result
+    }
+
     fn try_add_assign(&mut self, other: Amount) -> Result<(), ArithmeticError> {
-        // Try to credit the owner account first.
+        // By default, try to credit the owner account first.
         // TODO(#1648): This may need some additional design work.
-        let source = self.sources.last_mut().expect("at least one source");
-        source.try_add_assign(other)
+        match self.refund_target {
+            RefundTarget::LastSource => self
+                .sources
+                .last_mut()
+                .expect("at least one source")
+                .try_add_assign(other),
+            RefundTarget::FirstSource => self
+                .sources
+                .first_mut()
+                .expect("at least one source")
+                .try_add_assign(other),
+            RefundTarget::ChainAccount => match &mut self.chain_account {
+                Some(chain_account) => chain_account.try_add_assign(other),
+                None => self
+                    .sources
+                    .first_mut()
+                    .expect("at least one source")
+                    .try_add_assign(other),
+            },
+            RefundTarget::SplitEvenly => {
+                let count = self.sources.len() as u128;
+                assert!(count > 0, "at least one source");
+                let share = Amount::from_attos(u128::from(other) / count);
+                let remainder = Amount::from_attos(u128::from(other) % count);
+                // Compute all the new balances first so that nothing changes on error.
+                let mut balances = self
+                    .sources
+                    .iter()
+                    .map(|source| source.try_add(share))
+                    .collect::<Result<Vec<_>, _>>()?;
+                balances
+                    .last_mut()
+                    .expect("at least one source")
+                    .try_add_assign(remainder)?;
+                for (source, balance) in self.sources.iter_mut().zip(balances) {
+                    **source = balance;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn try_sub_assign_by_owner(
+        &mut self,
+        other: Amount,
+    ) -> Result<Vec<(AccountOwner, Amount)>, ArithmeticError> {
+        let before = self.balances();
+        self.try_sub_assign(other)?;
+        Ok(self
+            .owners
+            .iter()
+            .zip(before)
+            .zip(&self.sources)
+            .filter_map(|((owner, before), after)| {
+                let owner = (*owner)?;
+                let paid = before.saturating_sub(**after);
+                (paid > Amount::ZERO).then_some((owner, paid))
+            })
+            .collect())
     }
 
     fn try_sub_assign(&mut self, mut other: Amount) -> Result<(), ArithmeticError> {
@@ -705,4 +3478,8 @@ impl BalanceHolder for Sources<'_> {
             Ok(())
         }
     }
+
+    fn description(&self) -> &'static str {
+        "sources"
+    }
 }
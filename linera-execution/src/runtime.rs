@@ -24,7 +24,7 @@ use linera_base::{
     ownership::ChainOwnership,
     vm::VmRuntime,
 };
-use linera_views::batch::Batch;
+use linera_views::batch::{Batch, UnorderedBatch};
 use oneshot::Receiver;
 
 use crate::{
@@ -454,6 +454,7 @@ impl SyncRuntimeInternal<UserContractInstance> {
 
         // Load the application.
         let application = self.load_contract_instance(this, callee_id)?;
+        self.resource_controller.enter_call()?;
 
         let caller = self.current_application();
         let caller_id = caller.id;
@@ -477,7 +478,7 @@ impl SyncRuntimeInternal<UserContractInstance> {
     /// Cleans up the runtime after the execution of a call to a different contract.
     fn finish_call(&mut self) -> Result<(), ExecutionError> {
         self.pop_application();
-        Ok(())
+        self.resource_controller.exit_call()
     }
 
     /// Runs the service in a separate thread as an oracle.
@@ -500,7 +501,8 @@ impl SyncRuntimeInternal<UserContractInstance> {
             .resource_controller
             .remaining_service_oracle_execution_time()?;
         let execution_start = Instant::now();
-        let deadline = Some(execution_start + timeout);
+        // The timeout is unlimited in analytics mode.
+        let deadline = execution_start.checked_add(timeout);
 
         let mut service_runtime =
             ServiceSyncRuntime::new_with_txn_tracker(sender, context, deadline, txn_tracker);
@@ -896,7 +898,8 @@ where
             ExecutionError::UnauthorizedApplication(app_id)
         );
 
-        this.resource_controller.track_http_request()?;
+        this.resource_controller
+            .track_http_request_with_body(request.body.len())?;
 
         let response =
             if let Some(response) = this.transaction_tracker.next_replayed_oracle_response()? {
@@ -914,6 +917,8 @@ where
                     })?
                     .recv_response()?
             };
+        this.resource_controller
+            .track_http_response(response.body.len())?;
         this.transaction_tracker
             .add_oracle_response(OracleResponse::Http(response.clone()));
         Ok(response)
@@ -1214,6 +1219,8 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
 
     fn consume_fuel(&mut self, fuel: u64, vm_runtime: VmRuntime) -> Result<(), ExecutionError> {
         let mut this = self.inner();
+        let application_id = this.current_application().id;
+        this.resource_controller.track_application(application_id);
         this.resource_controller.track_fuel(fuel, vm_runtime)
     }
 
@@ -1224,6 +1231,7 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         let authenticated_signer = application.signer;
         let mut refund_grant_to = this.refund_grant_to;
 
+        // The price of the granted resources is already in native tokens.
         let grant = this
             .resource_controller
             .policy()
@@ -1231,7 +1239,9 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         if grant.is_zero() {
             refund_grant_to = None;
         } else {
-            this.resource_controller.track_grant(grant)?;
+            let message_index = this.transaction_tracker.next_message_index() as usize;
+            this.resource_controller
+                .track_native_grant_for(message_index, grant)?;
         }
         let kind = if message.is_tracked {
             MessageKind::Tracked
@@ -1611,14 +1621,11 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
         let id = this.current_application().id;
         let state = this.view_user_states.entry(id).or_default();
         state.force_all_pending_queries()?;
-        this.resource_controller.track_write_operations(
-            batch
-                .num_operations()
-                .try_into()
-                .map_err(|_| ExecutionError::from(ArithmeticError::Overflow))?,
-        )?;
+        this.resource_controller.estimate_writes(&batch)?;
+        // Writes that are overwritten later in the same batch are never persisted, so their
+        // fees are refunded. The batch itself is written unchanged.
         this.resource_controller
-            .track_bytes_written(batch.size() as u64)?;
+            .settle_writes(&committed_writes(batch.clone()))?;
         this.execution_state_sender
             .send_request(|callback| ExecutionRequest::WriteBatch {
                 id,
@@ -1630,6 +1637,26 @@ impl ContractRuntime for ContractSyncRuntimeHandle {
     }
 }
 
+/// Returns a batch with the same effect as `batch`, without the writes that are overwritten
+/// by later ones in the same batch.
+fn committed_writes(batch: Batch) -> Batch {
+    let UnorderedBatch {
+        key_prefix_deletions,
+        simple_unordered_batch,
+    } = batch.simplify();
+    let mut committed = Batch::new();
+    for key_prefix in key_prefix_deletions {
+        committed.delete_key_prefix(key_prefix);
+    }
+    for key in simple_unordered_batch.deletions {
+        committed.delete_key(key);
+    }
+    for (key, value) in simple_unordered_batch.insertions {
+        committed.put_key_value_bytes(key, value);
+    }
+    committed
+}
+
 impl ServiceSyncRuntime {
     /// Creates a new [`ServiceSyncRuntime`] ready to execute using a provided [`QueryContext`].
     pub fn new(execution_state_sender: ExecutionStateSender, context: QueryContext) -> Self {
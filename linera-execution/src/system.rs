@@ -28,6 +28,7 @@ use linera_views::{
     register_view::HashedRegisterView,
     set_view::HashedSetView,
     views::{ClonableView, HashableView, View},
+    ViewError,
 };
 use serde::{Deserialize, Serialize};
 
@@ -91,6 +92,8 @@ pub struct SystemExecutionStateView<C> {
     pub used_blobs: HashedSetView<C, BlobId>,
     /// The event stream subscriptions of applications on this chain.
     pub event_subscriptions: MapView<C, (ChainId, StreamId), EventSubscriptions>,
+    /// The refundable deposits locked for the blobs published by this chain.
+    pub blob_deposits: HashedMapView<C, BlobId, Amount>,
 }
 
 /// The applications subscribing to a particular stream, and the next event index.
@@ -325,6 +328,32 @@ where
     C: Context + Clone + Send + Sync + 'static,
     C::Extra: ExecutionRuntimeContext,
 {
+    /// Records the deposit locked for publishing the given blob, in addition to any deposit
+    /// already recorded for it.
+    pub async fn record_blob_deposit(
+        &mut self,
+        blob_id: BlobId,
+        deposit: Amount,
+    ) -> Result<(), ExecutionError> {
+        let held = self.blob_deposits.get(&blob_id).await?.unwrap_or_default();
+        self.blob_deposits
+            .insert(&blob_id, held.try_add(deposit)?)?;
+        Ok(())
+    }
+
+    /// Removes the record of the deposit locked for the given blob, and returns it, if any.
+    /// This is only used by `ResourceController::release_blob_deposit`, which credits it.
+    pub(crate) async fn take_blob_deposit(
+        &mut self,
+        blob_id: &BlobId,
+    ) -> Result<Option<Amount>, ViewError> {
+        let deposit = self.blob_deposits.get(blob_id).await?;
+        if deposit.is_some() {
+            self.blob_deposits.remove(blob_id)?;
+        }
+        Ok(deposit)
+    }
+
     /// Invariant for the states of active chains.
     pub fn is_active(&self) -> bool {
         self.description.get().is_some()
@@ -434,8 +463,9 @@ where
                     AdminOperation::CreateCommittee { epoch, blob_hash } => {
                         self.check_next_epoch(epoch)?;
                         let blob_id = BlobId::new(blob_hash, BlobType::Committee);
-                        let committee =
+                        let committee: Committee =
                             bcs::from_bytes(self.read_blob_content(blob_id).await?.bytes())?;
+                        committee.policy().validate()?;
                         self.blob_used(txn_tracker, blob_id).await?;
                         self.committees.get_mut().insert(epoch, committee);
                         self.epoch.set(epoch);
@@ -6,6 +6,7 @@
 #![allow(unused_imports)]
 
 mod mock_application;
+mod resource_tracker_spec;
 #[cfg(with_revm)]
 pub mod solidity;
 mod system_execution_state;
@@ -28,6 +29,7 @@ use serde::{Deserialize, Serialize};
 
 pub use self::{
     mock_application::{ExpectedCall, MockApplication, MockApplicationInstance},
+    resource_tracker_spec::ResourceTrackerSpec,
     system_execution_state::SystemExecutionState,
 };
 use crate::{
@@ -0,0 +1,79 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Partial expectations for a [`ResourceTracker`], to keep assertions in tests short.
+
+use std::time::Duration;
+
+use linera_base::data_types::Amount;
+
+use crate::ResourceTracker;
+
+macro_rules! resource_tracker_spec {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        /// The expected values of some fields of a [`ResourceTracker`]. Fields that are
+        /// `None` are not checked by [`ResourceTracker::assert_matches`].
+        #[derive(Clone, Debug, Default, PartialEq, Eq)]
+        pub struct ResourceTrackerSpec {
+            $(
+                #[doc = concat!("The expected value of `", stringify!($field), "`, if any.")]
+                pub $field: Option<$ty>,
+            )*
+        }
+
+        impl ResourceTracker {
+            /// Panics, listing every mismatching field, unless this tracker has the values
+            /// set in `expected`.
+            pub fn assert_matches(&self, expected: &ResourceTrackerSpec) {
+                let mut mismatches = Vec::new();
+                $(
+                    if let Some(value) = &expected.$field {
+                        if *value != self.$field {
+                            mismatches.push(format!(
+                                "{}: expected {:?}, got {:?}",
+                                stringify!($field),
+                                value,
+                                self.$field,
+                            ));
+                        }
+                    }
+                )*
+                assert!(
+                    mismatches.is_empty(),
+                    "resource tracker does not match the expected values:\n{}",
+                    mismatches.join("\n")
+                );
+            }
+        }
+    };
+}
+
+resource_tracker_spec! {
+    block_size: u64,
+    evm_fuel: u64,
+    wasm_fuel: u64,
+    read_operations: u32,
+    write_operations: u32,
+    bytes_runtime: u32,
+    bytes_read: u64,
+    bytes_written: u64,
+    deserialized_bytes: u64,
+    blobs_read: u32,
+    blobs_published: u32,
+    blob_bytes_read: u64,
+    blob_bytes_published: u64,
+    bytes_stored: i32,
+    operations: u32,
+    operation_bytes: u64,
+    messages: u32,
+    message_bytes: u64,
+    outgoing_message_hops: u64,
+    http_requests: u32,
+    http_request_bytes: u64,
+    http_response_bytes: u64,
+    service_oracle_queries: u32,
+    service_oracle_execution: Duration,
+    grants: Amount,
+    merged_balance_credits: Amount,
+    merged_balance_debits: Amount,
+}
@@ -0,0 +1,470 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Unit tests for the resource control policy.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use assert_matches::assert_matches;
+use linera_base::{
+    data_types::{Amount, ArithmeticError, Blob},
+    identifiers::BlobType,
+    vm::VmRuntime,
+};
+
+use crate::{
+    BlockCostPlan, ExecutionError, OperationDiscriminant, OracleTimeoutPolicy, PolicyError,
+    PolicyFieldChange, ResourceControlPolicy, ResourceKind, TransactionHints,
+};
+
+#[test]
+fn test_price_schedule_and_limits() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(3),
+        http_request: Amount::from_micros(50),
+        maximum_block_size: 1_000,
+        http_request_timeout_ms: 20_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    let prices = policy.price_schedule();
+    assert_eq!(prices.len(), 28);
+    assert_eq!(prices["wasm_fuel_unit"], Amount::from_attos(3));
+    assert_eq!(prices["http_request"], Amount::from_micros(50));
+    assert_eq!(prices["operation_byte"], Amount::ZERO);
+    assert!(prices.contains_key("service_as_oracle_query"));
+
+    let limits = policy.limits();
+    assert_eq!(limits.len(), 20);
+    assert_eq!(limits["maximum_block_size"], 1_000);
+    assert_eq!(limits["http_request_timeout_ms"], 20_000);
+    assert_eq!(limits["maximum_wasm_fuel_per_block"], u64::MAX);
+    assert!(!limits.contains_key("wasm_fuel_unit"));
+}
+
+#[test]
+fn test_project_block_cost() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_byte: Amount::from_attos(1),
+        message: Amount::from_attos(20),
+        message_byte: Amount::from_attos(2),
+        blob_read: Amount::from_attos(100),
+        blob_byte_read: Amount::from_attos(3),
+        blob_published: Amount::from_attos(1_000),
+        blob_byte_published: Amount::from_attos(4),
+        wasm_fuel_unit: Amount::from_attos(5),
+        evm_fuel_unit: Amount::from_attos(6),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let plan = BlockCostPlan {
+        operations: 2,
+        operation_bytes: 100,
+        messages: 3,
+        message_bytes: 50,
+        blobs_read: 1,
+        blob_bytes_read: 10,
+        blobs_published: 1,
+        blob_bytes_published: 20,
+        wasm_fuel: 1_000,
+        evm_fuel: 10,
+    };
+    let expected =
+        2 * 10 + 100 + 3 * 20 + 50 * 2 + 100 + 10 * 3 + 1_000 + 20 * 4 + 1_000 * 5 + 10 * 6;
+    assert_eq!(
+        policy.project_block_cost(&plan).unwrap(),
+        Amount::from_attos(expected)
+    );
+    assert_eq!(
+        policy
+            .project_block_cost(&BlockCostPlan::default())
+            .unwrap(),
+        Amount::ZERO
+    );
+
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::MAX,
+        ..ResourceControlPolicy::no_fees()
+    };
+    assert_matches!(
+        policy.project_block_cost(&BlockCostPlan {
+            wasm_fuel: 2,
+            ..BlockCostPlan::default()
+        }),
+        Err(ArithmeticError::Overflow)
+    );
+}
+
+#[test]
+fn test_blob_published_price_for() {
+    let policy = ResourceControlPolicy {
+        blob_published: Amount::from_attos(100),
+        blob_byte_published: Amount::from_attos(1),
+        blob_bytecode_multiplier: 3,
+        blob_description_multiplier: 2,
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    let price = |blob_type| policy.blob_published_price_for(blob_type, 10).unwrap();
+    assert_eq!(price(BlobType::Data), Amount::from_attos(110));
+    assert_eq!(price(BlobType::ContractBytecode), Amount::from_attos(330));
+    assert_eq!(price(BlobType::EvmBytecode), Amount::from_attos(330));
+    assert_eq!(price(BlobType::ChainDescription), Amount::from_attos(220));
+    assert_eq!(price(BlobType::Committee), Amount::ZERO);
+}
+
+#[test]
+fn test_adjust_base_fee() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1_000),
+        message: Amount::from_attos(2_000),
+        blob_byte_deposit: Amount::from_attos(500),
+        operation_price_overrides: BTreeMap::from([(
+            OperationDiscriminant::Transfer,
+            Amount::from_attos(3_000),
+        )]),
+        maximum_block_size: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    // Over the target, prices rise.
+    let adjusted = policy.adjust_base_fee(0.55, 0.5, 1_250);
+    assert_eq!(adjusted.wasm_fuel_unit, Amount::from_attos(1_100));
+    assert_eq!(adjusted.message, Amount::from_attos(2_200));
+    assert_eq!(adjusted.operation, Amount::ZERO);
+    assert_eq!(adjusted.maximum_block_size, 1_000);
+    // Deposits and per-operation overrides are scaled by the same factor.
+    assert_eq!(adjusted.blob_byte_deposit, Amount::from_attos(550));
+    assert_eq!(
+        adjusted.operation_price_overrides,
+        BTreeMap::from([(OperationDiscriminant::Transfer, Amount::from_attos(3_300))])
+    );
+
+    // Under the target, prices fall.
+    let adjusted = policy.adjust_base_fee(0.45, 0.5, 1_250);
+    assert_eq!(adjusted.wasm_fuel_unit, Amount::from_attos(900));
+    assert_eq!(adjusted.message, Amount::from_attos(1_800));
+
+    // The change is clamped in both directions.
+    let adjusted = policy.adjust_base_fee(1.0, 0.5, 1_250);
+    assert_eq!(adjusted.wasm_fuel_unit, Amount::from_attos(1_125));
+    let adjusted = policy.adjust_base_fee(0.0, 0.5, 1_250);
+    assert_eq!(adjusted.wasm_fuel_unit, Amount::from_attos(875));
+
+    // On target, nothing changes.
+    assert_eq!(policy.adjust_base_fee(0.5, 0.5, 1_250), policy);
+
+    // Invalid inputs leave the prices unchanged.
+    for (utilization, target) in [
+        (f64::NAN, 0.5),
+        (0.5, f64::NAN),
+        (f64::INFINITY, 0.5),
+        (0.5, f64::INFINITY),
+        (-1.0, 0.5),
+        (0.5, 0.0),
+        (0.5, -0.5),
+    ] {
+        assert_eq!(policy.adjust_base_fee(utilization, target, 1_250), policy);
+    }
+}
+
+#[test]
+fn test_estimate_http_cost() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_micros(50),
+        http_response_byte: Amount::from_nanos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    assert_eq!(
+        policy.estimate_http_cost(2_048).unwrap(),
+        Amount::from_nanos(50_000 + 2_048)
+    );
+    assert_eq!(
+        policy.estimate_http_cost(0).unwrap(),
+        Amount::from_micros(50)
+    );
+}
+
+#[test]
+fn test_consistent_pricing_across_categories() {
+    for unit_price in [Amount::from_attos(3), Amount::from_attos(u128::MAX / 2)] {
+        let policy = ResourceControlPolicy {
+            wasm_fuel_unit: unit_price,
+            read_operation: unit_price,
+            write_operation: unit_price,
+            byte_runtime: unit_price,
+            byte_read: unit_price,
+            byte_written: unit_price,
+            blob_byte_read: unit_price,
+            blob_byte_published: unit_price,
+            byte_stored: unit_price,
+            operation_byte: unit_price,
+            message_byte: unit_price,
+            service_as_oracle_query: unit_price,
+            http_request: unit_price,
+            ..ResourceControlPolicy::no_fees()
+        };
+        let expected = ResourceControlPolicy::price_for(unit_price, 7).ok();
+        let prices = [
+            policy.fuel_price(7, VmRuntime::Wasm),
+            policy.read_operations_price(7),
+            policy.write_operations_price(7),
+            policy.bytes_runtime_price(7),
+            policy.bytes_read_price(7),
+            policy.bytes_written_price(7),
+            policy.blob_read_price(7),
+            policy.blob_published_price(7),
+            policy.bytes_stored_price(7),
+            policy.operation_bytes_price(7),
+            policy.message_bytes_price(7),
+            policy.service_as_oracle_queries_price(7),
+            policy.http_requests_price(7),
+        ];
+        for price in prices {
+            assert_eq!(price.ok(), expected);
+        }
+    }
+    assert_eq!(
+        ResourceControlPolicy::price_for(Amount::from_attos(3), 7).unwrap(),
+        Amount::from_attos(21)
+    );
+    assert_matches!(
+        ResourceControlPolicy::price_for(Amount::from_attos(u128::MAX / 2), 7),
+        Err(ArithmeticError::Overflow)
+    );
+}
+
+#[test]
+fn test_blob_read_cost() {
+    let policy = ResourceControlPolicy {
+        blob_read: Amount::from_attos(100),
+        blob_read_fixed: Amount::from_attos(7),
+        blob_byte_read: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    assert_eq!(
+        policy.blob_read_cost(&Blob::new_data(vec![0; 10])).unwrap(),
+        Amount::from_attos(100 + 7 + 20)
+    );
+    assert_eq!(
+        policy
+            .blob_read_cost(&Blob::new_committee(vec![0; 10]))
+            .unwrap(),
+        Amount::ZERO
+    );
+}
+
+#[test]
+fn test_affordable_fuel() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_nanos(3),
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    // EVM fuel is free.
+    assert_eq!(
+        policy.affordable_fuel(Amount::ZERO, VmRuntime::Evm),
+        u64::MAX
+    );
+    assert_eq!(
+        policy.affordable_fuel(Amount::from_nanos(10), VmRuntime::Wasm),
+        3
+    );
+    assert_eq!(
+        policy.affordable_fuel(Amount::from_attos(1), VmRuntime::Wasm),
+        0
+    );
+    // The balance buys exactly what `fuel_price` charges.
+    let fuel = policy.affordable_fuel(Amount::ONE, VmRuntime::Wasm);
+    assert!(policy.fuel_price(fuel, VmRuntime::Wasm).unwrap() <= Amount::ONE);
+    assert!(policy.fuel_price(fuel + 1, VmRuntime::Wasm).unwrap() > Amount::ONE);
+}
+
+#[test]
+fn test_validate_transaction_hints() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 1_000,
+        maximum_blob_size: 100,
+        maximum_published_blobs: 2,
+        maximum_blob_bytes_published_per_block: 150,
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    let hints = TransactionHints {
+        size: 500,
+        blob_bytes_read: 1_000,
+        published_blob_sizes: vec![100, 50],
+    };
+    assert_matches!(policy.validate_transaction_hints(&hints), Ok(()));
+
+    // A blob is too large, and so is the total: both are reported.
+    let hints = TransactionHints {
+        published_blob_sizes: vec![120, 50],
+        ..hints
+    };
+    let errors = policy.validate_transaction_hints(&hints).unwrap_err();
+    assert_matches!(
+        errors.as_slice(),
+        [
+            ExecutionError::BlobTooLarge,
+            ExecutionError::ExcessiveBlobBytesPublished
+        ]
+    );
+}
+
+#[test]
+fn test_fuel_unit_price() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(3),
+        evm_fuel_unit: Amount::from_nanos(7),
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    assert_eq!(
+        policy.fuel_unit_price(VmRuntime::Wasm),
+        Amount::from_attos(3)
+    );
+    assert_eq!(
+        policy.fuel_unit_price(VmRuntime::Evm),
+        Amount::from_nanos(7)
+    );
+
+    let policy = ResourceControlPolicy {
+        uncharged_resources: BTreeSet::from([ResourceKind::WasmFuel]),
+        ..policy
+    };
+    assert_eq!(policy.fuel_unit_price(VmRuntime::Wasm), Amount::ZERO);
+}
+
+#[test]
+fn test_uncharged_resources() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(5),
+        byte_read: Amount::from_attos(2),
+        uncharged_resources: BTreeSet::from([ResourceKind::HttpRequests]),
+        ..ResourceControlPolicy::no_fees()
+    };
+    assert_eq!(policy.unit_price(ResourceKind::HttpRequests), None);
+    assert_eq!(policy.http_requests_price(7).unwrap(), Amount::ZERO);
+    assert_eq!(policy.bytes_read_price(7).unwrap(), Amount::from_attos(14));
+
+    // A maximal price still means a prohibitively expensive resource.
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::MAX,
+        ..ResourceControlPolicy::no_fees()
+    };
+    assert_eq!(policy.unit_price(ResourceKind::WasmFuel), Some(Amount::MAX));
+    assert_eq!(policy.affordable_fuel(Amount::ONE, VmRuntime::Wasm), 0);
+}
+
+#[test]
+fn test_diff() {
+    let base = ResourceControlPolicy::testnet();
+    assert_eq!(base.diff(&base), vec![]);
+
+    let proposed = ResourceControlPolicy {
+        message: base.message.saturating_mul(2),
+        maximum_block_size: base.maximum_block_size / 2,
+        blob_bytecode_multiplier: base.blob_bytecode_multiplier + 1,
+        oracle_timeout_policy: OracleTimeoutPolicy::Truncate,
+        ..base.clone()
+    };
+    assert_eq!(
+        base.diff(&proposed),
+        vec![
+            PolicyFieldChange::Price {
+                field: "message",
+                old: base.message,
+                new: proposed.message,
+            },
+            PolicyFieldChange::Limit {
+                field: "maximum_block_size",
+                old: base.maximum_block_size,
+                new: proposed.maximum_block_size,
+            },
+            PolicyFieldChange::Setting {
+                field: "blob_bytecode_multiplier",
+                old: base.blob_bytecode_multiplier.to_string(),
+                new: proposed.blob_bytecode_multiplier.to_string(),
+            },
+            PolicyFieldChange::Setting {
+                field: "oracle_timeout_policy",
+                old: "Abort".to_string(),
+                new: "Truncate".to_string(),
+            },
+        ]
+    );
+
+    // Every field of the policy is either a price, a limit or another setting.
+    let field_count = base.price_schedule().len() + base.limits().len();
+    assert_eq!(field_count + base.other_settings().len(), 62);
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(ResourceControlPolicy::no_fees().validate(), Ok(()));
+    assert_eq!(ResourceControlPolicy::testnet().validate(), Ok(()));
+
+    let policy = ResourceControlPolicy {
+        maximum_wasm_fuel_per_block: 0,
+        ..ResourceControlPolicy::testnet()
+    };
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::ZeroLimit("maximum_wasm_fuel_per_block"))
+    );
+
+    let policy = ResourceControlPolicy {
+        maximum_bytes_read_per_block: 0,
+        ..ResourceControlPolicy::testnet()
+    };
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::ZeroLimit("maximum_bytes_read_per_block"))
+    );
+
+    let policy = ResourceControlPolicy {
+        byte_written: Amount::from_tokens(1_000_000),
+        maximum_bytes_written_per_block: u64::MAX,
+        ..ResourceControlPolicy::testnet()
+    };
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::PriceOverflow {
+            price: "byte_written",
+            limit: "maximum_bytes_written_per_block",
+        })
+    );
+
+    let policy = ResourceControlPolicy {
+        message_byte: Amount::MAX,
+        ..ResourceControlPolicy::testnet()
+    };
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::PriceOverflow {
+            price: "message_byte",
+            limit: "maximum_block_size",
+        })
+    );
+
+    let policy = ResourceControlPolicy {
+        operation: Amount::MAX,
+        ..ResourceControlPolicy::testnet()
+    };
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::CountPriceOverflow("operation"))
+    );
+
+    let mut policy = ResourceControlPolicy::testnet();
+    policy
+        .operation_price_overrides
+        .insert(OperationDiscriminant::Transfer, Amount::MAX);
+    assert_eq!(
+        policy.validate(),
+        Err(PolicyError::CountPriceOverflow("operation_price_overrides"))
+    );
+}
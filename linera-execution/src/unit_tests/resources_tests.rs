@@ -0,0 +1,3317 @@
+// Copyright (c) Zefchain Labs, Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Unit tests for resource tracking and fee charging.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    mem::size_of,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use assert_matches::assert_matches;
+use linera_base::{
+    crypto::CryptoHash,
+    data_types::{Amount, ArithmeticError, Blob, BlobContent, BlockHeight, Timestamp},
+    identifiers::{AccountOwner, ApplicationId, BlobType, ChainId},
+    vm::VmRuntime,
+};
+use linera_views::batch::Batch;
+use test_case::test_case;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::{layer, layer::SubscriberExt as _, Layer};
+
+use super::{
+    message_size, operation_size, write_varint, BalanceHolder, BlockComponent, Charge, ChargeKind,
+    CompactReader, CustomResourceId, LogRecord, RefundTarget, RemainingFuel, ResourceController,
+    ResourceEvent, ResourceKind, ResourceTracker, Sources, SpendingHeadroom, TransactionReceipt,
+    Utilization, MAX_ORACLE_LATENCY_SAMPLES, RUNTIME_AMOUNT_SIZE, RUNTIME_APPLICATION_ID_SIZE,
+    RUNTIME_BLOCK_HEIGHT_SIZE, RUNTIME_CHAIN_ID_SIZE, RUNTIME_OWNER_WEIGHT_SIZE,
+    RUNTIME_TIMESTAMP_SIZE,
+};
+use crate::{
+    test_utils::{dummy_chain_description, ResourceTrackerSpec, SystemExecutionState},
+    ExecutionError, Message, Operation, OperationDiscriminant, OracleTimeoutPolicy,
+    OutgoingMessage, ResourceControlPolicy, StorageRefundTarget, SystemMessage, SystemOperation,
+};
+
+/// Creates a user operation with an argument of `size` bytes.
+fn user_operation(size: usize) -> Operation {
+    Operation::User {
+        application_id: ApplicationId::default(),
+        bytes: vec![0; size],
+    }
+}
+
+/// Creates a controller funded with `balance`, charging according to `policy`.
+fn controller_with(
+    policy: ResourceControlPolicy,
+    balance: Amount,
+) -> ResourceController<Amount, ResourceTracker> {
+    ResourceController::new(Arc::new(policy), ResourceTracker::default(), balance)
+}
+
+#[test]
+fn test_size_of_runtime_operations() {
+    assert_eq!(RUNTIME_AMOUNT_SIZE as usize, size_of::<Amount>());
+    assert_eq!(
+        RUNTIME_APPLICATION_ID_SIZE as usize,
+        size_of::<ApplicationId>()
+    );
+    assert_eq!(RUNTIME_BLOCK_HEIGHT_SIZE as usize, size_of::<BlockHeight>());
+    assert_eq!(RUNTIME_CHAIN_ID_SIZE as usize, size_of::<ChainId>());
+    assert_eq!(RUNTIME_TIMESTAMP_SIZE as usize, size_of::<Timestamp>());
+    assert_eq!(RUNTIME_OWNER_WEIGHT_SIZE as usize, size_of::<u64>());
+}
+
+#[test]
+fn test_reset_tracker_keeps_policy_and_account() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    let policy = controller.policy().clone();
+
+    controller.track_fuel(100, VmRuntime::Wasm).unwrap();
+    controller.track_http_request().unwrap();
+    controller.track_bytes_read(7).unwrap();
+    assert_ne!(controller.tracker, ResourceTracker::default());
+    assert_eq!(controller.account, Amount::from_attos(790));
+
+    controller.reset_tracker();
+
+    assert_eq!(controller.tracker, ResourceTracker::default());
+    assert!(Arc::ptr_eq(controller.policy(), &policy));
+    assert_eq!(controller.account, Amount::from_attos(790));
+}
+
+#[test]
+fn test_fork() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    controller.track_http_request().unwrap();
+
+    let mut fork = controller.fork();
+    assert!(Arc::ptr_eq(fork.policy(), controller.policy()));
+    assert_eq!(fork.tracker, ResourceTracker::default());
+    assert_eq!(fork.account, Amount::from_attos(90));
+
+    // The fork is independent from the original controller.
+    fork.track_http_request().unwrap();
+    assert_eq!(fork.account, Amount::from_attos(80));
+    assert_eq!(controller.account, Amount::from_attos(90));
+    assert_eq!(controller.tracker.http_requests, 1);
+}
+
+#[test]
+fn test_utilization() {
+    let policy = ResourceControlPolicy {
+        maximum_wasm_fuel_per_block: 1_000,
+        maximum_evm_fuel_per_block: 0,
+        maximum_bytes_read_per_block: 200,
+        maximum_bytes_written_per_block: 100,
+        maximum_block_size: 50,
+        maximum_service_oracle_execution_ms: 4_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.tracker.wasm_fuel = 500;
+    controller.tracker.bytes_read = 100;
+    controller.tracker.bytes_written = 50;
+    controller.tracker.block_size = 25;
+    controller.tracker.service_oracle_execution = Duration::from_secs(2);
+
+    assert_eq!(
+        controller.utilization(),
+        Utilization {
+            wasm_fuel: 0.5,
+            evm_fuel: 0.0,
+            bytes_read: 0.5,
+            bytes_written: 0.5,
+            block_size: 0.5,
+            service_oracle_execution: 0.5,
+        }
+    );
+}
+
+#[test]
+fn test_block_size_fraction() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let tracker = ResourceTracker {
+        block_size: 500,
+        ..ResourceTracker::default()
+    };
+    assert_eq!(tracker.block_size_fraction(&policy), 0.5);
+    assert_eq!(ResourceTracker::default().block_size_fraction(&policy), 0.0);
+}
+
+#[test]
+fn test_at_any_limit() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    assert_eq!(controller.at_any_limit(), None);
+
+    controller.tracker.block_size = 99;
+    assert_eq!(controller.at_any_limit(), None);
+    controller.tracker.block_size = 100;
+    assert_eq!(controller.at_any_limit(), Some(ResourceKind::BlockSize));
+}
+
+#[test]
+fn test_tightest_constraints() {
+    let policy = ResourceControlPolicy {
+        maximum_wasm_fuel_per_block: 1_000,
+        maximum_bytes_read_per_block: 200,
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.tracker.wasm_fuel = 900;
+    controller.tracker.block_size = 50;
+
+    assert_eq!(
+        controller.tightest_constraints(2),
+        vec![
+            (ResourceKind::WasmFuel, 0.9),
+            (ResourceKind::BlockSize, 0.5)
+        ]
+    );
+    // Unused budgets are tied at zero and ordered by kind.
+    assert_eq!(
+        controller.tightest_constraints(4)[2..],
+        [(ResourceKind::EvmFuel, 0.0), (ResourceKind::BytesRead, 0.0)]
+    );
+    assert!(controller.tightest_constraints(0).is_empty());
+    assert_eq!(controller.tightest_constraints(100).len(), 6);
+}
+
+#[test]
+fn test_blob_bytes_read_ceiling() {
+    let policy = ResourceControlPolicy {
+        maximum_blob_bytes_read_per_block: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+
+    controller.track_blob_read(60).unwrap();
+    controller.track_blob_read(40).unwrap();
+    assert_matches!(
+        controller.track_blob_read(1),
+        Err(ExecutionError::ExcessiveBlobBytesRead)
+    );
+}
+
+#[test]
+fn test_blob_bytes_published_ceiling() {
+    let policy = ResourceControlPolicy {
+        maximum_blob_bytes_published_per_block: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+
+    controller
+        .track_blob_published(&Blob::new_data(vec![0; 60]))
+        .unwrap();
+    assert_matches!(
+        controller.track_blob_published(&Blob::new_data(vec![1; 41])),
+        Err(ExecutionError::ExcessiveBlobBytesPublished)
+    );
+}
+
+#[test]
+fn test_check_operation_fits() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_byte: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(20));
+    let system_operation = Operation::System(Box::new(SystemOperation::CloseChain));
+
+    controller.check_operation_fits(&system_operation).unwrap();
+    controller
+        .check_operation_fits(&user_operation(10))
+        .unwrap();
+    assert_matches!(
+        controller.check_operation_fits(&user_operation(11)),
+        Err(ExecutionError::FeesExceedFunding { fees, balance })
+            if fees == Amount::from_attos(21) && balance == Amount::from_attos(20)
+    );
+    // Nothing was charged.
+    assert_eq!(controller.tracker, ResourceTracker::default());
+    assert_eq!(controller.account, Amount::from_attos(20));
+
+    controller.tracker.operation_bytes = u64::MAX - 5;
+    assert_matches!(
+        controller.check_operation_fits(&user_operation(6)),
+        Err(ExecutionError::ArithmeticError(ArithmeticError::Overflow))
+    );
+    controller.check_operation_fits(&system_operation).unwrap();
+
+    controller.tracker.operations = u32::MAX;
+    assert_matches!(
+        controller.check_operation_fits(&system_operation),
+        Err(ExecutionError::ArithmeticError(ArithmeticError::Overflow))
+    );
+}
+
+#[test]
+fn test_normalized_fuel() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(3),
+        evm_fuel_unit: Amount::from_attos(7),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let tracker = ResourceTracker {
+        wasm_fuel: 100,
+        evm_fuel: 50,
+        ..ResourceTracker::default()
+    };
+    let expected = policy
+        .fuel_price(100, VmRuntime::Wasm)
+        .unwrap()
+        .try_add(policy.fuel_price(50, VmRuntime::Evm).unwrap())
+        .unwrap();
+
+    assert_eq!(tracker.normalized_fuel(&policy), expected);
+    assert_eq!(expected, Amount::from_attos(650));
+}
+
+#[test]
+fn test_labeled_charges() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_tokens(10));
+
+    controller
+        .charge("registration", Amount::from_tokens(3))
+        .unwrap();
+    controller
+        .charge("renewal", Amount::from_tokens(2))
+        .unwrap();
+    assert_matches!(
+        controller.charge("premium", Amount::from_tokens(6)),
+        Err(ExecutionError::FeesExceedFunding { .. })
+    );
+
+    assert_eq!(
+        controller.tracker.custom_charges,
+        vec![
+            ("registration", Amount::from_tokens(3)),
+            ("renewal", Amount::from_tokens(2)),
+        ]
+    );
+    assert_eq!(controller.account, Amount::from_tokens(5));
+}
+
+#[test]
+fn test_fuel_accessors() {
+    let policy = ResourceControlPolicy {
+        maximum_wasm_fuel_per_block: 1_000,
+        maximum_evm_fuel_per_block: 2_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.track_fuel(300, VmRuntime::Wasm).unwrap();
+    controller.track_fuel(700, VmRuntime::Evm).unwrap();
+
+    assert_eq!(controller.max_fuel(VmRuntime::Wasm), 1_000);
+    assert_eq!(controller.max_fuel(VmRuntime::Evm), 2_000);
+    assert_eq!(controller.used_fuel(VmRuntime::Wasm), 300);
+    assert_eq!(controller.used_fuel(VmRuntime::Evm), 700);
+}
+
+#[test]
+fn test_execute_with_grant() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    let grant = Amount::from_attos(30);
+
+    // The whole grant is spent: nothing is refunded.
+    controller
+        .execute_with_grant(grant, |controller| {
+            for _ in 0..3 {
+                controller.track_http_request()?;
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(controller.account, Amount::from_attos(70));
+
+    // Part of the grant is spent: the rest is refunded.
+    let value = controller
+        .execute_with_grant(grant, |controller| {
+            controller.track_http_request()?;
+            Ok(42)
+        })
+        .unwrap();
+    assert_eq!(value, 42);
+    assert_eq!(controller.account, Amount::from_attos(60));
+
+    // Execution fails after spending part of the grant: nothing is refunded.
+    assert_matches!(
+        controller.execute_with_grant(grant, |controller| {
+            controller.track_http_request()?;
+            Err::<(), _>(ExecutionError::ExcessiveRead)
+        }),
+        Err(ExecutionError::ExcessiveRead)
+    );
+    assert_eq!(controller.account, Amount::from_attos(30));
+    // The fees paid from the grants are only counted once, as part of the grants.
+    assert_eq!(controller.tracker.debited, Amount::from_attos(90));
+    assert_eq!(controller.tracker.credited, Amount::from_attos(20));
+    assert_eq!(controller.tracker.http_requests, 5);
+
+    // Grants count toward the fee cap.
+    controller.set_fee_cap(Some(Amount::from_attos(20)));
+    assert_matches!(
+        controller.execute_with_grant(grant, |_| Ok(())),
+        Err(ExecutionError::FeeCapExceeded { .. })
+    );
+
+    // The unspent part of a rounded-up grant is refunded in full, and only the charges made
+    // with the grant are logged.
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        fee_increment: Amount::from_attos(25),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100)).with_event_log();
+    controller
+        .execute_with_grant(Amount::from_attos(30), |controller| {
+            controller.track_http_request()
+        })
+        .unwrap();
+    // The grant is rounded up to 50, and the request to 25.
+    assert_eq!(controller.account, Amount::from_attos(75));
+    assert_eq!(
+        controller.event_log().unwrap(),
+        [ResourceEvent {
+            kind: ChargeKind::Resource(ResourceKind::HttpRequests),
+            quantity: 1,
+            fee: Amount::from_attos(10),
+        }]
+    );
+}
+
+#[test]
+fn test_failed_message_refunds_grant() {
+    let policy = Arc::new(ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    });
+    let mut controller = ResourceController::new(
+        policy.clone(),
+        ResourceTracker::default(),
+        Amount::from_attos(100),
+    );
+    let grant = Amount::from_attos(30);
+    controller.track_grant(grant).unwrap();
+    assert_eq!(controller.account, Amount::from_attos(70));
+
+    // The message spends part of its grant, then fails.
+    let mut message_controller = ResourceController::new(policy, &mut controller.tracker, grant);
+    message_controller.track_http_request().unwrap();
+    let charged = grant.try_sub(message_controller.account).unwrap();
+
+    assert_eq!(
+        controller.on_message_failure(grant, charged).unwrap(),
+        Amount::from_attos(20)
+    );
+    assert_eq!(controller.account, Amount::from_attos(90));
+    assert_eq!(controller.tracker.grants, Amount::from_attos(10));
+    assert_eq!(controller.tracker.http_requests, 1);
+
+    // Nothing is refunded if the whole grant was spent.
+    assert_eq!(
+        controller
+            .on_message_failure(grant, Amount::from_attos(40))
+            .unwrap(),
+        Amount::ZERO
+    );
+    assert_eq!(controller.account, Amount::from_attos(90));
+}
+
+#[test]
+fn test_fee_cap() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_byte: Amount::from_attos(1),
+        http_request: Amount::from_attos(5),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // An operation that stays under its cap.
+    controller.set_fee_cap(Some(Amount::from_attos(30)));
+    controller.track_http_request().unwrap();
+    controller.track_operation(&user_operation(10)).unwrap();
+    assert_eq!(controller.tracker.capped_fees, Amount::from_attos(25));
+    assert_eq!(controller.account, Amount::from_attos(975));
+
+    // An operation that trips its cap mid-way: the fees charged before the cap was
+    // reached are kept, but the operation bytes are not charged.
+    controller.set_fee_cap(Some(Amount::from_attos(20)));
+    controller.track_http_request().unwrap();
+    controller.track_http_request().unwrap();
+    assert_matches!(
+        controller.track_operation(&user_operation(10)),
+        Err(ExecutionError::FeeCapExceeded { cap, needed })
+            if cap == Amount::from_attos(20) && needed == Amount::from_attos(30)
+    );
+    assert_eq!(controller.account, Amount::from_attos(955));
+}
+
+#[test]
+fn test_fee_cap_counts_refunds_and_merges() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 20,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.set_fee_cap(Some(Amount::from_attos(40)));
+
+    // The unused part of a fuel reservation no longer counts toward the cap.
+    let reservation = controller.reserve_fuel(VmRuntime::Wasm).unwrap();
+    reservation.consume(5).unwrap();
+    assert_eq!(controller.tracker.capped_fees, Amount::from_attos(10));
+
+    // Balances merged out count toward the cap, and balances merged in are deducted.
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(30))
+        .unwrap();
+    assert_eq!(controller.tracker.capped_fees, Amount::from_attos(30));
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(55))
+        .unwrap();
+    assert_eq!(controller.tracker.capped_fees, Amount::from_attos(25));
+    assert_matches!(
+        controller.merge_balance(Amount::from_attos(50), Amount::from_attos(30)),
+        Err(ExecutionError::FeeCapExceeded { cap, needed })
+            if cap == Amount::from_attos(40) && needed == Amount::from_attos(45)
+    );
+    assert_eq!(controller.account, Amount::from_attos(975));
+}
+
+#[test]
+fn test_fuel_reservation() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // Partial consumption: the unused fuel is refunded.
+    let reservation = controller.reserve_fuel(VmRuntime::Wasm).unwrap();
+    assert_eq!(reservation.fuel(), 500);
+    reservation.consume(100).unwrap();
+    assert_eq!(controller.tracker.wasm_fuel, 100);
+    assert_eq!(controller.account, Amount::from_attos(800));
+
+    // Full consumption: nothing is refunded.
+    let reservation = controller.reserve_fuel(VmRuntime::Wasm).unwrap();
+    assert_eq!(reservation.fuel(), 400);
+    reservation.consume(400).unwrap();
+    assert_eq!(controller.tracker.wasm_fuel, 500);
+    assert_eq!(controller.account, Amount::ZERO);
+}
+
+#[test]
+fn test_dropped_fuel_reservation_is_fully_charged() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    drop(controller.reserve_fuel(VmRuntime::Wasm).unwrap());
+    assert_eq!(controller.tracker.wasm_fuel, 100);
+    assert_eq!(controller.account, Amount::from_attos(800));
+
+    let reservation = controller.reserve_fuel(VmRuntime::Wasm).unwrap();
+    assert_eq!(reservation.fuel(), 0);
+    assert_matches!(
+        reservation.consume(1),
+        Err(ExecutionError::MaximumFuelExceeded(VmRuntime::Wasm))
+    );
+}
+
+#[test]
+fn test_refund_targets() {
+    let credit = |refund_target| {
+        let (mut grant, mut owner) = (Amount::from_attos(10), Amount::from_attos(20));
+        let mut sources = Sources {
+            sources: vec![&mut grant, &mut owner],
+            refund_target: RefundTarget::default(),
+            owners: Vec::new(),
+            chain_account: None,
+        }
+        .with_refund_target(refund_target);
+        sources.try_add_assign(Amount::from_attos(7)).unwrap();
+        assert_eq!(sources.balance().unwrap(), Amount::from_attos(37));
+        (grant, owner)
+    };
+
+    assert_eq!(
+        credit(RefundTarget::LastSource),
+        (Amount::from_attos(10), Amount::from_attos(27))
+    );
+    assert_eq!(
+        credit(RefundTarget::FirstSource),
+        (Amount::from_attos(17), Amount::from_attos(20))
+    );
+    assert_eq!(
+        credit(RefundTarget::SplitEvenly),
+        (Amount::from_attos(13), Amount::from_attos(24))
+    );
+}
+
+#[test]
+fn test_sources_new() {
+    assert_matches!(
+        Sources::new(Vec::new()),
+        Err(ExecutionError::NoFundingSources)
+    );
+
+    let (mut first, mut second, mut third) = (
+        Amount::from_attos(10),
+        Amount::from_attos(20),
+        Amount::from_attos(30),
+    );
+    let mut sources = Sources::new(vec![&mut first, &mut second, &mut third]).unwrap();
+    assert_eq!(sources.balance().unwrap(), Amount::from_attos(60));
+    sources.try_sub_assign(Amount::from_attos(45)).unwrap();
+    assert_eq!(
+        sources.balances(),
+        [Amount::ZERO, Amount::ZERO, Amount::from_attos(15)]
+    );
+    sources.try_sub_assign(Amount::from_attos(15)).unwrap();
+    assert_eq!(sources.balance().unwrap(), Amount::ZERO);
+    assert_matches!(
+        sources.try_sub_assign(Amount::from_attos(1)),
+        Err(ArithmeticError::Underflow)
+    );
+    assert_eq!(
+        (first, second, third),
+        (Amount::ZERO, Amount::ZERO, Amount::ZERO)
+    );
+}
+
+#[test]
+fn test_committee_blobs_are_counted_but_not_charged() {
+    let policy = ResourceControlPolicy {
+        blob_published: Amount::from_attos(100),
+        blob_byte_published: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    controller
+        .track_blob_published(&Blob::new_committee(vec![0; 30]))
+        .unwrap();
+    controller
+        .track_blob_published(&Blob::new_data(vec![0; 10]))
+        .unwrap();
+    controller
+        .track_blob_published(&Blob::new_committee(vec![1; 20]))
+        .unwrap();
+
+    assert_eq!(controller.tracker.committee_blobs_published, 2);
+    assert_eq!(controller.tracker.committee_blob_bytes_published, 50);
+    assert_eq!(controller.tracker.blobs_published, 1);
+    assert_eq!(controller.tracker.blob_bytes_published, 10);
+    assert_eq!(controller.account, Amount::from_attos(890));
+}
+
+#[test]
+fn test_bulk_read_operations() {
+    let policy = ResourceControlPolicy {
+        read_operation: Amount::from_attos(7),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut bulk = controller_with(policy.clone(), Amount::from_attos(100));
+    let mut single = controller_with(policy, Amount::from_attos(100));
+
+    bulk.track_read_operations(5).unwrap();
+    for _ in 0..5 {
+        single.track_read_operation().unwrap();
+    }
+
+    assert_eq!(bulk.tracker, single.tracker);
+    assert_eq!(bulk.tracker.read_operations, 5);
+    assert_eq!(bulk.account, single.account);
+    assert_eq!(bulk.account, Amount::from_attos(65));
+}
+
+#[test]
+fn test_refunds_cannot_exceed_charges() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+
+    // Without checks, a refund may exceed the charges.
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(5))
+        .unwrap();
+    assert_eq!(controller.account, Amount::from_attos(105));
+
+    controller.reset_tracker();
+    controller.check_refunds(Amount::from_attos(5));
+    controller.track_http_request().unwrap();
+    controller.track_http_request().unwrap();
+    assert_eq!(controller.tracker.debited, Amount::from_attos(20));
+
+    // Up to the charges plus the initial grants can be refunded.
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(20))
+        .unwrap();
+    assert_matches!(
+        controller.merge_balance(Amount::ZERO, Amount::from_attos(6)),
+        Err(ExecutionError::RefundExceedsCharges)
+    );
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(5))
+        .unwrap();
+    assert_eq!(controller.tracker.credited, Amount::from_attos(25));
+    assert_eq!(controller.account, Amount::from_attos(110));
+}
+
+/// A tracing layer recording the fields of every event.
+#[derive(Clone, Default)]
+struct EventRecorder(Arc<Mutex<Vec<BTreeMap<&'static str, String>>>>);
+
+impl<S: tracing::Subscriber> Layer<S> for EventRecorder {
+    fn on_event(&self, event: &tracing::Event<'_>, _context: layer::Context<'_, S>) {
+        let mut fields = FieldRecorder::default();
+        event.record(&mut fields);
+        self.0.lock().unwrap().push(fields.0);
+    }
+}
+
+#[derive(Default)]
+struct FieldRecorder(BTreeMap<&'static str, String>);
+
+impl Visit for FieldRecorder {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+#[test]
+fn test_fee_events() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    let recorder = EventRecorder::default();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        controller.track_fuel(100, VmRuntime::Wasm).unwrap();
+    });
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event["kind"], "WasmFuel");
+    assert_eq!(event["quantity"], "100");
+    assert_eq!(event["unit_price"], Amount::from_attos(2).to_string());
+    assert_eq!(event["fees"], Amount::from_attos(200).to_string());
+    assert_eq!(event["balance"], Amount::from_attos(800).to_string());
+}
+
+#[test]
+fn test_snapshot_and_restore() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_fuel(100, VmRuntime::Wasm).unwrap();
+    let snapshot = controller.snapshot().unwrap();
+    let (tracker, account) = (controller.tracker.clone(), controller.account);
+
+    controller.track_fuel(50, VmRuntime::Wasm).unwrap();
+    controller.track_http_request().unwrap();
+    controller
+        .charge("registration", Amount::from_attos(7))
+        .unwrap();
+    controller.restore(snapshot.clone()).unwrap();
+    assert_eq!(controller.tracker, tracker);
+    assert_eq!(controller.account, account);
+
+    // Restoring also undoes credits.
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(30))
+        .unwrap();
+    controller.restore(snapshot).unwrap();
+    assert_eq!(controller.tracker, tracker);
+    assert_eq!(controller.account, Amount::from_attos(800));
+}
+
+#[test]
+fn test_blob_type_pricing() {
+    let policy = ResourceControlPolicy {
+        blob_published: Amount::from_attos(100),
+        blob_byte_published: Amount::from_attos(1),
+        blob_description_multiplier: 2,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    controller
+        .track_blob_published(&Blob::new_data(vec![0; 10]))
+        .unwrap();
+    assert_eq!(controller.account, Amount::from_attos(890));
+
+    let description = BlobContent::new(BlobType::ApplicationDescription, vec![0; 10]);
+    controller
+        .track_blob_published(&Blob::new(description))
+        .unwrap();
+    assert_eq!(controller.account, Amount::from_attos(670));
+}
+
+#[test]
+fn test_can_afford() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(10));
+
+    assert!(controller.can_afford(Amount::from_attos(9)));
+    assert!(controller.can_afford(Amount::from_attos(10)));
+    assert!(!controller.can_afford(Amount::from_attos(11)));
+    assert_eq!(controller.account, Amount::from_attos(10));
+
+    // Balances summing beyond `Amount::MAX` saturate, so they afford any fee.
+    let (mut first, mut second) = (Amount::MAX, Amount::ONE);
+    let sources = Sources {
+        sources: vec![&mut first, &mut second],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    let controller = ResourceController::new(
+        controller.policy().clone(),
+        &mut controller.tracker,
+        sources,
+    );
+    assert!(controller.can_afford(Amount::MAX));
+}
+
+#[test]
+fn test_uncharged_resource() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::ONE,
+        wasm_fuel_unit: Amount::ONE,
+        maximum_wasm_fuel_per_block: 1_000,
+        uncharged_resources: BTreeSet::from([ResourceKind::HttpRequests, ResourceKind::WasmFuel]),
+        ..ResourceControlPolicy::no_fees()
+    };
+    assert_eq!(policy.unit_price(ResourceKind::HttpRequests), None);
+    let mut controller = controller_with(policy, Amount::from_attos(10));
+
+    controller.track_http_request().unwrap();
+    controller.track_http_request().unwrap();
+    assert_eq!(controller.tracker.http_requests, 2);
+    assert_eq!(controller.account, Amount::from_attos(10));
+
+    // Limits are still enforced.
+    assert_eq!(controller.remaining_fuel(VmRuntime::Wasm), 1_000);
+    controller.track_fuel(1_000, VmRuntime::Wasm).unwrap();
+    assert_matches!(
+        controller.track_fuel(1, VmRuntime::Wasm),
+        Err(ExecutionError::MaximumFuelExceeded(VmRuntime::Wasm))
+    );
+    assert_eq!(controller.account, Amount::from_attos(10));
+}
+
+#[test]
+fn test_max_single_charge() {
+    let policy = ResourceControlPolicy {
+        byte_read: Amount::from_attos(1),
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    for count in [30, 70, 20] {
+        controller.track_bytes_read(count).unwrap();
+    }
+    controller.track_http_request().unwrap();
+
+    assert_eq!(
+        controller.tracker.max_single_charge,
+        BTreeMap::from([
+            (ResourceKind::BytesRead, Amount::from_attos(70)),
+            (ResourceKind::HttpRequests, Amount::from_attos(10)),
+        ])
+    );
+}
+
+#[test]
+fn test_track_runtime_value() {
+    let policy = ResourceControlPolicy {
+        byte_runtime: Amount::from_attos(3),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy.clone(), Amount::from_attos(1_000));
+    let value: Vec<u64> = vec![1, 2, 3];
+    let size = bcs::serialized_size(&value).unwrap() as u32;
+
+    controller.track_runtime_value(&value).unwrap();
+
+    assert_eq!(controller.tracker.bytes_runtime, size);
+    assert_eq!(
+        controller.account,
+        Amount::from_attos(1_000)
+            .try_sub(policy.bytes_runtime_price(size).unwrap())
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_track_runtime_reads_in_bulk() {
+    let policy = ResourceControlPolicy {
+        byte_runtime: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    controller.track_runtime_timestamps(5).unwrap();
+    assert_eq!(controller.tracker.bytes_runtime, 5 * RUNTIME_TIMESTAMP_SIZE);
+    controller.track_runtime_block_heights(3).unwrap();
+    assert_eq!(
+        controller.tracker.bytes_runtime,
+        5 * RUNTIME_TIMESTAMP_SIZE + 3 * RUNTIME_BLOCK_HEIGHT_SIZE
+    );
+    assert_eq!(controller.account, Amount::from_attos(1_000 - 64));
+
+    assert_matches!(
+        controller.track_runtime_timestamps(usize::MAX),
+        Err(ExecutionError::ArithmeticError(ArithmeticError::Overflow))
+    );
+}
+
+#[test]
+fn test_runtime_bytes_ceiling() {
+    let policy = ResourceControlPolicy {
+        maximum_bytes_runtime_per_block: u64::from(2 * RUNTIME_CHAIN_ID_SIZE),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+
+    controller.track_runtime_chain_id().unwrap();
+    controller.track_runtime_chain_id().unwrap();
+    assert_matches!(
+        controller.track_runtime_timestamp(),
+        Err(ExecutionError::ExcessiveRuntimeReads)
+    );
+}
+
+/// A value that must never be serialized.
+struct Unserializable;
+
+impl serde::Serialize for Unserializable {
+    fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        panic!("the value should not be serialized");
+    }
+}
+
+#[test]
+fn test_full_block_skips_serialization() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.track_block_size(100).unwrap();
+
+    assert_matches!(
+        controller.track_block_size_of(&Unserializable),
+        Err(ExecutionError::BlockTooLarge)
+    );
+    assert_eq!(controller.tracker.block_size, 100);
+}
+
+#[test]
+fn test_report_json() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        read_operation: Amount::from_attos(10),
+        byte_read: Amount::from_attos(1),
+        operation: Amount::from_attos(100),
+        operation_byte: Amount::from_attos(3),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let tracker = ResourceTracker {
+        wasm_fuel: 500,
+        read_operations: 4,
+        bytes_read: 20,
+        operations: 2,
+        operation_bytes: 7,
+        service_oracle_execution: Duration::from_millis(1_234),
+        ..ResourceTracker::default()
+    };
+
+    let report = tracker.to_report_json(&policy);
+
+    let mut keys = report.as_object().unwrap().keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, ["costs", "counters", "utilization"]);
+    assert_eq!(report["counters"]["wasm_fuel"], 500);
+    assert_eq!(report["counters"]["service_oracle_execution_ms"], 1_234);
+    assert_eq!(report["utilization"]["wasm_fuel"], 0.5);
+
+    let fuel = Amount::from_attos(2 * 500);
+    let storage = Amount::from_attos(10 * 4 + 20);
+    let operations = Amount::from_attos(100 * 2 + 3 * 7);
+    let total = fuel.saturating_add(storage).saturating_add(operations);
+    assert_eq!(report["costs"]["fuel"], fuel.to_string());
+    assert_eq!(report["costs"]["storage"], storage.to_string());
+    assert_eq!(report["costs"]["operations"], operations.to_string());
+    assert_eq!(report["costs"]["messages"], Amount::ZERO.to_string());
+    assert_eq!(report["costs"]["total"], total.to_string());
+}
+
+#[test]
+fn test_read_charge_granularity() {
+    let policy = ResourceControlPolicy {
+        byte_read: Amount::from_attos(3),
+        read_charge_granularity: 64,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let initial_balance = Amount::from_attos(10_000);
+
+    let mut chatty = controller_with(policy.clone(), initial_balance);
+    for _ in 0..100 {
+        chatty.track_bytes_read(7).unwrap();
+    }
+    assert_eq!(chatty.tracker.bytes_read, 700);
+    assert_eq!(chatty.tracker.pending_bytes_read, 700 % 64);
+    assert_eq!(
+        chatty.balance().unwrap(),
+        Amount::from_attos(10_000 - 3 * (700 - 700 % 64))
+    );
+    // The pending bytes are taken into account before they are charged.
+    let available = Amount::from_attos(10_000 - 3 * 700);
+    assert!(chatty.can_afford(available));
+    assert!(!chatty.can_afford(available.saturating_add(Amount::from_attos(1))));
+    chatty.flush_pending_charges().unwrap();
+    assert_eq!(chatty.tracker.pending_bytes_read, 0);
+
+    let mut single = controller_with(policy, initial_balance);
+    single.track_bytes_read(700).unwrap();
+    single.flush_pending_charges().unwrap();
+
+    assert_eq!(chatty.balance().unwrap(), single.balance().unwrap());
+    assert_eq!(
+        single.balance().unwrap(),
+        Amount::from_attos(10_000 - 3 * 700)
+    );
+}
+
+#[test]
+fn test_track_deserialization() {
+    let policy = ResourceControlPolicy {
+        byte_read: Amount::from_attos(1),
+        byte_deserialized: Amount::from_attos(2),
+        maximum_bytes_deserialized_per_block: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_bytes_read(10).unwrap();
+    controller.track_deserialization(30).unwrap();
+
+    // Deserialization is counted and charged separately from the bytes read.
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        bytes_read: Some(10),
+        deserialized_bytes: Some(30),
+        ..ResourceTrackerSpec::default()
+    });
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(930));
+    let receipt = controller.receipt();
+    assert_eq!(receipt.fees["storage"], Amount::from_attos(10));
+    assert_eq!(receipt.fees["deserialization"], Amount::from_attos(60));
+
+    assert_matches!(
+        controller.track_deserialization(70),
+        Err(ExecutionError::ExcessiveDeserialization)
+    );
+}
+
+#[test]
+fn test_analytics_mode_ignores_limits() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1),
+        byte_read: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 100,
+        maximum_bytes_read_per_block: 100,
+        maximum_bytes_written_per_block: 100,
+        maximum_block_size: 100,
+        maximum_service_oracle_execution_ms: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let workload = |controller: &mut ResourceController| -> Result<(), ExecutionError> {
+        controller.track_fuel(500, VmRuntime::Wasm)?;
+        controller.track_bytes_read(300)?;
+        controller.track_bytes_written(300)?;
+        controller.track_block_size(1_000)?;
+        controller.track_service_oracle_execution(Duration::from_millis(1_000))?;
+        Ok(())
+    };
+
+    let mut enforcing = controller_with(policy.clone(), Amount::from_attos(10_000));
+    assert_matches!(
+        workload(&mut enforcing),
+        Err(ExecutionError::MaximumFuelExceeded(VmRuntime::Wasm))
+    );
+
+    let mut analytics =
+        ResourceController::analytics_mode(Arc::new(policy), Amount::from_attos(10_000));
+    workload(&mut analytics).unwrap();
+    assert_eq!(analytics.tracker.wasm_fuel, 500);
+    assert_eq!(analytics.tracker.bytes_read, 300);
+    assert_eq!(analytics.tracker.bytes_written, 300);
+    assert_eq!(analytics.tracker.block_size, 1_000);
+    assert_eq!(
+        analytics.tracker.service_oracle_execution,
+        Duration::from_millis(1_000)
+    );
+    // Fees are still charged.
+    assert_eq!(
+        analytics.balance().unwrap(),
+        Amount::from_attos(10_000 - 500 - 2 * 300)
+    );
+    assert_eq!(analytics.remaining_fuel(VmRuntime::Wasm), 8_900);
+
+    assert_eq!(
+        analytics.remaining_service_oracle_execution_time().unwrap(),
+        Duration::MAX
+    );
+
+    analytics.reset_tracker();
+    assert!(analytics.is_analytics_mode());
+    // Controllers derived from it are in analytics mode too.
+    assert!(analytics.fork().is_analytics_mode());
+    let (fuel, _) = analytics
+        .with_budget(Amount::from_attos(1_000), |controller| {
+            controller.track_fuel(500, VmRuntime::Wasm)?;
+            Ok(controller.tracker.wasm_fuel)
+        })
+        .unwrap();
+    assert_eq!(fuel, 500);
+}
+
+#[test]
+fn test_grants_by_message() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_tokens(10));
+
+    controller
+        .track_grant_for(0, Amount::from_tokens(2))
+        .unwrap();
+    controller
+        .track_grant_for(1, Amount::from_tokens(3))
+        .unwrap();
+    assert_eq!(controller.tracker.grants, Amount::from_tokens(5));
+    assert_eq!(controller.balance().unwrap(), Amount::from_tokens(5));
+
+    controller
+        .refund_grant_for(0, Amount::from_tokens(2))
+        .unwrap();
+    assert_eq!(
+        controller.tracker.grants_by_message,
+        [(0, Amount::ZERO), (1, Amount::from_tokens(3))]
+    );
+    assert_eq!(controller.tracker.grants, Amount::from_tokens(3));
+    assert_eq!(controller.balance().unwrap(), Amount::from_tokens(7));
+
+    // The first message has nothing left to refund, and there is no third message.
+    assert_matches!(
+        controller.refund_grant_for(0, Amount::ONE),
+        Err(ExecutionError::RefundExceedsCharges)
+    );
+    assert_matches!(
+        controller.refund_grant_for(2, Amount::ONE),
+        Err(ExecutionError::RefundExceedsCharges)
+    );
+    assert_eq!(controller.balance().unwrap(), Amount::from_tokens(7));
+}
+
+#[test]
+fn test_tracker_matches_partial_spec() {
+    let tracker = ResourceTracker {
+        wasm_fuel: 100,
+        operations: 2,
+        grants: Amount::ONE,
+        ..ResourceTracker::default()
+    };
+    tracker.assert_matches(&ResourceTrackerSpec {
+        wasm_fuel: Some(100),
+        operations: Some(2),
+        messages: Some(0),
+        ..ResourceTrackerSpec::default()
+    });
+}
+
+#[test]
+#[should_panic(expected = "operations: expected 3, got 2")]
+fn test_tracker_mismatch_is_reported() {
+    let tracker = ResourceTracker {
+        wasm_fuel: 100,
+        operations: 2,
+        ..ResourceTracker::default()
+    };
+    tracker.assert_matches(&ResourceTrackerSpec {
+        wasm_fuel: Some(100),
+        operations: Some(3),
+        ..ResourceTrackerSpec::default()
+    });
+}
+
+#[test]
+fn test_operation_pricing_by_serialized_size() {
+    let policy = ResourceControlPolicy {
+        operation_byte: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let operation = user_operation(10);
+    let serialized_size = bcs::serialized_size(&operation).unwrap() as u64;
+    assert!(serialized_size > 10);
+
+    let mut by_argument = controller_with(policy.clone(), Amount::from_attos(1_000));
+    by_argument.track_operation(&operation).unwrap();
+    assert_eq!(by_argument.tracker.operation_bytes, 10);
+    assert_eq!(by_argument.balance().unwrap(), Amount::from_attos(990));
+
+    let policy = ResourceControlPolicy {
+        price_operations_by_serialized_size: true,
+        ..policy
+    };
+    let mut by_serialized_size = controller_with(policy, Amount::from_attos(1_000));
+    by_serialized_size.check_operation_fits(&operation).unwrap();
+    by_serialized_size.track_operation(&operation).unwrap();
+    assert_eq!(by_serialized_size.tracker.operation_bytes, serialized_size);
+    assert_eq!(
+        by_serialized_size.balance().unwrap(),
+        Amount::from_attos(1_000 - u128::from(serialized_size))
+    );
+}
+
+#[test]
+fn test_total_io_bytes() {
+    let tracker = ResourceTracker {
+        bytes_read: 1,
+        bytes_written: 2,
+        blob_bytes_read: 3,
+        blob_bytes_published: 4,
+        ..ResourceTracker::default()
+    };
+    assert_eq!(tracker.total_io_bytes(), 10);
+
+    let tracker = ResourceTracker {
+        bytes_read: u64::MAX - 1,
+        bytes_written: 2,
+        blob_bytes_read: u64::MAX,
+        ..tracker
+    };
+    assert_eq!(tracker.total_io_bytes(), u64::MAX);
+}
+
+#[test]
+fn test_fee_increment() {
+    let policy = ResourceControlPolicy {
+        read_operation: Amount::from_attos(3),
+        byte_read: Amount::from_attos(1),
+        fee_increment: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // Charges of 3, 2 and 12 attos are rounded up to 10, 10 and 20.
+    controller.track_read_operations(1).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(990));
+    controller.track_bytes_read(2).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(980));
+    controller.track_bytes_read(12).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(960));
+
+    assert_eq!(controller.tracker.debited, Amount::from_attos(40));
+    assert_eq!(controller.tracker.rounding_residue, Amount::from_attos(23));
+    assert_eq!(
+        controller.receipt().fees["rounding"],
+        Amount::from_attos(23)
+    );
+}
+
+#[test]
+fn test_minimum_operation_fee() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_byte: Amount::from_attos(1),
+        minimum_operation_fee: Amount::from_attos(50),
+        ..ResourceControlPolicy::no_fees()
+    };
+
+    // An operation computed at 10 + 5 attos is charged the minimum.
+    let mut controller = controller_with(policy.clone(), Amount::from_attos(1_000));
+    controller.track_operation(&user_operation(5)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(950));
+    assert_eq!(
+        controller.tracker.minimum_fee_surcharges,
+        Amount::from_attos(35)
+    );
+
+    // An operation computed at 10 + 100 attos is charged that amount.
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_operation(&user_operation(100)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(890));
+    assert_eq!(controller.tracker.minimum_fee_surcharges, Amount::ZERO);
+}
+
+#[test]
+fn test_warmup_operations() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_byte: Amount::from_attos(1),
+        warmup_operations: 2,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+
+    // The first two operations are counted but free.
+    for _ in 0..2 {
+        controller.check_operation_fits(&user_operation(5)).unwrap();
+        controller.track_operation(&user_operation(5)).unwrap();
+    }
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(100));
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        operations: Some(2),
+        operation_bytes: Some(10),
+        ..ResourceTrackerSpec::default()
+    });
+    assert_eq!(controller.tracker.warmup_used, 2);
+
+    // The third one is charged.
+    controller.track_operation(&user_operation(5)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(85));
+    assert_eq!(controller.tracker.operations, 3);
+    assert_eq!(controller.tracker.warmup_used, 2);
+}
+
+#[test]
+fn test_minimum_operation_fee_must_fit() {
+    let policy = ResourceControlPolicy {
+        minimum_operation_fee: Amount::from_attos(50),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let controller = controller_with(policy, Amount::from_attos(49));
+    assert_matches!(
+        controller.check_operation_fits(&user_operation(5)),
+        Err(ExecutionError::FeesExceedFunding { fees, .. }) if fees == Amount::from_attos(50)
+    );
+}
+
+#[test]
+fn test_operation_price_overrides() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(10),
+        operation_price_overrides: BTreeMap::from([(
+            OperationDiscriminant::CloseChain,
+            Amount::from_attos(3),
+        )]),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let close_chain = Operation::system(SystemOperation::CloseChain);
+    assert_eq!(
+        close_chain.discriminant(),
+        OperationDiscriminant::CloseChain
+    );
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // The overridden type is charged its own price, the others the flat price.
+    controller.track_operation(&close_chain).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(997));
+    controller.track_operation(&user_operation(0)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(987));
+
+    assert_eq!(
+        controller.tracker.operations_by_type,
+        BTreeMap::from([
+            (OperationDiscriminant::User, 1),
+            (OperationDiscriminant::CloseChain, 1),
+        ])
+    );
+    assert_eq!(
+        controller.receipt().fees["operations"],
+        Amount::from_attos(13)
+    );
+}
+
+#[test]
+fn test_with_budget() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    let budget = Amount::from_attos(25);
+
+    let (value, leftover) = controller
+        .with_budget(budget, |controller| {
+            controller.track_http_request()?;
+            controller.track_http_request()?;
+            Ok("done")
+        })
+        .unwrap();
+    assert_eq!(value, "done");
+    assert_eq!(leftover, Amount::from_attos(5));
+    assert_eq!(controller.tracker.http_requests, 2);
+    // What was spent from the budget is taken from the account.
+    assert_eq!(controller.account, Amount::from_attos(80));
+    assert_eq!(controller.tracker.debited, Amount::from_attos(20));
+
+    // Overspending the budget leaves the tracker and the account unchanged.
+    assert_matches!(
+        controller.with_budget(budget, |controller| {
+            for _ in 0..3 {
+                controller.track_http_request()?;
+            }
+            Ok(())
+        }),
+        Err(ExecutionError::FeesExceedFunding { .. })
+    );
+    assert_eq!(controller.account, Amount::from_attos(80));
+    assert_eq!(controller.tracker.http_requests, 2);
+    assert_eq!(controller.tracker.debited, Amount::from_attos(20));
+
+    // So does a budget that the account cannot pay for.
+    let mut controller = controller_with(
+        ResourceControlPolicy {
+            http_request: Amount::from_attos(10),
+            ..ResourceControlPolicy::no_fees()
+        },
+        Amount::from_attos(5),
+    );
+    assert_matches!(
+        controller.with_budget(budget, |controller| controller.track_http_request()),
+        Err(ExecutionError::FeesExceedFunding { .. })
+    );
+    assert_eq!(controller.account, Amount::from_attos(5));
+    assert_eq!(controller.tracker.http_requests, 0);
+}
+
+#[test]
+fn test_fuel_halt_reasons() {
+    // The fuel limit is reached while funds remain.
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1),
+        maximum_wasm_fuel_per_block: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    assert_matches!(
+        controller.track_fuel(101, VmRuntime::Wasm),
+        Err(ExecutionError::MaximumFuelExceeded(VmRuntime::Wasm))
+    );
+
+    // The balance runs out before the fuel limit.
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    controller.track_fuel(40, VmRuntime::Wasm).unwrap();
+    assert_matches!(
+        controller.track_fuel(11, VmRuntime::Wasm),
+        Err(ExecutionError::InsufficientFundsForFuel { fuel, needed, balance })
+            if fuel == 11
+                && needed == Amount::from_attos(22)
+                && balance == Amount::from_attos(20)
+    );
+    // Nothing was recorded for the unaffordable fuel.
+    assert_eq!(controller.tracker.wasm_fuel, 40);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(20));
+}
+
+#[test]
+fn test_grant_in_other_denomination() {
+    let policy = ResourceControlPolicy {
+        grant_denomination: Some("USD".to_string()),
+        // One unit of the grant denomination is worth 2.5 native tokens.
+        grant_exchange_rate: Amount::from_millis(2_500),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy.clone(), Amount::from_tokens(10));
+
+    controller.track_grant(Amount::from_tokens(2)).unwrap();
+    assert_eq!(controller.tracker.grants, Amount::from_tokens(5));
+    assert_eq!(controller.balance().unwrap(), Amount::from_tokens(5));
+
+    // Fractions of an atto are rounded up.
+    controller.track_grant(Amount::from_attos(1)).unwrap();
+    assert_eq!(
+        controller.tracker.grants,
+        Amount::from_tokens(5).saturating_add(Amount::from_attos(3))
+    );
+
+    // Grants to messages are converted too, and recorded in native tokens.
+    let granted = controller
+        .track_grant_for(0, Amount::from_tokens(1))
+        .unwrap();
+    assert_eq!(granted, Amount::from_millis(2_500));
+    assert_eq!(
+        controller.tracker.grants_by_message,
+        vec![(0, Amount::from_millis(2_500))]
+    );
+
+    // Grants that are already in native tokens, e.g. priced resources, are not converted.
+    controller
+        .track_native_grant_for(1, Amount::from_tokens(1))
+        .unwrap();
+    assert_eq!(
+        controller.tracker.grants_by_message,
+        vec![(0, Amount::from_millis(2_500)), (1, Amount::from_tokens(1))]
+    );
+
+    // Large grants do not overflow the intermediate products.
+    let mut controller = controller_with(policy, Amount::from_tokens(10_000));
+    controller.track_grant(Amount::from_tokens(2_000)).unwrap();
+    assert_eq!(controller.tracker.grants, Amount::from_tokens(5_000));
+
+    // Without a denomination, grants are in native tokens.
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_tokens(10));
+    controller.track_grant(Amount::from_tokens(2)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_tokens(8));
+}
+
+#[test]
+fn test_finalize_rejects_negative_net_fees() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    controller.track_http_request().unwrap();
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(10))
+        .unwrap();
+    controller.finalize().unwrap();
+
+    controller
+        .merge_balance(Amount::ZERO, Amount::from_attos(1))
+        .unwrap();
+    assert_matches!(
+        controller.finalize(),
+        Err(ExecutionError::NegativeNetFees { debited, credited })
+            if debited == Amount::from_attos(10) && credited == Amount::from_attos(11)
+    );
+}
+
+#[test]
+fn test_http_response_bytes() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(100),
+        http_response_byte: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy.clone(), Amount::from_attos(1_000));
+    controller.track_http_request().unwrap();
+    controller.track_http_response(250).unwrap();
+
+    assert_eq!(controller.tracker.http_response_bytes, 250);
+    assert_eq!(
+        controller.balance().unwrap(),
+        Amount::from_attos(1_000)
+            .try_sub(policy.estimate_http_cost(250).unwrap())
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_batched_blob_reads() {
+    let policy = ResourceControlPolicy {
+        blob_read: Amount::from_attos(100),
+        blob_byte_read: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let sizes = [10, 20, 30, 40];
+
+    let mut looped = controller_with(policy.clone(), Amount::from_attos(10_000));
+    for size in sizes {
+        looped.track_blob_read(size).unwrap();
+    }
+    let mut batched = controller_with(policy, Amount::from_attos(10_000));
+    batched.track_blobs_read(&sizes).unwrap();
+
+    assert_eq!(batched.tracker.blobs_read, looped.tracker.blobs_read);
+    assert_eq!(
+        batched.tracker.blob_bytes_read,
+        looped.tracker.blob_bytes_read
+    );
+    assert_eq!(
+        looped.balance().unwrap(),
+        Amount::from_attos(10_000 - 400 - 100)
+    );
+    assert_eq!(batched.balance().unwrap(), looped.balance().unwrap());
+    assert_eq!(batched.receipt().fees["blobs"], Amount::from_attos(500));
+
+    // An empty batch is free.
+    batched.track_blobs_read(&[]).unwrap();
+    assert_eq!(batched.balance().unwrap(), Amount::from_attos(9_500));
+}
+
+#[test]
+fn test_fixed_blob_prices() {
+    let policy = ResourceControlPolicy {
+        blob_read_fixed: Amount::from_attos(7),
+        blob_published_fixed: Amount::from_attos(50),
+        blob_description_multiplier: 2,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // The fixed price does not depend on the size, even for empty blobs.
+    controller.track_blob_read(0).unwrap();
+    controller.track_blob_read(1_000).unwrap();
+    assert_eq!(
+        controller.balance().unwrap(),
+        Amount::from_attos(1_000 - 14)
+    );
+    controller.track_blobs_read(&[1, 2, 3]).unwrap();
+    assert_eq!(
+        controller.balance().unwrap(),
+        Amount::from_attos(1_000 - 35)
+    );
+
+    // Publishing charges the fixed price once, not scaled by the blob type multiplier.
+    controller
+        .track_blob_published(&Blob::new_data(Vec::new()))
+        .unwrap();
+    let description = BlobContent::new(BlobType::ApplicationDescription, vec![0; 10]);
+    controller
+        .track_blob_published(&Blob::new(description))
+        .unwrap();
+    assert_eq!(
+        controller.balance().unwrap(),
+        Amount::from_attos(1_000 - 135)
+    );
+
+    // Committee blobs remain free.
+    controller
+        .track_blob_published(&Blob::new_committee(vec![0; 10]))
+        .unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(865));
+}
+
+#[test]
+fn test_settle_writes() {
+    let policy = ResourceControlPolicy {
+        write_operation: Amount::from_attos(10),
+        byte_written: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    let mut estimate = Batch::new();
+    for key in 0..5 {
+        estimate.put_key_value_bytes(vec![key; 5], vec![0; 15]);
+    }
+    controller.estimate_writes(&estimate).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(850));
+    // Writes outside of the batch are not part of the estimate.
+    controller.track_bytes_written(7).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(843));
+
+    // Only two writes of 30 bytes in total are committed: the rest is refunded.
+    let mut batch = Batch::new();
+    batch.put_key_value_bytes(vec![0; 5], vec![0; 15]);
+    batch.delete_key(vec![1; 10]);
+    controller.settle_writes(&batch).unwrap();
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        write_operations: Some(2),
+        bytes_written: Some(37),
+        ..ResourceTrackerSpec::default()
+    });
+    assert_eq!(controller.tracker.write_estimate, None);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(943));
+
+    // Writes that were not estimated are charged.
+    batch.put_key_value_bytes(vec![2; 5], vec![0; 5]);
+    controller.settle_writes(&batch).unwrap();
+    assert_eq!(controller.tracker.write_operations, 5);
+    assert_eq!(controller.tracker.bytes_written, 77);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(873));
+}
+
+#[test]
+fn test_account_description() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::ONE);
+    assert_eq!(controller.account_description(), "amount");
+
+    let mut grant = Amount::ONE;
+    let sources = Sources {
+        sources: vec![&mut grant],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    let controller = ResourceController::new(
+        controller.policy().clone(),
+        &mut controller.tracker,
+        sources,
+    );
+    assert_eq!(controller.account_description(), "sources");
+}
+
+#[test]
+fn test_message_hops() {
+    let policy = ResourceControlPolicy {
+        message: Amount::from_attos(10),
+        message_byte: Amount::from_attos(1),
+        message_hop: Amount::from_attos(100),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    let chain_id = dummy_chain_description(0).id();
+    let other_chain_id = dummy_chain_description(1).id();
+    let message = Message::User {
+        application_id: ApplicationId::default(),
+        bytes: vec![0; 5],
+    };
+
+    // A message to the sending chain itself makes no hops.
+    controller
+        .track_message(chain_id, &OutgoingMessage::new(chain_id, message.clone()))
+        .unwrap();
+    assert_eq!(controller.tracker.outgoing_message_hops, 0);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(985));
+
+    // A message to another chain is charged for its hop.
+    controller
+        .track_message(chain_id, &OutgoingMessage::new(other_chain_id, message))
+        .unwrap();
+    assert_eq!(controller.tracker.outgoing_message_hops, 1);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(870));
+}
+
+#[test]
+fn test_strict_empty_account() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.set_strict_empty_account(true);
+    let tracker = controller.tracker.clone();
+
+    // The first charge fails before anything is recorded.
+    assert_matches!(
+        controller.track_http_request(),
+        Err(ExecutionError::FeesExceedFunding { balance, .. }) if balance == Amount::ZERO
+    );
+    assert_eq!(controller.tracker, tracker);
+    // Even free resources are rejected.
+    assert_matches!(
+        controller.track_bytes_read(10),
+        Err(ExecutionError::FeesExceedFunding { .. })
+    );
+    assert_eq!(controller.tracker, tracker);
+
+    // Without strict mode, the counter is updated before the charge fails.
+    controller.set_strict_empty_account(false);
+    assert_matches!(
+        controller.track_http_request(),
+        Err(ExecutionError::FeesExceedFunding { .. })
+    );
+    assert_eq!(controller.tracker.http_requests, 1);
+}
+
+#[test]
+fn test_merge_balance_records_deltas() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(100));
+
+    // The other side gained 30 and then lost 12, relative to the initial balance.
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(80))
+        .unwrap();
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(38))
+        .unwrap();
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(50))
+        .unwrap();
+
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        merged_balance_credits: Some(Amount::from_attos(30)),
+        merged_balance_debits: Some(Amount::from_attos(12)),
+        ..ResourceTrackerSpec::default()
+    });
+    assert_eq!(controller.account, Amount::from_attos(118));
+}
+
+#[test]
+fn test_merge_balance_is_not_a_fee_or_refund() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(100));
+    controller.check_refunds(Amount::ZERO);
+
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(80))
+        .unwrap();
+    controller
+        .merge_balance(Amount::from_attos(50), Amount::from_attos(40))
+        .unwrap();
+
+    assert_eq!(controller.account, Amount::from_attos(120));
+    assert_eq!(controller.tracker.credited, Amount::ZERO);
+    assert_eq!(controller.tracker.debited, Amount::ZERO);
+    controller.finalize().unwrap();
+}
+
+#[test]
+fn test_resource_kind_all() {
+    // Adding a variant breaks this match until it is also added to `ResourceKind::ALL`.
+    let position = |kind: ResourceKind| match kind {
+        ResourceKind::WasmFuel => 0,
+        ResourceKind::EvmFuel => 1,
+        ResourceKind::ReadOperations => 2,
+        ResourceKind::WriteOperations => 3,
+        ResourceKind::BytesRead => 4,
+        ResourceKind::BytesWritten => 5,
+        ResourceKind::BlobBytesRead => 6,
+        ResourceKind::BlobBytesPublished => 7,
+        ResourceKind::Operations => 8,
+        ResourceKind::OperationBytes => 9,
+        ResourceKind::Messages => 10,
+        ResourceKind::MessageBytes => 11,
+        ResourceKind::MessageHops => 12,
+        ResourceKind::HttpRequests => 13,
+        ResourceKind::HttpRequestBytes => 14,
+        ResourceKind::HttpResponseBytes => 15,
+        ResourceKind::RuntimeBytes => 16,
+        ResourceKind::OracleQueries => 17,
+        ResourceKind::OracleTime => 18,
+        ResourceKind::BlockSize => 19,
+        ResourceKind::DeserializedBytes => 20,
+        ResourceKind::Custom => 21,
+    };
+    assert_eq!(ResourceKind::ALL.len(), 22);
+    for (index, kind) in ResourceKind::ALL.iter().enumerate() {
+        assert_eq!(position(*kind), index);
+    }
+
+    let names = ResourceKind::ALL
+        .iter()
+        .map(ToString::to_string)
+        .collect::<BTreeSet<_>>();
+    assert_eq!(names.len(), ResourceKind::ALL.len());
+    assert_eq!(
+        ResourceKind::HttpResponseBytes.to_string(),
+        "http_response_bytes"
+    );
+}
+
+#[test]
+fn test_block_size_grace() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.track_block_size_with_grace(90, 20).unwrap();
+    assert!(!controller.tracker.block_size_grace_used);
+
+    // The closing message may exceed the limit by up to the grace.
+    controller.track_block_size_with_grace(25, 20).unwrap();
+    assert!(controller.tracker.block_size_grace_used);
+    assert_eq!(controller.tracker.block_size, 115);
+
+    // The grace can only be used once.
+    assert_matches!(
+        controller.track_block_size_with_grace(1, 20),
+        Err(ExecutionError::BlockTooLarge)
+    );
+}
+
+#[test]
+fn test_block_size_grace_is_bounded() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    assert_matches!(
+        controller.track_block_size_with_grace(121, 20),
+        Err(ExecutionError::BlockTooLarge)
+    );
+    assert!(!controller.tracker.block_size_grace_used);
+}
+
+#[test]
+fn test_charges_by_kind() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1),
+        byte_read: Amount::from_attos(2),
+        http_request: Amount::from_attos(10),
+        operation: Amount::from_attos(3),
+        minimum_operation_fee: Amount::from_attos(5),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_http_request().unwrap();
+    controller.track_bytes_read(4).unwrap();
+    controller.track_http_request().unwrap();
+    controller.track_bytes_read(1).unwrap();
+    controller
+        .track_operation(&Operation::system(SystemOperation::CloseChain))
+        .unwrap();
+    controller
+        .reserve_fuel(VmRuntime::Wasm)
+        .unwrap()
+        .consume(100)
+        .unwrap();
+
+    assert_eq!(
+        controller.tracker.charges_by_kind,
+        BTreeMap::from([
+            (ResourceKind::WasmFuel, Amount::from_attos(100)),
+            (ResourceKind::BytesRead, Amount::from_attos(10)),
+            // The minimum fee surcharge counts towards the operation.
+            (ResourceKind::Operations, Amount::from_attos(5)),
+            (ResourceKind::HttpRequests, Amount::from_attos(20)),
+        ])
+    );
+    let total = controller
+        .tracker
+        .charges_by_kind
+        .values()
+        .fold(Amount::ZERO, |total, amount| total.saturating_add(*amount));
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(865));
+    assert_eq!(total, Amount::from_attos(135));
+}
+
+#[test]
+fn test_blob_deposits() {
+    let policy = ResourceControlPolicy {
+        blob_byte_deposit: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    let first = Blob::new_data(vec![0; 100]);
+    let second = Blob::new_data(vec![1; 50]);
+
+    controller.track_blob_published(&first).unwrap();
+    let first_deposit = controller.track_blob_deposit(&first).unwrap();
+    assert_eq!(first_deposit, Amount::from_attos(200));
+    controller.track_blob_deposit(&second).unwrap();
+    assert_eq!(
+        controller.tracker.blob_deposits_held,
+        Amount::from_attos(300)
+    );
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(700));
+
+    // Committee blobs are exempt.
+    let deposit = controller
+        .track_blob_deposit(&Blob::new_committee(vec![0; 100]))
+        .unwrap();
+    assert_eq!(deposit, Amount::ZERO);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(700));
+
+    // Removing a blob, e.g. in a later block, releases the deposit recorded when it was
+    // published, even if the price changed since.
+    let policy = ResourceControlPolicy {
+        blob_byte_deposit: Amount::from_attos(5),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut later = controller_with(policy, Amount::from_attos(700));
+    later.refund_blob_deposit(first_deposit).unwrap();
+    assert_eq!(
+        later.tracker.blob_deposits_released,
+        Amount::from_attos(200)
+    );
+    assert_eq!(later.tracker.credited, Amount::ZERO);
+    assert_eq!(later.balance().unwrap(), Amount::from_attos(900));
+}
+
+#[tokio::test]
+async fn test_release_blob_deposit() -> anyhow::Result<()> {
+    let owner = AccountOwner::from(CryptoHash::test_hash("owner"));
+    let exempt = AccountOwner::from(CryptoHash::test_hash("exempt owner"));
+    let policy = Arc::new(ResourceControlPolicy {
+        blob_byte_deposit: Amount::from_attos(2),
+        fee_exempt_owners: BTreeSet::from([exempt]),
+        ..ResourceControlPolicy::no_fees()
+    });
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        balances: BTreeMap::from([(owner, Amount::from_attos(1_000))]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+    let blob = Blob::new_data(vec![0; 100]);
+
+    let mut controller =
+        ResourceController::new(policy.clone(), ResourceTracker::default(), Some(owner));
+    let deposit = controller
+        .with_state(&mut view.system)
+        .await?
+        .track_blob_deposit(&blob)?;
+    view.system.record_blob_deposit(blob.id(), deposit).await?;
+    assert_eq!(
+        view.system.balances.get(&owner).await?,
+        Some(Amount::from_attos(800))
+    );
+
+    // Releasing the deposit credits it back and removes it from the chain state, so that
+    // it cannot be released twice.
+    let mut later =
+        ResourceController::new(policy.clone(), ResourceTracker::default(), Some(owner));
+    assert_eq!(
+        later
+            .release_blob_deposit(&mut view.system, &blob.id())
+            .await?,
+        Amount::from_attos(200)
+    );
+    assert_eq!(
+        later
+            .release_blob_deposit(&mut view.system, &blob.id())
+            .await?,
+        Amount::ZERO
+    );
+    assert_eq!(
+        later.tracker.blob_deposits_released,
+        Amount::from_attos(200)
+    );
+    assert_eq!(
+        view.system.balances.get(&owner).await?,
+        Some(Amount::from_attos(1_000))
+    );
+    assert_eq!(view.system.blob_deposits.get(&blob.id()).await?, None);
+
+    // Fee-exempt accounts lock no deposit, so there is nothing to release.
+    let mut controller = ResourceController::new(policy, ResourceTracker::default(), Some(exempt));
+    let deposit = controller
+        .with_state(&mut view.system)
+        .await?
+        .track_blob_deposit(&blob)?;
+    assert_eq!(deposit, Amount::ZERO);
+    assert_eq!(controller.tracker.blob_deposits_held, Amount::ZERO);
+    Ok(())
+}
+
+#[test]
+fn test_applications_executed() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::ZERO);
+    let first_app = ApplicationId::new(CryptoHash::test_hash("first"));
+    let second_app = ApplicationId::new(CryptoHash::test_hash("second"));
+    let operation_of = |application_id| Operation::User {
+        application_id,
+        bytes: vec![],
+    };
+
+    controller
+        .track_operation(&operation_of(first_app))
+        .unwrap();
+    controller
+        .track_operation(&operation_of(second_app))
+        .unwrap();
+    controller
+        .track_operation(&operation_of(first_app))
+        .unwrap();
+    controller
+        .track_operation(&Operation::system(SystemOperation::CloseChain))
+        .unwrap();
+    assert_eq!(controller.applications_executed(), 2);
+
+    // Consuming fuel for an application that already executed does not count it again.
+    controller.track_application(second_app);
+    assert_eq!(controller.applications_executed(), 2);
+}
+
+#[test]
+fn test_sources_balance_saturates() {
+    let (mut first, mut second, mut third) =
+        (Amount::MAX, Amount::ONE, Amount::from_attos(u128::MAX - 1));
+    let sources = Sources {
+        sources: vec![&mut first, &mut second, &mut third],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    assert_eq!(sources.balance().unwrap(), Amount::MAX);
+
+    let (mut first, mut second) = (Amount::from_attos(u128::MAX - 1), Amount::ONE);
+    let sources = Sources {
+        sources: vec![&mut first, &mut second],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    assert_eq!(sources.balance().unwrap(), Amount::MAX);
+
+    let (mut first, mut second) = (Amount::from_attos(u128::MAX - 2), Amount::from_attos(1));
+    let sources = Sources {
+        sources: vec![&mut first, &mut second],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    assert_eq!(
+        sources.balance().unwrap(),
+        Amount::from_attos(u128::MAX - 1)
+    );
+}
+
+#[test]
+fn test_balances() {
+    assert_eq!(
+        Amount::from_attos(7).balances(),
+        vec![Amount::from_attos(7)]
+    );
+
+    let (mut grant, mut chain) = (Amount::from_tokens(5), Amount::from_tokens(10));
+    let sources = Sources {
+        sources: vec![&mut grant, &mut chain],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    assert_eq!(
+        sources.balances(),
+        vec![Amount::from_tokens(5), Amount::from_tokens(10)]
+    );
+    assert_eq!(sources.balance().unwrap(), Amount::from_tokens(15));
+}
+
+#[test_case(OracleTimeoutPolicy::Abort; "abort")]
+#[test_case(OracleTimeoutPolicy::Truncate; "truncate")]
+fn test_oracle_timeout_policy(oracle_timeout_policy: OracleTimeoutPolicy) {
+    let policy = ResourceControlPolicy {
+        maximum_service_oracle_execution_ms: 1_000,
+        oracle_timeout_policy,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+
+    // Just below the limit, both policies behave the same.
+    controller
+        .track_service_oracle_execution(Duration::from_millis(999))
+        .unwrap();
+    assert!(!controller.tracker.oracle_time_truncated);
+
+    // Reaching the limit aborts or truncates.
+    let result = controller.track_service_oracle_execution(Duration::from_millis(500));
+    match oracle_timeout_policy {
+        OracleTimeoutPolicy::Abort => {
+            assert_matches!(
+                result,
+                Err(ExecutionError::MaximumServiceOracleExecutionTimeExceeded)
+            );
+            assert!(!controller.tracker.oracle_time_truncated);
+        }
+        OracleTimeoutPolicy::Truncate => {
+            result.unwrap();
+            assert!(controller.tracker.oracle_time_truncated);
+            assert_eq!(
+                controller.tracker.service_oracle_execution,
+                Duration::from_millis(1_000)
+            );
+            assert_eq!(
+                controller
+                    .remaining_service_oracle_execution_time()
+                    .unwrap(),
+                Duration::ZERO
+            );
+        }
+    }
+}
+
+/// Returns a tracker with the resource usage of a typical block.
+fn typical_tracker() -> ResourceTracker {
+    ResourceTracker {
+        block_size: 2_345,
+        wasm_fuel: 1_234_567,
+        read_operations: 12,
+        write_operations: 4,
+        bytes_runtime: 96,
+        bytes_read: 4_096,
+        bytes_written: 512,
+        blobs_read: 1,
+        blob_bytes_read: 30_000,
+        bytes_stored: -17,
+        operations: 2,
+        operation_bytes: 150,
+        messages: 3,
+        message_bytes: 200,
+        outgoing_message_hops: 2,
+        service_oracle_execution: Duration::from_millis(1_250),
+        grants: Amount::from_millis(5),
+        ..ResourceTracker::default()
+    }
+}
+
+#[test]
+fn test_compact_bytes_round_trip() {
+    for tracker in [
+        ResourceTracker::default(),
+        typical_tracker(),
+        ResourceTracker {
+            block_size: u64::MAX,
+            read_operations: u32::MAX,
+            bytes_stored: i32::MIN,
+            service_oracle_execution: Duration::MAX,
+            grants: Amount::MAX,
+            ..ResourceTracker::default()
+        },
+    ] {
+        let bytes = tracker.to_compact_bytes();
+        assert_eq!(
+            ResourceTracker::from_compact_bytes(&bytes).unwrap(),
+            tracker
+        );
+    }
+
+    // Bookkeeping that is not resource usage is not encoded.
+    let tracker = ResourceTracker {
+        debited: Amount::ONE,
+        call_depth: 1,
+        ..typical_tracker()
+    };
+    let decoded = ResourceTracker::from_compact_bytes(&tracker.to_compact_bytes()).unwrap();
+    assert_eq!(decoded, typical_tracker());
+
+    // Truncated or extended encodings are rejected.
+    let bytes = typical_tracker().to_compact_bytes();
+    assert_matches!(
+        ResourceTracker::from_compact_bytes(&bytes[..bytes.len() - 1]),
+        Err(ExecutionError::InvalidCompactResourceTracker)
+    );
+    assert_matches!(
+        ResourceTracker::from_compact_bytes(&[bytes.as_slice(), &[0]].concat()),
+        Err(ExecutionError::InvalidCompactResourceTracker)
+    );
+
+    // The encoding survives being embedded in a BCS value, e.g. a block header.
+    let embedded = bcs::to_bytes(&bytes).unwrap();
+    let bytes = bcs::from_bytes::<Vec<u8>>(&embedded).unwrap();
+    assert_eq!(
+        ResourceTracker::from_compact_bytes(&bytes).unwrap(),
+        typical_tracker()
+    );
+}
+
+#[test]
+fn test_compact_varints_match_bcs() {
+    // BCS encodes the length of a sequence as a ULEB128 varint, and accepts only the
+    // shortest encoding of each length.
+    for value in [0, 1, 127, 128, 255, 16_383, 16_384, 1 << 21] {
+        let bcs_bytes = bcs::to_bytes(&vec![(); value]).unwrap();
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, value as u64);
+        assert_eq!(bytes, bcs_bytes);
+        let mut reader = CompactReader(&bcs_bytes);
+        assert_eq!(reader.varint().unwrap(), value as u64);
+        assert!(reader.0.is_empty());
+    }
+    for overlong in [&[0x80, 0x00][..], &[0x81, 0x00], &[0xff, 0x80, 0x00]] {
+        assert!(bcs::from_bytes::<Vec<()>>(overlong).is_err());
+        assert_matches!(
+            CompactReader(overlong).varint(),
+            Err(ExecutionError::InvalidCompactResourceTracker)
+        );
+    }
+}
+
+#[test]
+fn test_compact_bytes_are_smaller_than_bcs() {
+    let tracker = typical_tracker();
+    // BCS encodes a struct as the concatenation of its fields.
+    let bcs_bytes = [
+        bcs::to_bytes(&(
+            tracker.block_size,
+            tracker.evm_fuel,
+            tracker.wasm_fuel,
+            tracker.read_operations,
+            tracker.write_operations,
+            tracker.bytes_runtime,
+            tracker.bytes_read,
+            tracker.bytes_written,
+            tracker.blobs_read,
+            tracker.blobs_published,
+            tracker.blob_bytes_read,
+            tracker.blob_bytes_published,
+        ))
+        .unwrap(),
+        bcs::to_bytes(&(
+            tracker.bytes_stored,
+            tracker.operations,
+            tracker.operation_bytes,
+            tracker.messages,
+            tracker.message_bytes,
+            tracker.outgoing_message_hops,
+            tracker.http_requests,
+            tracker.http_request_bytes,
+            tracker.http_response_bytes,
+            tracker.service_oracle_queries,
+            tracker.service_oracle_execution,
+            tracker.grants,
+        ))
+        .unwrap(),
+    ]
+    .concat();
+    let compact_bytes = tracker.to_compact_bytes();
+    assert!(
+        compact_bytes.len() < bcs_bytes.len() / 2,
+        "{} compact bytes vs. {} BCS bytes",
+        compact_bytes.len(),
+        bcs_bytes.len()
+    );
+}
+
+#[test]
+fn test_track_fuel_best_effort() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(10),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(500));
+
+    // The balance only affords half of the requested fuel.
+    assert_eq!(
+        controller
+            .track_fuel_best_effort(100, VmRuntime::Wasm)
+            .unwrap(),
+        50
+    );
+    assert_eq!(controller.tracker.wasm_fuel, 50);
+    assert_eq!(controller.balance().unwrap(), Amount::ZERO);
+    assert_eq!(
+        controller
+            .track_fuel_best_effort(100, VmRuntime::Wasm)
+            .unwrap(),
+        0
+    );
+
+    // The per-block limit is respected too.
+    let mut controller = controller_with(controller.policy().as_ref().clone(), Amount::ONE);
+    assert_eq!(
+        controller
+            .track_fuel_best_effort(2_000, VmRuntime::Wasm)
+            .unwrap(),
+        1_000
+    );
+    assert_eq!(
+        controller.balance().unwrap(),
+        Amount::ONE.try_sub(Amount::from_attos(10_000)).unwrap()
+    );
+}
+
+#[test]
+fn test_custom_resources() {
+    let mut controller =
+        controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(1_000));
+    let ai_tokens =
+        controller.register_custom_resource("AI tokens".to_string(), Amount::from_attos(2), 100);
+    let storage_slots =
+        controller.register_custom_resource("storage slots".to_string(), Amount::from_attos(50), 3);
+    assert_ne!(ai_tokens, storage_slots);
+
+    controller.track_custom(ai_tokens, 100).unwrap();
+    controller.track_custom(storage_slots, 2).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(700));
+    assert_eq!(controller.tracker.custom_resource_counts[&ai_tokens], 100);
+    assert_eq!(controller.tracker.custom_resource_counts[&storage_slots], 2);
+
+    // Each resource has its own limit.
+    assert_matches!(
+        controller.track_custom(ai_tokens, 1),
+        Err(ExecutionError::MaximumCustomResourceExceeded(name)) if name == "AI tokens"
+    );
+    controller.track_custom(storage_slots, 1).unwrap();
+    assert_matches!(
+        controller.track_custom(storage_slots, 1),
+        Err(ExecutionError::MaximumCustomResourceExceeded(name)) if name == "storage slots"
+    );
+
+    // Rejected units are not counted, and the charges are recorded like any other.
+    assert_eq!(controller.tracker.custom_resource_counts[&ai_tokens], 100);
+    assert_eq!(controller.tracker.custom_resource_counts[&storage_slots], 3);
+    assert_eq!(
+        controller.tracker.charges_by_kind[&ResourceKind::Custom],
+        Amount::from_attos(350)
+    );
+    assert_eq!(
+        controller.tracker.max_single_charge[&ResourceKind::Custom],
+        Amount::from_attos(200)
+    );
+
+    assert_matches!(
+        controller.track_custom(CustomResourceId(2), 1),
+        Err(ExecutionError::UnknownCustomResource(CustomResourceId(2)))
+    );
+
+    // Registrations survive a reset of the counters.
+    controller.reset_tracker();
+    assert!(controller.tracker.custom_resource_counts.is_empty());
+    controller.track_custom(storage_slots, 3).unwrap();
+}
+
+#[test]
+fn test_oracle_latency_percentile() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::ZERO);
+    assert_eq!(controller.tracker.oracle_latency_percentile(0.5), None);
+
+    for millis in [40, 10, 50, 30, 20] {
+        controller
+            .track_service_oracle_execution(Duration::from_millis(millis))
+            .unwrap();
+    }
+    let tracker = &controller.tracker;
+    assert_eq!(
+        tracker.oracle_latency_percentile(0.5),
+        Some(Duration::from_millis(30))
+    );
+    assert_eq!(
+        tracker.oracle_latency_percentile(0.99),
+        Some(Duration::from_millis(50))
+    );
+    assert_eq!(
+        tracker.oracle_latency_percentile(0.0),
+        Some(Duration::from_millis(10))
+    );
+    assert_eq!(tracker.oracle_latency_percentile(1.5), None);
+
+    // The number of samples is bounded.
+    for _ in 0..MAX_ORACLE_LATENCY_SAMPLES {
+        controller
+            .track_service_oracle_execution(Duration::ZERO)
+            .unwrap();
+    }
+    assert_eq!(
+        controller.tracker.oracle_latencies.len(),
+        MAX_ORACLE_LATENCY_SAMPLES
+    );
+}
+
+#[test]
+fn test_http_request_bodies() {
+    let policy = ResourceControlPolicy {
+        http_request: Amount::from_attos(100),
+        http_request_byte: Amount::from_attos(2),
+        maximum_http_request_bytes_per_block: 1_500,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(10_000));
+
+    // An empty body only pays for the request.
+    controller.track_http_request_with_body(0).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(9_900));
+
+    // A large body also pays for each byte.
+    controller.track_http_request_with_body(1_000).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(7_800));
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        http_requests: Some(2),
+        http_request_bytes: Some(1_000),
+        ..ResourceTrackerSpec::default()
+    });
+
+    assert_matches!(
+        controller.track_http_request_with_body(501),
+        Err(ExecutionError::ExcessiveHttpRequestBytes)
+    );
+}
+
+#[test]
+fn test_spending_headroom() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(10),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let headroom = |balance: Amount, used_fuel: u64| {
+        let mut controller = controller_with(policy.clone(), balance);
+        controller.tracker.wasm_fuel = used_fuel;
+        controller.spending_headroom(VmRuntime::Wasm)
+    };
+
+    // The balance is the constraint.
+    assert_eq!(
+        headroom(Amount::from_attos(5_005), 0),
+        SpendingHeadroom {
+            fuel_units: 500,
+            fee_amount: Amount::from_attos(5_005),
+        }
+    );
+    // The per-block limit is the constraint.
+    assert_eq!(
+        headroom(Amount::from_attos(100_000), 400),
+        SpendingHeadroom {
+            fuel_units: 600,
+            fee_amount: Amount::from_attos(100_000),
+        }
+    );
+    // Nothing is left.
+    assert_eq!(
+        headroom(Amount::ZERO, 0),
+        SpendingHeadroom {
+            fuel_units: 0,
+            fee_amount: Amount::ZERO,
+        }
+    );
+    assert_eq!(headroom(Amount::from_attos(100_000), 1_000).fuel_units, 0);
+
+    // EVM fuel is free and unlimited in this policy.
+    let controller = controller_with(policy, Amount::from_attos(7));
+    assert_eq!(
+        controller.spending_headroom(VmRuntime::Evm),
+        SpendingHeadroom {
+            fuel_units: u64::MAX,
+            fee_amount: Amount::from_attos(7),
+        }
+    );
+}
+
+#[test]
+fn test_failed_charge_attempts() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(10),
+        maximum_failed_charge_attempts: 3,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+
+    // Affordable charges are not counted.
+    assert_eq!(
+        controller
+            .track_fuel_best_effort(5, VmRuntime::Wasm)
+            .unwrap(),
+        5
+    );
+    assert_eq!(controller.tracker.failed_charge_attempts, 0);
+
+    for attempt in 1..=3 {
+        controller
+            .track_fuel_best_effort(100, VmRuntime::Wasm)
+            .unwrap();
+        assert_eq!(controller.tracker.failed_charge_attempts, attempt);
+    }
+    assert_matches!(
+        controller.track_fuel_best_effort(100, VmRuntime::Wasm),
+        Err(ExecutionError::TooManyFailedChargeAttempts)
+    );
+}
+
+#[test]
+fn test_track_write_value() {
+    let policy = ResourceControlPolicy {
+        write_operation: Amount::from_attos(100),
+        byte_written: Amount::from_attos(3),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let key = b"counter".as_slice();
+    let value = (42u64, "hello".to_string());
+    let mut controller = controller_with(policy.clone(), Amount::from_attos(10_000));
+    controller.track_write_value(key, &value).unwrap();
+
+    let mut expected = controller_with(policy, Amount::from_attos(10_000));
+    expected.track_write_operations(1).unwrap();
+    expected
+        .track_bytes_written((key.len() + 8 + 1 + 5) as u64)
+        .unwrap();
+
+    assert_eq!(controller.tracker, expected.tracker);
+    assert_eq!(controller.balance().unwrap(), expected.balance().unwrap());
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        write_operations: Some(1),
+        bytes_written: Some(21),
+        ..ResourceTrackerSpec::default()
+    });
+}
+
+#[test]
+fn test_charge_timestamps() {
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(1),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy.clone(), Amount::from_attos(100));
+    controller.track_operation(&user_operation(0)).unwrap();
+    assert!(controller.tracker.charge_times.is_empty());
+    let untimed = controller.tracker.clone();
+
+    // A clock that advances by one microsecond each time it is read.
+    let time = Arc::new(AtomicU64::new(1_000));
+    let clock = {
+        let time = time.clone();
+        move || Timestamp::from(time.fetch_add(1, Ordering::SeqCst))
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100)).with_clock(clock);
+    controller.track_operation(&user_operation(0)).unwrap();
+    assert_eq!(controller.tracker, untimed);
+    for _ in 0..2 {
+        controller.track_operation(&user_operation(0)).unwrap();
+    }
+    assert_eq!(
+        controller.tracker.charge_times.first,
+        Some(Timestamp::from(1_000))
+    );
+    assert_eq!(
+        controller.tracker.charge_times.last,
+        Some(Timestamp::from(1_002))
+    );
+
+    // Controllers derived from this one use the same clock.
+    let mut forked = controller.fork();
+    forked.track_operation(&user_operation(0)).unwrap();
+    assert_eq!(
+        forked.tracker.charge_times.first,
+        Some(Timestamp::from(1_003))
+    );
+    assert_eq!(time.load(Ordering::SeqCst), 1_004);
+}
+
+#[tokio::test]
+async fn test_fee_exempt_owners() -> anyhow::Result<()> {
+    let exempt = AccountOwner::from(CryptoHash::test_hash("exempt owner"));
+    let other = AccountOwner::from(CryptoHash::test_hash("other owner"));
+    let balance = Amount::from_attos(10);
+    let policy = Arc::new(ResourceControlPolicy {
+        operation: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 5,
+        fee_exempt_owners: BTreeSet::from([exempt]),
+        ..ResourceControlPolicy::no_fees()
+    });
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        balances: BTreeMap::from([(exempt, balance), (other, balance)]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+
+    for (owner, expected_balance) in [(exempt, balance), (other, Amount::from_attos(4))] {
+        let mut controller =
+            ResourceController::new(policy.clone(), ResourceTracker::default(), Some(owner));
+        let mut state = controller.with_state(&mut view.system).await?;
+        for _ in 0..3 {
+            state.track_operation(&user_operation(0))?;
+        }
+        assert_matches!(
+            state.track_fuel(6, VmRuntime::Wasm),
+            Err(ExecutionError::MaximumFuelExceeded(VmRuntime::Wasm))
+        );
+        assert_eq!(
+            view.system.balances.get(&owner).await?,
+            Some(expected_balance)
+        );
+        controller.tracker.assert_matches(&ResourceTrackerSpec {
+            operations: Some(3),
+            ..ResourceTrackerSpec::default()
+        });
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fee_exempt_owner_receives_no_refunds() -> anyhow::Result<()> {
+    let exempt = AccountOwner::from(CryptoHash::test_hash("exempt owner"));
+    let balance = Amount::from_attos(100);
+    let policy = Arc::new(ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 20,
+        fee_exempt_owners: BTreeSet::from([exempt]),
+        ..ResourceControlPolicy::no_fees()
+    });
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        balances: BTreeMap::from([(exempt, balance)]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+
+    let mut controller = ResourceController::new(policy, ResourceTracker::default(), Some(exempt));
+    let mut state = controller.with_state(&mut view.system).await?;
+    let reservation = state.reserve_fuel(VmRuntime::Wasm)?;
+    reservation.consume(5)?;
+    state.track_grant_for(0, Amount::from_attos(30))?;
+    state.refund_grant_for(0, Amount::from_attos(20))?;
+    assert_eq!(
+        state.on_message_failure(Amount::from_attos(10), Amount::ZERO)?,
+        Amount::from_attos(10)
+    );
+    assert_eq!(view.system.balances.get(&exempt).await?, Some(balance));
+    assert_eq!(controller.tracker.credited, Amount::ZERO);
+    assert_eq!(controller.tracker.wasm_fuel, 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fee_exempt_owner_skips_balance_checks() -> anyhow::Result<()> {
+    let exempt = AccountOwner::from(CryptoHash::test_hash("exempt owner"));
+    let policy = Arc::new(ResourceControlPolicy {
+        operation: Amount::from_attos(2),
+        wasm_fuel_unit: Amount::from_attos(2),
+        maximum_wasm_fuel_per_block: 20,
+        fee_exempt_owners: BTreeSet::from([exempt]),
+        ..ResourceControlPolicy::no_fees()
+    });
+    let mut view = SystemExecutionState {
+        description: Some(dummy_chain_description(0)),
+        balances: BTreeMap::from([(exempt, Amount::ZERO)]),
+        ..SystemExecutionState::default()
+    }
+    .into_view()
+    .await;
+
+    let mut controller = ResourceController::new(policy, ResourceTracker::default(), Some(exempt));
+    controller.set_strict_empty_account(true);
+    let mut state = controller.with_state(&mut view.system).await?;
+    assert!(state.can_afford(Amount::from_attos(100)));
+    state.check_operation_fits(&user_operation(0))?;
+    state.track_operation(&user_operation(0))?;
+    assert_eq!(state.remaining_fuel(VmRuntime::Wasm), 20);
+    state.track_fuel(5, VmRuntime::Wasm)?;
+    assert_eq!(state.track_fuel_best_effort(10, VmRuntime::Wasm)?, 10);
+    assert_eq!(view.system.balances.get(&exempt).await?, Some(Amount::ZERO));
+    assert_eq!(controller.tracker.wasm_fuel, 15);
+    assert_eq!(controller.tracker.failed_charge_attempts, 0);
+    Ok(())
+}
+
+#[test_case(StorageRefundTarget::OwnerAccount => (Amount::ZERO, Amount::from_attos(26)))]
+#[test_case(StorageRefundTarget::ChainAccount => (Amount::from_attos(6), Amount::from_attos(20)))]
+#[test_case(StorageRefundTarget::Burn => (Amount::ZERO, Amount::from_attos(20)))]
+fn test_storage_refund_target(storage_refund_target: StorageRefundTarget) -> (Amount, Amount) {
+    let policy = ResourceControlPolicy {
+        byte_stored: Amount::from_attos(2),
+        storage_refund_target,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let (mut chain, mut owner) = (Amount::from_attos(10), Amount::from_attos(20));
+    let sources = Sources {
+        sources: vec![&mut chain, &mut owner],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    };
+    let mut tracker = ResourceTracker::default();
+    let mut controller = ResourceController::new(Arc::new(policy), &mut tracker, sources);
+    controller.track_stored_bytes(5).unwrap();
+    controller.track_stored_bytes(-3).unwrap();
+    assert_eq!(controller.tracker.bytes_stored, 2);
+    assert_eq!(
+        controller.tracker.bytes_stored_charged,
+        Amount::from_attos(4)
+    );
+    (chain, owner)
+}
+
+#[test]
+fn test_storage_refund_is_capped_by_charges() {
+    let policy = ResourceControlPolicy {
+        byte_stored: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let (mut chain, mut owner) = (Amount::from_attos(10), Amount::from_attos(20));
+    let sources = Sources::new(vec![&mut chain, &mut owner]).unwrap();
+    let mut tracker = ResourceTracker::default();
+    let mut controller = ResourceController::new(Arc::new(policy), &mut tracker, sources);
+    controller.track_stored_bytes(2).unwrap();
+    // Freeing more bytes than were charged for only refunds what was paid.
+    controller.track_stored_bytes(-10).unwrap();
+    assert_eq!(controller.tracker.bytes_stored, -8);
+    assert_eq!(controller.tracker.bytes_stored_charged, Amount::ZERO);
+    assert_eq!(controller.tracker.credited, Amount::from_attos(4));
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(30));
+}
+
+#[test]
+fn test_storage_refund_to_chain_account_with_grant() {
+    let policy = ResourceControlPolicy {
+        byte_stored: Amount::from_attos(2),
+        storage_refund_target: StorageRefundTarget::ChainAccount,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let (mut grant, mut chain) = (Amount::from_attos(10), Amount::from_attos(20));
+    let sources = Sources::new(vec![&mut grant])
+        .unwrap()
+        .with_chain_account(&mut chain);
+    let mut tracker = ResourceTracker::default();
+    let mut controller = ResourceController::new(Arc::new(policy), &mut tracker, sources);
+    controller.track_stored_bytes(3).unwrap();
+    controller.track_stored_bytes(-3).unwrap();
+    assert_eq!(
+        (grant, chain),
+        (Amount::from_attos(4), Amount::from_attos(26))
+    );
+}
+
+#[test]
+fn test_burn() {
+    let (mut chain, mut owner) = (Amount::from_attos(10), Amount::from_attos(20));
+    let sources = Sources::new(vec![&mut chain, &mut owner]).unwrap();
+    let mut tracker = ResourceTracker::default();
+    let policy = Arc::new(ResourceControlPolicy::no_fees());
+    let mut controller = ResourceController::new(policy, &mut tracker, sources);
+    controller.burn(Amount::from_attos(5)).unwrap();
+    controller.burn(Amount::from_attos(2)).unwrap();
+    assert_eq!(controller.tracker.burned, Amount::from_attos(7));
+    assert_eq!(controller.tracker.credited, Amount::ZERO);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(30));
+    assert_eq!(
+        (chain, owner),
+        (Amount::from_attos(10), Amount::from_attos(20))
+    );
+}
+
+#[test]
+fn test_storage_rent() {
+    let policy = ResourceControlPolicy {
+        storage_rent: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // 100 bytes for 3 seconds, plus a partial second charged as a whole one.
+    controller
+        .track_storage_rent(100, Duration::from_millis(3_500))
+        .unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(200));
+    assert_eq!(controller.tracker.rent_charged, Amount::from_attos(800));
+    assert_eq!(
+        controller.receipt().fees["storage"],
+        Amount::from_attos(800)
+    );
+}
+
+#[test]
+fn test_storage_rent_without_elapsed_time() {
+    let policy = ResourceControlPolicy {
+        storage_rent: Amount::from_attos(2),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_storage_rent(100, Duration::ZERO).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(1_000));
+    assert_eq!(controller.tracker.rent_charged, Amount::ZERO);
+}
+
+#[test]
+fn test_operation_and_message_sizes() {
+    let mut controller = controller_with(ResourceControlPolicy::no_fees(), Amount::ZERO);
+    let user_op = user_operation(7);
+    let system_op = Operation::System(Box::new(SystemOperation::CloseChain));
+    assert_eq!(operation_size(&user_op), 7);
+    assert_eq!(operation_size(&system_op), 0);
+    controller.track_operation(&user_op).unwrap();
+    controller.track_operation(&system_op).unwrap();
+    assert_eq!(controller.tracker.operation_bytes, 7);
+
+    let chain_id = dummy_chain_description(0).id();
+    let user_message = Message::User {
+        application_id: ApplicationId::default(),
+        bytes: vec![0; 5],
+    };
+    let system_message = Message::System(SystemMessage::Credit {
+        target: AccountOwner::CHAIN,
+        amount: Amount::ONE,
+        source: AccountOwner::CHAIN,
+    });
+    assert_eq!(message_size(&user_message), 5);
+    assert_eq!(message_size(&system_message), 0);
+    for message in [user_message, system_message] {
+        controller
+            .track_message(chain_id, &OutgoingMessage::new(chain_id, message))
+            .unwrap();
+    }
+    assert_eq!(controller.tracker.message_bytes, 5);
+}
+
+#[test]
+fn test_track_block_size_labeled() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    controller.track_block_size(5).unwrap();
+    controller
+        .track_block_size_labeled(BlockComponent::Operations, 20)
+        .unwrap();
+    controller
+        .track_block_size_labeled(BlockComponent::Messages, 30)
+        .unwrap();
+    controller
+        .track_block_size_labeled(BlockComponent::Operations, 10)
+        .unwrap();
+    assert_matches!(
+        controller.track_block_size_labeled(BlockComponent::Blobs, 40),
+        Err(ExecutionError::BlockTooLarge)
+    );
+
+    assert_eq!(controller.tracker.block_size, 105);
+    assert_eq!(
+        controller.tracker.block_size_by_component,
+        BTreeMap::from([
+            (BlockComponent::Operations, 30),
+            (BlockComponent::Messages, 30),
+            (BlockComponent::Blobs, 40),
+        ])
+    );
+}
+
+#[test]
+fn test_track_block_size_compressed() {
+    let policy = ResourceControlPolicy {
+        maximum_block_size: 100,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    let halve = |bytes: &[u8]| bytes.len() / 2;
+
+    // 120 serialized bytes are too many for the block, but 60 compressed bytes fit.
+    let data = vec![7u8; 119];
+    assert_eq!(bcs::serialized_size(&data).unwrap(), 120);
+    controller
+        .track_block_size_compressed(&data, &halve)
+        .unwrap();
+    assert_eq!(controller.tracker.block_size, 60);
+    assert_eq!(controller.tracker.compressed_block_size, 60);
+    assert_eq!(controller.tracker.uncompressed_block_size, 120);
+
+    assert_matches!(
+        controller.track_block_size_compressed(&data, &halve),
+        Err(ExecutionError::BlockTooLarge)
+    );
+}
+
+#[test]
+fn test_call_depth() {
+    let policy = ResourceControlPolicy {
+        maximum_call_depth: 3,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::ZERO);
+    for _ in 0..3 {
+        controller.enter_call().unwrap();
+    }
+    assert_matches!(
+        controller.enter_call(),
+        Err(ExecutionError::CallDepthExceeded)
+    );
+    assert_eq!(controller.tracker.call_depth, 3);
+
+    // Returning from a call makes room for another one.
+    controller.exit_call().unwrap();
+    controller.enter_call().unwrap();
+    for _ in 0..3 {
+        controller.exit_call().unwrap();
+    }
+    assert_eq!(controller.tracker.call_depth, 0);
+    assert_eq!(controller.tracker.max_call_depth_reached, 3);
+
+    // Returning without a call is an error, and leaves the depth unchanged.
+    assert_matches!(
+        controller.exit_call(),
+        Err(ExecutionError::ArithmeticError(ArithmeticError::Underflow))
+    );
+    assert_eq!(controller.tracker.call_depth, 0);
+
+    // The call depth is limited in analytics mode too.
+    let policy = ResourceControlPolicy {
+        maximum_call_depth: 1,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut analytics = ResourceController::analytics_mode(Arc::new(policy), Amount::ZERO);
+    analytics.enter_call().unwrap();
+    assert_matches!(
+        analytics.enter_call(),
+        Err(ExecutionError::CallDepthExceeded)
+    );
+}
+
+#[test]
+fn test_event_log() {
+    let policy = ResourceControlPolicy {
+        read_operation: Amount::from_attos(3),
+        byte_read: Amount::from_attos(1),
+        base_transaction_fee: Amount::from_attos(5),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let controller = controller_with(policy.clone(), Amount::from_attos(1_000));
+    assert_eq!(controller.event_log(), None);
+
+    let mut controller = controller_with(policy, Amount::from_attos(1_000)).with_event_log();
+    controller.track_read_operations(2).unwrap();
+    controller.track_bytes_read(10).unwrap();
+    controller.track_transaction_base().unwrap();
+    controller.track_grant(Amount::from_attos(7)).unwrap();
+    controller
+        .charge("registration", Amount::from_attos(4))
+        .unwrap();
+    // Charges made by sub-controllers sharing the tracker are logged too.
+    controller
+        .with_budget(Amount::from_attos(10), |controller| {
+            controller.track_read_operations(1)
+        })
+        .unwrap();
+    assert_eq!(
+        controller.event_log().unwrap(),
+        [
+            ResourceEvent {
+                kind: ChargeKind::Resource(ResourceKind::ReadOperations),
+                quantity: 2,
+                fee: Amount::from_attos(6),
+            },
+            ResourceEvent {
+                kind: ChargeKind::Resource(ResourceKind::BytesRead),
+                quantity: 10,
+                fee: Amount::from_attos(10),
+            },
+            ResourceEvent {
+                kind: ChargeKind::Fee("base_transaction_fee"),
+                quantity: 1,
+                fee: Amount::from_attos(5),
+            },
+            ResourceEvent {
+                kind: ChargeKind::Fee("grant"),
+                quantity: 1,
+                fee: Amount::from_attos(7),
+            },
+            ResourceEvent {
+                kind: ChargeKind::Fee("registration"),
+                quantity: 1,
+                fee: Amount::from_attos(4),
+            },
+            ResourceEvent {
+                kind: ChargeKind::Resource(ResourceKind::ReadOperations),
+                quantity: 1,
+                fee: Amount::from_attos(3),
+            },
+        ]
+    );
+
+    // Failed charges are not logged, and a reset keeps the log enabled.
+    controller.reset_tracker();
+    assert!(controller.track_grant(Amount::from_attos(10_000)).is_err());
+    assert_eq!(controller.event_log(), Some(&[][..]));
+}
+
+#[test]
+fn test_charge_all() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1),
+        byte_read: Amount::from_attos(2),
+        byte_written: Amount::from_attos(3),
+        maximum_bytes_written_per_block: 10,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+
+    // The third charge exceeds the limit on written bytes, so nothing is charged.
+    let result = controller.charge_all(&[
+        Charge::Fuel(100, VmRuntime::Wasm),
+        Charge::BytesRead(10),
+        Charge::BytesWritten(20),
+    ]);
+    assert_matches!(result, Err(ExecutionError::ExcessiveWrite));
+    assert_eq!(controller.tracker, ResourceTracker::default());
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(1_000));
+
+    controller
+        .charge_all(&[
+            Charge::Fuel(100, VmRuntime::Wasm),
+            Charge::BytesRead(10),
+            Charge::BytesWritten(5),
+        ])
+        .unwrap();
+    controller.tracker.assert_matches(&ResourceTrackerSpec {
+        wasm_fuel: Some(100),
+        bytes_read: Some(10),
+        bytes_written: Some(5),
+        ..ResourceTrackerSpec::default()
+    });
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(865));
+}
+
+#[test]
+fn test_net_grants() {
+    let mut controller =
+        controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(1_000));
+    controller
+        .track_grant_for(0, Amount::from_attos(100))
+        .unwrap();
+    assert_eq!(controller.net_grants(), Amount::from_attos(100));
+
+    controller
+        .refund_grant_for(0, Amount::from_attos(40))
+        .unwrap();
+    assert_eq!(controller.net_grants(), Amount::from_attos(60));
+    // The `grants` counter is already net of refunds.
+    assert_eq!(controller.tracker.grants, Amount::from_attos(60));
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(940));
+}
+
+#[test]
+fn test_check_deadline() {
+    /// A fixed point in time, to check deadlines against a clock that does not move.
+    fn fixed_instant() -> Instant {
+        static INSTANT: OnceLock<Instant> = OnceLock::new();
+        *INSTANT.get_or_init(Instant::now)
+    }
+
+    let controller = controller_with(ResourceControlPolicy::no_fees(), Amount::ZERO)
+        .with_monotonic_clock(fixed_instant);
+    assert_matches!(controller.check_deadline(), Ok(()));
+
+    let past = fixed_instant() - Duration::from_secs(1);
+    let controller = controller.with_deadline(past);
+    assert_matches!(
+        controller.check_deadline(),
+        Err(ExecutionError::BlockExecutionTimedOut)
+    );
+
+    let future = fixed_instant() + Duration::from_secs(1);
+    let controller = controller.with_deadline(future);
+    assert_matches!(controller.check_deadline(), Ok(()));
+}
+
+#[test]
+fn test_receipt() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(1),
+        byte_read: Amount::from_attos(2),
+        byte_written: Amount::from_attos(3),
+        operation: Amount::from_attos(10),
+        maximum_bytes_written_per_block: 20,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(1_000));
+    controller.track_operation(&user_operation(4)).unwrap();
+    controller.track_fuel(100, VmRuntime::Wasm).unwrap();
+    controller.track_bytes_read(10).unwrap();
+    controller.track_bytes_written(19).unwrap();
+
+    let receipt = controller.receipt();
+    let tracker = &controller.tracker;
+    assert_eq!(receipt.wasm_fuel, tracker.wasm_fuel);
+    assert_eq!(receipt.evm_fuel, 0);
+    assert_eq!(receipt.bytes_read, tracker.bytes_read);
+    assert_eq!(receipt.bytes_written, tracker.bytes_written);
+    assert_eq!(receipt.fees["fuel"], Amount::from_attos(100));
+    assert_eq!(receipt.fees["storage"], Amount::from_attos(77));
+    assert_eq!(receipt.fees["operations"], Amount::from_attos(10));
+    assert_eq!(receipt.total_fee, Amount::from_attos(187));
+    assert_eq!(receipt.total_fee, tracker.debited);
+    assert_eq!(receipt.limits_reached, Vec::<ResourceKind>::new());
+
+    controller.track_bytes_written(1).unwrap_err();
+    let receipt = controller.receipt();
+    assert_eq!(receipt.limits_reached, vec![ResourceKind::BytesWritten]);
+    let bytes = bcs::to_bytes(&receipt).unwrap();
+    assert_eq!(
+        bcs::from_bytes::<TransactionReceipt>(&bytes).unwrap(),
+        receipt
+    );
+}
+
+#[test]
+fn test_track_transaction_base() {
+    let policy = ResourceControlPolicy {
+        base_transaction_fee: Amount::from_attos(25),
+        operation: Amount::from_attos(10),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let mut controller = controller_with(policy, Amount::from_attos(100));
+    controller.track_transaction_base().unwrap();
+    assert!(controller.tracker.base_fee_charged);
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(75));
+
+    // The base fee is only charged once per transaction, and it is distinct from the
+    // operation fees.
+    controller.track_transaction_base().unwrap();
+    controller.track_operation(&user_operation(0)).unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(65));
+    assert_eq!(
+        controller.receipt().fees["base_transaction_fee"],
+        Amount::from_attos(25)
+    );
+
+    // The next transaction in the same block pays the base fee again.
+    controller.start_transaction();
+    assert!(!controller.tracker.base_fee_charged);
+    controller.track_transaction_base().unwrap();
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(40));
+    assert_eq!(
+        controller.receipt().fees["base_transaction_fee"],
+        Amount::from_attos(50)
+    );
+}
+
+#[test]
+fn test_spendable_balance() {
+    let mut controller =
+        controller_with(ResourceControlPolicy::no_fees(), Amount::from_attos(1_000));
+    assert_eq!(
+        controller.spendable_balance().unwrap(),
+        Amount::from_attos(1_000)
+    );
+
+    controller
+        .track_grant_for(0, Amount::from_attos(300))
+        .unwrap();
+    // The grant was already deducted, and is not subtracted a second time.
+    assert_eq!(controller.balance().unwrap(), Amount::from_attos(700));
+    assert_eq!(
+        controller.spendable_balance().unwrap(),
+        Amount::from_attos(700)
+    );
+
+    controller
+        .track_grant_for(1, Amount::from_attos(400))
+        .unwrap();
+    assert_eq!(
+        controller.spendable_balance().unwrap(),
+        Amount::from_attos(300)
+    );
+}
+
+#[test]
+fn test_log_record() {
+    let tracker = ResourceTracker {
+        wasm_fuel: 500,
+        bytes_read: 20,
+        bytes_stored: -7,
+        operations: 2,
+        service_oracle_execution: Duration::from_millis(1_234),
+        grants: Amount::from_attos(42),
+        ..ResourceTracker::default()
+    };
+    let record = tracker.to_log_record();
+    assert_eq!(record.service_oracle_execution_ms, 1_234);
+
+    let mut log = Vec::new();
+    record.write_json_line(&mut log).unwrap();
+    ResourceTracker::default()
+        .to_log_record()
+        .write_json_line(&mut log)
+        .unwrap();
+    let log = String::from_utf8(log).unwrap();
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with(r#"{"block_size":0,"wasm_fuel":500,"evm_fuel":0,"#));
+    assert_eq!(serde_json::from_str::<LogRecord>(lines[0]).unwrap(), record);
+    assert_eq!(
+        serde_json::from_str::<LogRecord>(lines[1]).unwrap(),
+        LogRecord::default()
+    );
+}
+
+#[test]
+fn test_paid_by_owner() {
+    let alice = AccountOwner::from(CryptoHash::test_hash("alice"));
+    let bob = AccountOwner::from(CryptoHash::test_hash("bob"));
+    let policy = ResourceControlPolicy {
+        operation: Amount::from_attos(25),
+        ..ResourceControlPolicy::no_fees()
+    };
+    let (mut chain, mut alice_balance, mut bob_balance) = (
+        Amount::from_attos(10),
+        Amount::from_attos(20),
+        Amount::from_attos(30),
+    );
+    let sources = Sources {
+        sources: vec![&mut chain, &mut alice_balance, &mut bob_balance],
+        refund_target: RefundTarget::default(),
+        owners: Vec::new(),
+        chain_account: None,
+    }
+    .with_owners(vec![None, Some(alice), Some(bob)]);
+    let mut tracker = ResourceTracker::default();
+    let mut controller = ResourceController::new(Arc::new(policy), &mut tracker, sources);
+
+    // The chain pays 10, then Alice 15.
+    controller.track_operation(&user_operation(0)).unwrap();
+    // Alice pays her last 5, then Bob 20.
+    controller.track_operation(&user_operation(0)).unwrap();
+
+    assert_eq!(
+        tracker.paid_by_owner,
+        BTreeMap::from([
+            (alice, Amount::from_attos(20)),
+            (bob, Amount::from_attos(20)),
+        ])
+    );
+    assert_eq!(
+        (chain, alice_balance, bob_balance),
+        (Amount::ZERO, Amount::ZERO, Amount::from_attos(10))
+    );
+}
+
+#[test]
+fn test_remaining_fuel_detailed() {
+    let policy = ResourceControlPolicy {
+        wasm_fuel_unit: Amount::from_attos(10),
+        maximum_wasm_fuel_per_block: 1_000,
+        ..ResourceControlPolicy::no_fees()
+    };
+    let remaining = |balance: Amount, used_fuel: u64| {
+        let mut controller = controller_with(policy.clone(), balance);
+        controller.tracker.wasm_fuel = used_fuel;
+        controller.remaining_fuel_detailed(VmRuntime::Wasm)
+    };
+
+    // The balance is binding.
+    assert_eq!(
+        remaining(Amount::from_attos(3_000), 0),
+        RemainingFuel {
+            by_balance: 300,
+            by_cap: 1_000,
+            effective: 300,
+        }
+    );
+    // The per-block limit is binding, because of the fuel already spent.
+    assert_eq!(
+        remaining(Amount::from_attos(100_000), 900),
+        RemainingFuel {
+            by_balance: 10_000,
+            by_cap: 100,
+            effective: 100,
+        }
+    );
+    // Both bounds are equal.
+    assert_eq!(
+        remaining(Amount::from_attos(5_000), 500),
+        RemainingFuel {
+            by_balance: 500,
+            by_cap: 500,
+            effective: 500,
+        }
+    );
+}
@@ -146,6 +146,60 @@ async fn test_write_batch() {
     );
 }
 
+/// Test writing a batch where a key is overwritten.
+///
+/// Ensure that the batch is written unchanged, but that only the committed writes are paid for.
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_write_batch_with_overwritten_key() {
+    let (runtime, mut execution_state_receiver) = create_contract_runtime();
+    let mut runtime = SyncRuntimeHandle::from(runtime);
+    let mut batch = Batch::new();
+
+    let key = vec![1, 2, 3, 4, 5];
+    let first_data = vec![6, 7, 8, 9];
+    let second_data = vec![10, 11];
+
+    let expected_bytes_count = key.len() + second_data.len();
+
+    batch.put_key_value_bytes(key.clone(), first_data);
+    batch.put_key_value_bytes(key, second_data);
+
+    let expected_batch = batch.clone();
+
+    tokio::spawn(async move {
+        let request = execution_state_receiver
+            .next()
+            .await
+            .expect("Missing expected request to write a batch");
+
+        let ExecutionRequest::WriteBatch {
+            batch, callback, ..
+        } = request
+        else {
+            panic!("Expected a `ExecutionRequest::WriteBatch` but got {request:?} instead");
+        };
+
+        assert_eq!(batch, expected_batch);
+
+        callback
+            .send(())
+            .expect("Failed to notify that writing the batch finished");
+    });
+
+    runtime
+        .write_batch(batch)
+        .expect("Failed to write test batch");
+
+    assert_eq!(
+        runtime.inner().resource_controller.tracker.write_operations,
+        1
+    );
+    assert_eq!(
+        runtime.inner().resource_controller.tracker.bytes_written,
+        expected_bytes_count as u64
+    );
+}
+
 /// Creates a [`SyncRuntimeInternal`] instance for contracts, and returns it and the receiver
 /// endpoint for the requests the runtime sends to the [`ExecutionStateView`] actor.
 fn create_contract_runtime() -> (
@@ -5,7 +5,11 @@
 
 #![allow(clippy::items_after_test_module)]
 
-use std::{collections::BTreeSet, sync::Arc, vec};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    vec,
+};
 
 use linera_base::{
     crypto::AccountPublicKey,
@@ -19,8 +23,9 @@ use linera_execution::{
         blob_oracle_responses, dummy_chain_description, ExpectedCall, RegisterMockApplication,
         SystemExecutionState,
     },
-    ContractRuntime, ExecutionError, Message, MessageContext, ResourceControlPolicy,
-    ResourceController, ResourceTracker, TransactionTracker,
+    ContractRuntime, ExecutionError, Message, MessageContext, OracleTimeoutPolicy,
+    ResourceControlPolicy, ResourceController, ResourceTracker, StorageRefundTarget,
+    TransactionTracker,
 };
 use test_case::test_case;
 
@@ -215,32 +220,62 @@ async fn test_fee_consumption(
         write_operation: Amount::from_tokens(5),
         byte_runtime: Amount::from_millis(1),
         byte_read: Amount::from_tokens(7),
+        read_charge_granularity: 0,
         byte_written: Amount::from_tokens(11),
+        byte_deserialized: Amount::ZERO,
         byte_stored: Amount::from_tokens(13),
+        storage_rent: Amount::ZERO,
+        storage_refund_target: StorageRefundTarget::OwnerAccount,
         operation: Amount::from_tokens(17),
+        operation_price_overrides: BTreeMap::new(),
+        base_transaction_fee: Amount::ZERO,
+        fee_increment: Amount::ZERO,
         operation_byte: Amount::from_tokens(19),
+        price_operations_by_serialized_size: false,
+        minimum_operation_fee: Amount::ZERO,
+        warmup_operations: 0,
         message: Amount::from_tokens(23),
         message_byte: Amount::from_tokens(29),
+        message_hop: Amount::ZERO,
+        grant_denomination: None,
+        grant_exchange_rate: Amount::ONE,
         service_as_oracle_query: Amount::from_millis(31),
         http_request: Amount::from_tokens(37),
+        http_response_byte: Amount::ZERO,
+        http_request_byte: Amount::ZERO,
         maximum_wasm_fuel_per_block: 4_868_145_137,
         maximum_evm_fuel_per_block: 4_868_145_137,
         maximum_block_size: 41,
         maximum_service_oracle_execution_ms: 43,
+        oracle_timeout_policy: OracleTimeoutPolicy::Abort,
         maximum_blob_size: 47,
         maximum_published_blobs: 53,
         maximum_bytecode_size: 59,
         maximum_block_proposal_size: 61,
         maximum_bytes_read_per_block: 67,
         maximum_bytes_written_per_block: 71,
+        maximum_bytes_deserialized_per_block: u64::MAX,
+        maximum_blob_bytes_read_per_block: u64::MAX,
+        maximum_blob_bytes_published_per_block: u64::MAX,
+        maximum_bytes_runtime_per_block: u64::MAX,
         maximum_oracle_response_bytes: 73,
         maximum_http_response_bytes: 79,
+        maximum_http_request_bytes_per_block: u64::MAX,
+        maximum_failed_charge_attempts: u64::MAX,
+        maximum_call_depth: u64::MAX,
         http_request_timeout_ms: 83,
         blob_read: Amount::from_tokens(89),
+        blob_read_fixed: Amount::ZERO,
         blob_published: Amount::from_tokens(97),
+        blob_published_fixed: Amount::ZERO,
         blob_byte_read: Amount::from_tokens(101),
         blob_byte_published: Amount::from_tokens(103),
+        blob_byte_deposit: Amount::ZERO,
+        blob_bytecode_multiplier: 1,
+        blob_description_multiplier: 1,
         http_request_allow_list: BTreeSet::new(),
+        fee_exempt_owners: BTreeSet::new(),
+        uncharged_resources: BTreeSet::new(),
     };
 
     let consumed_fees = spends
@@ -17,7 +17,8 @@ use linera_chain::{
 use linera_core::{data_types::CrossChainRequest, node::NodeError};
 use linera_execution::{
     system::{AdminOperation, Recipient, SystemMessage, SystemOperation},
-    Message, MessageKind, Operation,
+    Message, MessageKind, Operation, OperationDiscriminant, OracleTimeoutPolicy, ResourceKind,
+    StorageRefundTarget,
 };
 use linera_rpc::RpcMessage;
 use serde_reflection::{Registry, Result, Samples, Tracer, TracerConfig};
@@ -65,6 +66,10 @@ fn get_registry() -> Result<Registry> {
     tracer.trace_type::<Message>(&samples)?;
     tracer.trace_type::<OriginalProposal>(&samples)?;
     tracer.trace_type::<VmRuntime>(&samples)?;
+    tracer.trace_type::<OracleTimeoutPolicy>(&samples)?;
+    tracer.trace_type::<StorageRefundTarget>(&samples)?;
+    tracer.trace_type::<ResourceKind>(&samples)?;
+    tracer.trace_type::<OperationDiscriminant>(&samples)?;
     tracer.trace_type::<MessageAction>(&samples)?;
     tracer.trace_type::<MessageKind>(&samples)?;
     tracer.trace_type::<CertificateKind>(&samples)?;
@@ -360,18 +360,34 @@ pub enum ClientCommand {
         #[arg(long)]
         byte_read: Option<Amount>,
 
+        /// Set the number of bytes read from storage that are charged together.
+        #[arg(long)]
+        read_charge_granularity: Option<u64>,
+
         /// Set the price per byte written.
         #[arg(long)]
         byte_written: Option<Amount>,
 
+        /// Set the price per byte deserialized.
+        #[arg(long)]
+        byte_deserialized: Option<Amount>,
+
         /// Set the base price to read a blob.
         #[arg(long)]
         blob_read: Option<Amount>,
 
+        /// Set the fixed price charged for each blob read.
+        #[arg(long)]
+        blob_read_fixed: Option<Amount>,
+
         /// Set the base price to publish a blob.
         #[arg(long)]
         blob_published: Option<Amount>,
 
+        /// Set the fixed price charged for each blob published.
+        #[arg(long)]
+        blob_published_fixed: Option<Amount>,
+
         /// Set the price to read a blob, per byte.
         #[arg(long)]
         blob_byte_read: Option<Amount>,
@@ -380,18 +396,54 @@ pub enum ClientCommand {
         #[arg(long)]
         blob_byte_published: Option<Amount>,
 
+        /// Set the refundable deposit locked for each byte of a published blob.
+        #[arg(long)]
+        blob_byte_deposit: Option<Amount>,
+
+        /// Set the multiplier applied to the price of publishing a bytecode blob.
+        #[arg(long)]
+        blob_bytecode_multiplier: Option<u64>,
+
+        /// Set the multiplier applied to the price of publishing an application or chain description blob.
+        #[arg(long)]
+        blob_description_multiplier: Option<u64>,
+
         /// Set the price per byte stored.
         #[arg(long)]
         byte_stored: Option<Amount>,
 
+        /// Set the rent for keeping one byte stored for one second.
+        #[arg(long)]
+        storage_rent: Option<Amount>,
+
         /// Set the base price of sending an operation from a block..
         #[arg(long)]
         operation: Option<Amount>,
 
+        /// Set the flat fee charged once per transaction.
+        #[arg(long)]
+        base_transaction_fee: Option<Amount>,
+
+        /// Set the increment that every fee is rounded up to.
+        #[arg(long)]
+        fee_increment: Option<Amount>,
+
         /// Set the additional price for each byte in the argument of a user operation.
         #[arg(long)]
         operation_byte: Option<Amount>,
 
+        /// Set whether the bytes of an operation are counted from its whole BCS serialization.
+        #[arg(long)]
+        price_operations_by_serialized_size: Option<bool>,
+
+        /// Set the minimum total fee for an operation, including the price of its bytes.
+        #[arg(long)]
+        minimum_operation_fee: Option<Amount>,
+
+        /// Set the number of operations on a new chain that are not charged any fees.
+        #[arg(long)]
+        warmup_operations: Option<u32>,
+
         /// Set the base price of sending a message from a block..
         #[arg(long)]
         message: Option<Amount>,
@@ -400,6 +452,10 @@ pub enum ClientCommand {
         #[arg(long)]
         message_byte: Option<Amount>,
 
+        /// Set the additional price for each chain an outgoing message travels to.
+        #[arg(long)]
+        message_hop: Option<Amount>,
+
         /// Set the price per query to a service as an oracle.
         #[arg(long)]
         service_as_oracle_query: Option<Amount>,
@@ -408,6 +464,14 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request: Option<Amount>,
 
+        /// Set the additional price for each byte in the body of an HTTP response.
+        #[arg(long)]
+        http_response_byte: Option<Amount>,
+
+        /// Set the price per byte in the body of an HTTP request.
+        #[arg(long)]
+        http_request_byte: Option<Amount>,
+
         /// Set the maximum amount of Wasm fuel per block.
         #[arg(long)]
         maximum_wasm_fuel_per_block: Option<u64>,
@@ -449,6 +513,22 @@ pub enum ClientCommand {
         #[arg(long)]
         maximum_bytes_written_per_block: Option<u64>,
 
+        /// Set the maximum deserialized data per block.
+        #[arg(long)]
+        maximum_bytes_deserialized_per_block: Option<u64>,
+
+        /// Set the maximum blob data read per block.
+        #[arg(long)]
+        maximum_blob_bytes_read_per_block: Option<u64>,
+
+        /// Set the maximum blob data published per block.
+        #[arg(long)]
+        maximum_blob_bytes_published_per_block: Option<u64>,
+
+        /// Set the maximum number of bytes read from the runtime per block.
+        #[arg(long)]
+        maximum_bytes_runtime_per_block: Option<u64>,
+
         /// Set the maximum size of oracle responses.
         #[arg(long)]
         maximum_oracle_response_bytes: Option<u64>,
@@ -457,6 +537,18 @@ pub enum ClientCommand {
         #[arg(long)]
         maximum_http_response_bytes: Option<u64>,
 
+        /// Set the maximum number of bytes in the bodies of HTTP requests per block.
+        #[arg(long)]
+        maximum_http_request_bytes_per_block: Option<u64>,
+
+        /// Set the maximum number of charges per block that may be rejected for a lack of funds.
+        #[arg(long)]
+        maximum_failed_charge_attempts: Option<u64>,
+
+        /// Set the maximum depth of nested application calls.
+        #[arg(long)]
+        maximum_call_depth: Option<u64>,
+
         /// Set the maximum amount of time allowed to wait for an HTTP response.
         #[arg(long)]
         http_request_timeout_ms: Option<u64>,
@@ -554,21 +646,41 @@ pub enum ClientCommand {
         #[arg(long)]
         byte_read_price: Option<Amount>,
 
+        /// Set the number of bytes read from storage that are charged together.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        read_charge_granularity: Option<u64>,
+
         /// Set the price per byte written.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         byte_written_price: Option<Amount>,
 
+        /// Set the price per byte deserialized.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        byte_deserialized_price: Option<Amount>,
+
         /// Set the base price to read a blob.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         blob_read_price: Option<Amount>,
 
+        /// Set the fixed price charged for each blob read.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        blob_read_fixed_price: Option<Amount>,
+
         /// Set the base price to publish a blob.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         blob_published_price: Option<Amount>,
 
+        /// Set the fixed price charged for each blob published.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        blob_published_fixed_price: Option<Amount>,
+
         /// Set the price to read a blob, per byte.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
@@ -579,21 +691,66 @@ pub enum ClientCommand {
         #[arg(long)]
         blob_byte_published_price: Option<Amount>,
 
+        /// Set the refundable deposit locked for each byte of a published blob.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        blob_byte_deposit_price: Option<Amount>,
+
+        /// Set the multiplier applied to the price of publishing a bytecode blob.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        blob_bytecode_multiplier: Option<u64>,
+
+        /// Set the multiplier applied to the price of publishing an application or chain description blob.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        blob_description_multiplier: Option<u64>,
+
         /// Set the price per byte stored.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         byte_stored_price: Option<Amount>,
 
+        /// Set the rent for keeping one byte stored for one second.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        storage_rent_price: Option<Amount>,
+
         /// Set the base price of sending an operation from a block..
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         operation_price: Option<Amount>,
 
+        /// Set the flat fee charged once per transaction.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        base_transaction_fee_price: Option<Amount>,
+
+        /// Set the increment that every fee is rounded up to.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        fee_increment_price: Option<Amount>,
+
         /// Set the additional price for each byte in the argument of a user operation.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
         operation_byte_price: Option<Amount>,
 
+        /// Set whether the bytes of an operation are counted from its whole BCS serialization.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        price_operations_by_serialized_size: Option<bool>,
+
+        /// Set the minimum total fee for an operation, including the price of its bytes.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        minimum_operation_fee_price: Option<Amount>,
+
+        /// Set the number of operations on a new chain that are not charged any fees.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        warmup_operations: Option<u32>,
+
         /// Set the base price of sending a message from a block..
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
@@ -604,6 +761,11 @@ pub enum ClientCommand {
         #[arg(long)]
         message_byte_price: Option<Amount>,
 
+        /// Set the additional price for each chain an outgoing message travels to.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        message_hop_price: Option<Amount>,
+
         /// Set the price per query to a service as an oracle.
         #[arg(long)]
         service_as_oracle_query_price: Option<Amount>,
@@ -612,6 +774,16 @@ pub enum ClientCommand {
         #[arg(long)]
         http_request_price: Option<Amount>,
 
+        /// Set the additional price for each byte in the body of an HTTP response.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        http_response_byte_price: Option<Amount>,
+
+        /// Set the price per byte in the body of an HTTP request.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        http_request_byte_price: Option<Amount>,
+
         /// Set the maximum amount of Wasm fuel per block.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
@@ -662,6 +834,26 @@ pub enum ClientCommand {
         #[arg(long)]
         maximum_bytes_written_per_block: Option<u64>,
 
+        /// Set the maximum deserialized data per block.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_bytes_deserialized_per_block: Option<u64>,
+
+        /// Set the maximum blob data read per block.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_blob_bytes_read_per_block: Option<u64>,
+
+        /// Set the maximum blob data published per block.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_blob_bytes_published_per_block: Option<u64>,
+
+        /// Set the maximum number of bytes read from the runtime per block.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_bytes_runtime_per_block: Option<u64>,
+
         /// Set the maximum size of oracle responses.
         /// (This will overwrite value from `--policy-config`)
         #[arg(long)]
@@ -671,6 +863,21 @@ pub enum ClientCommand {
         #[arg(long)]
         maximum_http_response_bytes: Option<u64>,
 
+        /// Set the maximum number of bytes in the bodies of HTTP requests per block.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_http_request_bytes_per_block: Option<u64>,
+
+        /// Set the maximum number of charges per block that may be rejected for a lack of funds.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_failed_charge_attempts: Option<u64>,
+
+        /// Set the maximum depth of nested application calls.
+        /// (This will overwrite value from `--policy-config`)
+        #[arg(long)]
+        maximum_call_depth: Option<u64>,
+
         /// Set the maximum amount of time allowed to wait for an HTTP response.
         #[arg(long)]
         http_request_timeout_ms: Option<u64>,
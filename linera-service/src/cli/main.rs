@@ -632,18 +632,34 @@ impl Runnable for Job {
                                     write_operation,
                                     byte_runtime,
                                     byte_read,
+                                    read_charge_granularity,
                                     byte_written,
+                                    byte_deserialized,
                                     blob_read,
+                                    blob_read_fixed,
                                     blob_published,
+                                    blob_published_fixed,
                                     blob_byte_read,
                                     blob_byte_published,
+                                    blob_byte_deposit,
+                                    blob_bytecode_multiplier,
+                                    blob_description_multiplier,
                                     byte_stored,
+                                    storage_rent,
                                     operation,
+                                    base_transaction_fee,
+                                    fee_increment,
                                     operation_byte,
+                                    price_operations_by_serialized_size,
+                                    minimum_operation_fee,
+                                    warmup_operations,
                                     message,
                                     message_byte,
+                                    message_hop,
                                     service_as_oracle_query,
                                     http_request,
+                                    http_response_byte,
+                                    http_request_byte,
                                     maximum_wasm_fuel_per_block,
                                     maximum_evm_fuel_per_block,
                                     maximum_service_oracle_execution_ms,
@@ -654,8 +670,15 @@ impl Runnable for Job {
                                     maximum_block_proposal_size,
                                     maximum_bytes_read_per_block,
                                     maximum_bytes_written_per_block,
+                                    maximum_bytes_deserialized_per_block,
+                                    maximum_blob_bytes_read_per_block,
+                                    maximum_blob_bytes_published_per_block,
+                                    maximum_bytes_runtime_per_block,
                                     maximum_oracle_response_bytes,
                                     maximum_http_response_bytes,
+                                    maximum_http_request_bytes_per_block,
+                                    maximum_failed_charge_attempts,
+                                    maximum_call_depth,
                                     http_request_timeout_ms,
                                     http_request_allow_list,
                                 } => {
@@ -672,27 +695,67 @@ impl Runnable for Job {
                                         byte_runtime: byte_runtime
                                             .unwrap_or(existing_policy.byte_runtime),
                                         byte_read: byte_read.unwrap_or(existing_policy.byte_read),
+                                        read_charge_granularity: read_charge_granularity
+                                            .unwrap_or(existing_policy.read_charge_granularity),
                                         byte_written: byte_written
                                             .unwrap_or(existing_policy.byte_written),
+                                        byte_deserialized: byte_deserialized
+                                            .unwrap_or(existing_policy.byte_deserialized),
                                         blob_read: blob_read.unwrap_or(existing_policy.blob_read),
+                                        blob_read_fixed: blob_read_fixed
+                                            .unwrap_or(existing_policy.blob_read_fixed),
                                         blob_published: blob_published
                                             .unwrap_or(existing_policy.blob_published),
+                                        blob_published_fixed: blob_published_fixed
+                                            .unwrap_or(existing_policy.blob_published_fixed),
                                         blob_byte_read: blob_byte_read
                                             .unwrap_or(existing_policy.blob_byte_read),
                                         blob_byte_published: blob_byte_published
                                             .unwrap_or(existing_policy.blob_byte_published),
+                                        blob_byte_deposit: blob_byte_deposit
+                                            .unwrap_or(existing_policy.blob_byte_deposit),
+                                        blob_bytecode_multiplier: blob_bytecode_multiplier
+                                            .unwrap_or(existing_policy.blob_bytecode_multiplier),
+                                        blob_description_multiplier: blob_description_multiplier
+                                            .unwrap_or(existing_policy.blob_description_multiplier),
                                         byte_stored: byte_stored
                                             .unwrap_or(existing_policy.byte_stored),
+                                        storage_rent: storage_rent
+                                            .unwrap_or(existing_policy.storage_rent),
+                                        storage_refund_target: existing_policy
+                                            .storage_refund_target,
                                         operation: operation.unwrap_or(existing_policy.operation),
+                                        operation_price_overrides: existing_policy
+                                            .operation_price_overrides,
+                                        base_transaction_fee: base_transaction_fee
+                                            .unwrap_or(existing_policy.base_transaction_fee),
+                                        fee_increment: fee_increment
+                                            .unwrap_or(existing_policy.fee_increment),
                                         operation_byte: operation_byte
                                             .unwrap_or(existing_policy.operation_byte),
+                                        price_operations_by_serialized_size:
+                                            price_operations_by_serialized_size.unwrap_or(
+                                                existing_policy.price_operations_by_serialized_size,
+                                            ),
+                                        minimum_operation_fee: minimum_operation_fee
+                                            .unwrap_or(existing_policy.minimum_operation_fee),
+                                        warmup_operations: warmup_operations
+                                            .unwrap_or(existing_policy.warmup_operations),
                                         message: message.unwrap_or(existing_policy.message),
                                         message_byte: message_byte
                                             .unwrap_or(existing_policy.message_byte),
+                                        message_hop: message_hop
+                                            .unwrap_or(existing_policy.message_hop),
+                                        grant_denomination: existing_policy.grant_denomination,
+                                        grant_exchange_rate: existing_policy.grant_exchange_rate,
                                         service_as_oracle_query: service_as_oracle_query
                                             .unwrap_or(existing_policy.service_as_oracle_query),
                                         http_request: http_request
                                             .unwrap_or(existing_policy.http_request),
+                                        http_response_byte: http_response_byte
+                                            .unwrap_or(existing_policy.http_response_byte),
+                                        http_request_byte: http_request_byte
+                                            .unwrap_or(existing_policy.http_request_byte),
                                         maximum_wasm_fuel_per_block: maximum_wasm_fuel_per_block
                                             .unwrap_or(existing_policy.maximum_wasm_fuel_per_block),
                                         maximum_evm_fuel_per_block: maximum_evm_fuel_per_block
@@ -701,6 +764,8 @@ impl Runnable for Job {
                                             maximum_service_oracle_execution_ms.unwrap_or(
                                                 existing_policy.maximum_service_oracle_execution_ms,
                                             ),
+                                        oracle_timeout_policy: existing_policy
+                                            .oracle_timeout_policy,
                                         maximum_block_size: maximum_block_size
                                             .unwrap_or(existing_policy.maximum_block_size),
                                         maximum_bytecode_size: maximum_bytecode_size
@@ -719,19 +784,54 @@ impl Runnable for Job {
                                             maximum_bytes_written_per_block.unwrap_or(
                                                 existing_policy.maximum_bytes_written_per_block,
                                             ),
+                                        maximum_bytes_deserialized_per_block:
+                                            maximum_bytes_deserialized_per_block.unwrap_or(
+                                                existing_policy
+                                                    .maximum_bytes_deserialized_per_block,
+                                            ),
+                                        maximum_blob_bytes_read_per_block:
+                                            maximum_blob_bytes_read_per_block.unwrap_or(
+                                                existing_policy.maximum_blob_bytes_read_per_block,
+                                            ),
+                                        maximum_blob_bytes_published_per_block:
+                                            maximum_blob_bytes_published_per_block.unwrap_or(
+                                                existing_policy
+                                                    .maximum_blob_bytes_published_per_block,
+                                            ),
+                                        maximum_bytes_runtime_per_block:
+                                            maximum_bytes_runtime_per_block.unwrap_or(
+                                                existing_policy.maximum_bytes_runtime_per_block,
+                                            ),
                                         maximum_oracle_response_bytes:
                                             maximum_oracle_response_bytes.unwrap_or(
                                                 existing_policy.maximum_oracle_response_bytes,
                                             ),
                                         maximum_http_response_bytes: maximum_http_response_bytes
                                             .unwrap_or(existing_policy.maximum_http_response_bytes),
+                                        maximum_http_request_bytes_per_block:
+                                            maximum_http_request_bytes_per_block.unwrap_or(
+                                                existing_policy
+                                                    .maximum_http_request_bytes_per_block,
+                                            ),
+                                        maximum_failed_charge_attempts:
+                                            maximum_failed_charge_attempts.unwrap_or(
+                                                existing_policy.maximum_failed_charge_attempts,
+                                            ),
+                                        maximum_call_depth: maximum_call_depth
+                                            .unwrap_or(existing_policy.maximum_call_depth),
                                         http_request_timeout_ms: http_request_timeout_ms
                                             .unwrap_or(existing_policy.http_request_timeout_ms),
                                         http_request_allow_list: http_request_allow_list
                                             .map(BTreeSet::from_iter)
                                             .unwrap_or(existing_policy.http_request_allow_list),
+                                        fee_exempt_owners: existing_policy.fee_exempt_owners,
+                                        uncharged_resources: existing_policy.uncharged_resources,
                                     };
                                     info!("{policy}");
+                                    if let Err(error) = policy.validate() {
+                                        warn!("Skipping invalid resource control policy: {error}");
+                                        return Ok(ClientOutcome::Committed(None));
+                                    }
                                     if committee.policy() == &policy {
                                         return Ok(ClientOutcome::Committed(None));
                                     }
@@ -1696,18 +1796,34 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             write_operation_price,
             byte_runtime_price,
             byte_read_price,
+            read_charge_granularity,
             byte_written_price,
+            byte_deserialized_price,
             byte_stored_price,
+            storage_rent_price,
             blob_read_price,
+            blob_read_fixed_price,
             blob_published_price,
+            blob_published_fixed_price,
             blob_byte_read_price,
             blob_byte_published_price,
+            blob_byte_deposit_price,
+            blob_bytecode_multiplier,
+            blob_description_multiplier,
             operation_price,
+            base_transaction_fee_price,
+            fee_increment_price,
             operation_byte_price,
+            price_operations_by_serialized_size,
+            minimum_operation_fee_price,
+            warmup_operations,
             message_price,
             message_byte_price,
+            message_hop_price,
             service_as_oracle_query_price,
             http_request_price,
+            http_response_byte_price,
+            http_request_byte_price,
             maximum_wasm_fuel_per_block,
             maximum_evm_fuel_per_block,
             maximum_service_oracle_execution_ms,
@@ -1718,8 +1834,15 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
             maximum_block_proposal_size,
             maximum_bytes_read_per_block,
             maximum_bytes_written_per_block,
+            maximum_bytes_deserialized_per_block,
+            maximum_blob_bytes_read_per_block,
+            maximum_blob_bytes_published_per_block,
+            maximum_bytes_runtime_per_block,
             maximum_oracle_response_bytes,
             maximum_http_response_bytes,
+            maximum_http_request_bytes_per_block,
+            maximum_failed_charge_attempts,
+            maximum_call_depth,
             http_request_timeout_ms,
             http_request_allow_list,
             testing_prng_seed,
@@ -1736,26 +1859,58 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                 write_operation: write_operation_price.unwrap_or(existing_policy.write_operation),
                 byte_runtime: byte_runtime_price.unwrap_or(existing_policy.byte_runtime),
                 byte_read: byte_read_price.unwrap_or(existing_policy.byte_read),
+                read_charge_granularity: read_charge_granularity
+                    .unwrap_or(existing_policy.read_charge_granularity),
                 byte_written: byte_written_price.unwrap_or(existing_policy.byte_written),
+                byte_deserialized: byte_deserialized_price
+                    .unwrap_or(existing_policy.byte_deserialized),
                 blob_read: blob_read_price.unwrap_or(existing_policy.blob_read),
+                blob_read_fixed: blob_read_fixed_price.unwrap_or(existing_policy.blob_read_fixed),
                 blob_published: blob_published_price.unwrap_or(existing_policy.blob_published),
+                blob_published_fixed: blob_published_fixed_price
+                    .unwrap_or(existing_policy.blob_published_fixed),
                 blob_byte_read: blob_byte_read_price.unwrap_or(existing_policy.blob_byte_read),
                 blob_byte_published: blob_byte_published_price
                     .unwrap_or(existing_policy.blob_byte_published),
+                blob_byte_deposit: blob_byte_deposit_price
+                    .unwrap_or(existing_policy.blob_byte_deposit),
+                blob_bytecode_multiplier: blob_bytecode_multiplier
+                    .unwrap_or(existing_policy.blob_bytecode_multiplier),
+                blob_description_multiplier: blob_description_multiplier
+                    .unwrap_or(existing_policy.blob_description_multiplier),
                 byte_stored: byte_stored_price.unwrap_or(existing_policy.byte_stored),
+                storage_rent: storage_rent_price.unwrap_or(existing_policy.storage_rent),
+                storage_refund_target: existing_policy.storage_refund_target,
                 operation: operation_price.unwrap_or(existing_policy.operation),
+                operation_price_overrides: existing_policy.operation_price_overrides,
+                base_transaction_fee: base_transaction_fee_price
+                    .unwrap_or(existing_policy.base_transaction_fee),
+                fee_increment: fee_increment_price.unwrap_or(existing_policy.fee_increment),
                 operation_byte: operation_byte_price.unwrap_or(existing_policy.operation_byte),
+                price_operations_by_serialized_size: price_operations_by_serialized_size
+                    .unwrap_or(existing_policy.price_operations_by_serialized_size),
+                minimum_operation_fee: minimum_operation_fee_price
+                    .unwrap_or(existing_policy.minimum_operation_fee),
+                warmup_operations: warmup_operations.unwrap_or(existing_policy.warmup_operations),
                 message: message_price.unwrap_or(existing_policy.message),
                 message_byte: message_byte_price.unwrap_or(existing_policy.message_byte),
+                message_hop: message_hop_price.unwrap_or(existing_policy.message_hop),
+                grant_denomination: existing_policy.grant_denomination,
+                grant_exchange_rate: existing_policy.grant_exchange_rate,
                 service_as_oracle_query: service_as_oracle_query_price
                     .unwrap_or(existing_policy.service_as_oracle_query),
                 http_request: http_request_price.unwrap_or(existing_policy.http_request),
+                http_response_byte: http_response_byte_price
+                    .unwrap_or(existing_policy.http_response_byte),
+                http_request_byte: http_request_byte_price
+                    .unwrap_or(existing_policy.http_request_byte),
                 maximum_wasm_fuel_per_block: maximum_wasm_fuel_per_block
                     .unwrap_or(existing_policy.maximum_wasm_fuel_per_block),
                 maximum_evm_fuel_per_block: maximum_evm_fuel_per_block
                     .unwrap_or(existing_policy.maximum_evm_fuel_per_block),
                 maximum_service_oracle_execution_ms: maximum_service_oracle_execution_ms
                     .unwrap_or(existing_policy.maximum_service_oracle_execution_ms),
+                oracle_timeout_policy: existing_policy.oracle_timeout_policy,
                 maximum_block_size: maximum_block_size
                     .unwrap_or(existing_policy.maximum_block_size),
                 maximum_bytecode_size: maximum_bytecode_size
@@ -1769,17 +1924,36 @@ async fn run(options: &ClientOptions) -> Result<i32, Error> {
                     .unwrap_or(existing_policy.maximum_bytes_read_per_block),
                 maximum_bytes_written_per_block: maximum_bytes_written_per_block
                     .unwrap_or(existing_policy.maximum_bytes_written_per_block),
+                maximum_bytes_deserialized_per_block: maximum_bytes_deserialized_per_block
+                    .unwrap_or(existing_policy.maximum_bytes_deserialized_per_block),
+                maximum_blob_bytes_read_per_block: maximum_blob_bytes_read_per_block
+                    .unwrap_or(existing_policy.maximum_blob_bytes_read_per_block),
+                maximum_blob_bytes_published_per_block: maximum_blob_bytes_published_per_block
+                    .unwrap_or(existing_policy.maximum_blob_bytes_published_per_block),
+                maximum_bytes_runtime_per_block: maximum_bytes_runtime_per_block
+                    .unwrap_or(existing_policy.maximum_bytes_runtime_per_block),
                 maximum_oracle_response_bytes: maximum_oracle_response_bytes
                     .unwrap_or(existing_policy.maximum_oracle_response_bytes),
                 maximum_http_response_bytes: maximum_http_response_bytes
                     .unwrap_or(existing_policy.maximum_http_response_bytes),
+                maximum_http_request_bytes_per_block: maximum_http_request_bytes_per_block
+                    .unwrap_or(existing_policy.maximum_http_request_bytes_per_block),
+                maximum_failed_charge_attempts: maximum_failed_charge_attempts
+                    .unwrap_or(existing_policy.maximum_failed_charge_attempts),
+                maximum_call_depth: maximum_call_depth
+                    .unwrap_or(existing_policy.maximum_call_depth),
                 http_request_timeout_ms: http_request_timeout_ms
                     .unwrap_or(existing_policy.http_request_timeout_ms),
                 http_request_allow_list: http_request_allow_list
                     .as_ref()
                     .map(|list| list.iter().cloned().collect())
                     .unwrap_or(existing_policy.http_request_allow_list),
+                fee_exempt_owners: existing_policy.fee_exempt_owners,
+                uncharged_resources: existing_policy.uncharged_resources,
             };
+            policy
+                .validate()
+                .context("Invalid resource control policy")?;
             let timestamp = start_timestamp
                 .map(|st| {
                     let micros =